@@ -0,0 +1,217 @@
+use crate::*;
+use std::cell::RefCell;
+use std::mem::MaybeUninit;
+
+const PAGE_LEN: usize = 1024;
+const INITIAL_THRESHOLD: usize = PAGE_LEN * 16;
+
+thread_local!(
+    /// The allocator for the current thread.
+    /// A thread which runs a VM shares the allocator of the thread which created the VM.
+    pub static ALLOC: RefCell<Option<AllocatorRef>> = RefCell::new(None);
+);
+
+/// Objects which hold Values to be traced by GC.
+pub trait GC {
+    fn mark(&self, alloc: &mut Allocator);
+}
+
+/// Heap slot for an RValue.
+/// `inner` must be the first field, so that a pointer to RValue is also a pointer to GCBox.
+#[repr(C)]
+struct GCBox {
+    inner: MaybeUninit<RValue>,
+    next: *mut GCBox,
+    alive: bool,
+    marked: bool,
+}
+
+impl GCBox {
+    fn new() -> Self {
+        GCBox {
+            inner: MaybeUninit::uninit(),
+            next: std::ptr::null_mut(),
+            alive: false,
+            marked: false,
+        }
+    }
+}
+
+/// Mark-and-sweep garbage collector for RValue.
+/// Objects are allocated in arena pages, and freed slots are reused via the free list.
+pub struct Allocator {
+    pages: Vec<Box<[GCBox]>>,
+    free_list: *mut GCBox,
+    /// Objects which were marked but whose children are not marked yet.
+    mark_stack: Vec<*mut GCBox>,
+    /// Number of live objects.
+    live: usize,
+    /// Number of objects allocated since the last GC.
+    allocated: usize,
+    /// GC is performed when `allocated` exceeds `threshold`.
+    threshold: usize,
+    /// Number of GC performed.
+    count: usize,
+    total_allocated: usize,
+    total_freed: usize,
+    /// GC is disabled by `GC.disable`.
+    disabled: bool,
+}
+
+pub type AllocatorRef = Ref<Allocator>;
+
+impl std::fmt::Debug for Allocator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Allocator {{ live: {}, count: {} }}",
+            self.live, self.count
+        )
+    }
+}
+
+impl Allocator {
+    /// Create a new allocator, and set it as the allocator for the current thread.
+    pub fn init() -> AllocatorRef {
+        let alloc = AllocatorRef::new(Allocator {
+            pages: vec![],
+            free_list: std::ptr::null_mut(),
+            mark_stack: vec![],
+            live: 0,
+            allocated: 0,
+            threshold: INITIAL_THRESHOLD,
+            count: 0,
+            total_allocated: 0,
+            total_freed: 0,
+            disabled: false,
+        });
+        Allocator::set_current(alloc);
+        alloc
+    }
+
+    /// Set `alloc` as the allocator for the current thread.
+    pub fn set_current(alloc: AllocatorRef) {
+        ALLOC.with(|a| *a.borrow_mut() = Some(alloc));
+    }
+
+    /// Allocate `data` on the heap of the allocator for the current thread.
+    pub fn alloc_current(data: RValue) -> *mut RValue {
+        let alloc = ALLOC.with(|a| *a.borrow());
+        let mut alloc = match alloc {
+            Some(alloc) => alloc,
+            None => Allocator::init(),
+        };
+        alloc.alloc(data)
+    }
+
+    fn alloc(&mut self, data: RValue) -> *mut RValue {
+        if self.free_list.is_null() {
+            self.add_page();
+        }
+        let gcbox = unsafe { &mut *self.free_list };
+        self.free_list = gcbox.next;
+        gcbox.next = std::ptr::null_mut();
+        gcbox.alive = true;
+        gcbox.marked = false;
+        gcbox.inner = MaybeUninit::new(data);
+        self.live += 1;
+        self.allocated += 1;
+        self.total_allocated += 1;
+        gcbox.inner.as_mut_ptr()
+    }
+
+    fn add_page(&mut self) {
+        let mut page: Box<[GCBox]> = (0..PAGE_LEN).map(|_| GCBox::new()).collect();
+        for gcbox in page.iter_mut().rev() {
+            gcbox.next = self.free_list;
+            self.free_list = gcbox;
+        }
+        self.pages.push(page);
+    }
+
+    pub fn is_gc_needed(&self) -> bool {
+        !self.disabled && self.allocated >= self.threshold
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    /// Disable or enable GC, and return the previous state.
+    pub fn set_disabled(&mut self, disabled: bool) -> bool {
+        std::mem::replace(&mut self.disabled, disabled)
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Get statistics of the heap. (name, value)
+    pub fn stat(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("count", self.count),
+            ("heap_allocated_pages", self.pages.len()),
+            ("heap_live_slots", self.live),
+            ("heap_free_slots", self.pages.len() * PAGE_LEN - self.live),
+            ("total_allocated_objects", self.total_allocated),
+            ("total_freed_objects", self.total_freed),
+        ]
+    }
+
+    /// Mark the object `obj` as reachable.
+    pub fn mark(&mut self, obj: ObjectRef) {
+        let gcbox = obj.id() as *mut GCBox;
+        let gcbox_ref = unsafe { &mut *gcbox };
+        if !gcbox_ref.marked {
+            gcbox_ref.marked = true;
+            self.mark_stack.push(gcbox);
+        }
+    }
+
+    /// Examine whether `obj` survived the last GC.
+    /// The result is valid only until the next allocation, which may reuse the slot.
+    pub fn is_alive(&self, obj: ObjectRef) -> bool {
+        let gcbox = obj.id() as *const GCBox;
+        unsafe { (*gcbox).alive }
+    }
+
+    /// Perform GC. All objects which are not reachable from `root` are freed.
+    pub fn gc(&mut self, root: &dyn GC) {
+        root.mark(self);
+        while let Some(gcbox) = self.mark_stack.pop() {
+            let obj = unsafe { &*(*gcbox).inner.as_ptr() };
+            obj.mark(self);
+        }
+        self.sweep();
+        self.count += 1;
+        self.allocated = 0;
+        self.threshold = std::cmp::max(INITIAL_THRESHOLD, self.live);
+    }
+
+    fn sweep(&mut self) {
+        let mut free_list = self.free_list;
+        let mut freed = 0;
+        for page in self.pages.iter_mut() {
+            for gcbox in page.iter_mut() {
+                if !gcbox.alive {
+                    continue;
+                }
+                if gcbox.marked {
+                    gcbox.marked = false;
+                    continue;
+                }
+                unsafe {
+                    (*gcbox.inner.as_mut_ptr()).free();
+                    std::ptr::drop_in_place(gcbox.inner.as_mut_ptr());
+                }
+                gcbox.alive = false;
+                gcbox.next = free_list;
+                free_list = gcbox;
+                freed += 1;
+            }
+        }
+        self.free_list = free_list;
+        self.live -= freed;
+        self.total_freed += freed;
+    }
+}
@@ -1,10 +1,13 @@
 pub mod array;
 pub mod class;
+pub mod comparable;
+pub mod enumerable;
 pub mod enumerator;
 pub mod errorobj;
 pub mod fiber;
 pub mod file;
 pub mod float;
+pub mod gc;
 pub mod hash;
 pub mod integer;
 pub mod kernel;
@@ -18,3 +21,4 @@ pub mod range;
 pub mod regexp;
 pub mod string;
 pub mod structobj;
+pub mod symbol;
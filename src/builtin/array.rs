@@ -1,5 +1,5 @@
-use crate::error::RubyError;
 use crate::*;
+use std::cmp::Ordering;
 
 pub fn init_array(globals: &mut Globals) -> Value {
     let array_id = globals.get_ident_id("Array");
@@ -258,7 +258,8 @@ fn map(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
         Some(method) => method,
         None => {
             let id = vm.globals.get_ident_id("map");
-            let val = Value::enumerator(&vm.globals, id, self_val, args.clone());
+            let size = EnumSize::Func(receiver_size);
+            let val = Value::enumerator_with_size(&vm.globals, id, self_val, args.clone(), size);
             return Ok(val);
         }
     };
@@ -269,6 +270,7 @@ fn map(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     for elem in &aref.elements {
         args[0] = *elem;
         let val = vm.eval_block(method, &args)?;
+        vm.temp_push(val);
         res.push(val);
     }
 
@@ -280,24 +282,11 @@ fn flat_map(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let aref = vm.expect_array(self_val, "Receiver")?;
     let method = vm.expect_block(args.block)?;
     let mut res = vec![];
-    let param_num = vm.get_iseq(method)?.params.req_params;
-    let mut arg = Args::new(param_num);
+    let mut arg = Args::new1(Value::nil());
     for elem in &aref.elements {
-        if param_num == 0 {
-        } else if param_num == 1 {
-            arg[0] = *elem;
-        } else {
-            match elem.as_array() {
-                Some(ary) => {
-                    for i in 0..param_num {
-                        arg[i] = ary.elements[i];
-                    }
-                }
-                None => arg[0] = *elem,
-            }
-        }
-
+        arg[0] = *elem;
         let ary = vm.eval_block(method, &arg)?;
+        vm.temp_push(ary);
         match ary.as_array() {
             Some(mut ary) => {
                 res.append(&mut ary.elements);
@@ -318,12 +307,18 @@ fn each(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
         Some(method) => method,
         None => {
             let id = vm.globals.get_ident_id("each");
-            let val = Value::enumerator(&vm.globals, id, self_val, args.clone());
+            let size = EnumSize::Func(receiver_size);
+            let val = Value::enumerator_with_size(&vm.globals, id, self_val, args.clone(), size);
             return Ok(val);
         }
     };
 
-    let mut arg = Args::new(vm.get_iseq(method)?.params.req_params);
+    // A builtin block takes an element as it is.
+    let param_num = match vm.globals.get_method_info(method) {
+        MethodInfo::RubyFunc { iseq } => iseq.params.req_params,
+        _ => 1,
+    };
+    let mut arg = Args::new(param_num);
     for i in &aref.elements {
         match i.as_array() {
             Some(aref) if arg.len() != 1 => {
@@ -438,32 +433,19 @@ fn transpose(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     Ok(res)
 }
 
-fn min(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
-    fn to_float(vm: &VM, val: Value) -> Result<f64, RubyError> {
-        if val.is_packed_fixnum() {
-            Ok(val.as_packed_fixnum() as f64)
-        } else if val.is_packed_num() {
-            Ok(val.as_packed_flonum())
-        } else {
-            Err(vm.error_type("Currently, each element must be Numeric."))
-        }
-    }
-
+fn min(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
     let aref = vm.expect_array(self_val, "Receiver")?;
     if aref.elements.len() == 0 {
         return Ok(Value::nil());
     }
-    let mut min_obj = aref.elements[0];
-    let mut min = to_float(vm, min_obj)?;
+    let mut min = aref.elements[0];
     for elem in &aref.elements {
-        let elem_f = to_float(vm, *elem)?;
-        if elem_f < min {
-            min_obj = *elem;
-            min = elem_f;
-        }
+        if vm.compare_values(*elem, min)? == Ordering::Less {
+            min = *elem;
+        };
     }
-
-    return Ok(min_obj);
+    Ok(min)
 }
 
 fn max(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
@@ -474,7 +456,7 @@ fn max(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     }
     let mut max = aref.elements[0];
     for elem in &aref.elements {
-        if vm.eval_gt(max, *elem)? == Value::true_val() {
+        if vm.compare_values(*elem, max)? == Ordering::Greater {
             max = *elem;
         };
     }
@@ -503,7 +485,7 @@ fn uniq_(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let mut set = std::collections::HashSet::new();
     match args.block {
         None => {
-            aref.elements.retain(|x| set.insert(HashKey(*x)));
+            HashInfo::catch_key_error(|| aref.elements.retain(|x| set.insert(HashKey(*x))))?;
             Ok(self_val)
         }
         Some(block) => {
@@ -511,6 +493,7 @@ fn uniq_(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
             aref.elements.retain(|x| {
                 block_args[0] = *x;
                 let res = vm.eval_block(block, &block_args).unwrap();
+                vm.temp_push(res);
                 set.insert(HashKey(res))
             });
             Ok(self_val)
@@ -623,6 +606,7 @@ fn zip(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
             }
         }
         let zip = Value::array_from(&vm.globals, vec);
+        vm.temp_push(zip);
         ary.push(zip);
     }
     match args.block {
@@ -656,14 +640,14 @@ fn grep(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
 }
 
 fn sort(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
-    //use std::cmp::Ordering;
     vm.check_args_num(args.len(), 0)?;
-    let aref = vm.expect_array(self_val, "Receiver")?.dup();
+    let mut aref = vm.expect_array(self_val, "Receiver")?.dup();
     match args.block {
-        None => {
-            vm.sort_array(aref)?;
-        }
-        Some(_block) => return Err(vm.error_argument("Currently, can not use block.")),
+        None => vm.sort_array(aref)?,
+        Some(block) => vm.sort_values(&mut aref.elements, |vm, a, b| {
+            let res = vm.eval_block(block, &Args::new2(a, b))?;
+            Ok(res.expect_integer(vm, "Result of the block")?.cmp(&0))
+        })?,
     };
     Ok(Value::array(&vm.globals, aref))
 }
@@ -676,11 +660,13 @@ fn uniq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let mut v = vec![];
     match args.block {
         None => {
-            for elem in &aref.elements {
-                if h.insert(HashKey(*elem)) {
-                    v.push(*elem);
-                };
-            }
+            HashInfo::catch_key_error(|| {
+                for elem in &aref.elements {
+                    if h.insert(HashKey(*elem)) {
+                        v.push(*elem);
+                    };
+                }
+            })?;
         }
         Some(_block) => return Err(vm.error_argument("Currently, can not use block.")),
     };
@@ -5,6 +5,7 @@ pub fn init(globals: &mut Globals) {
     globals.add_builtin_instance_method(class, "new", new);
     globals.add_builtin_instance_method(class, "superclass", superclass);
     globals.add_builtin_instance_method(class, "inspect", inspect);
+    globals.add_builtin_instance_method(class, "inherited", inherited);
     globals.add_builtin_class_method(globals.builtins.class, "new", class_new);
 }
 
@@ -18,9 +19,12 @@ fn class_new(vm: &mut VM, _: Value, args: &Args) -> VMResult {
     let superclass = if args.len() == 0 {
         vm.globals.builtins.object
     } else {
+        vm.expect_class(args[0], "Superclass")?;
         args[0]
     };
     let val = Value::class_from(&mut vm.globals, None, superclass);
+    vm.temp_push(val);
+    vm.call_hook(superclass, "inherited", val)?;
 
     match args.block {
         Some(method) => {
@@ -36,11 +40,15 @@ fn class_new(vm: &mut VM, _: Value, args: &Args) -> VMResult {
 
 /// Create new instance of `self`.
 pub fn new(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
-    let new_instance = Value::ordinary_object(self_val);
-    // Call initialize method if it exists.
-    if let Some(method) = self_val.get_instance_method(IdentId::INITIALIZE) {
-        vm.eval_send(method, new_instance, args)?;
+    let new_instance = if builtin::errorobj::is_exception_class(&vm.globals, self_val) {
+        Value::exception(self_val, Value::nil())
+    } else {
+        Value::ordinary_object(self_val)
     };
+    vm.temp_push(new_instance);
+    // Call initialize method.
+    let method = vm.get_instance_method(self_val, IdentId::INITIALIZE)?;
+    vm.eval_send(method, new_instance, args)?;
     Ok(new_instance)
 }
 
@@ -50,6 +58,13 @@ fn superclass(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
     Ok(class.superclass)
 }
 
+/// inherited(subclass)
+/// A hook called when a subclass of `self` is created. Does nothing by default.
+fn inherited(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    Ok(Value::nil())
+}
+
 fn inspect(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
     let cref = vm.expect_class(self_val, "Receiver")?;
     let s = match cref.name {
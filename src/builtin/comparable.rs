@@ -0,0 +1,145 @@
+use crate::*;
+use std::cmp::Ordering;
+
+pub fn init_comparable(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("Comparable");
+    let class = ClassRef::from(id, None);
+    globals.add_builtin_instance_method(class, "==", eq);
+    globals.add_builtin_instance_method(class, "<", lt);
+    globals.add_builtin_instance_method(class, "<=", le);
+    globals.add_builtin_instance_method(class, ">", gt);
+    globals.add_builtin_instance_method(class, ">=", ge);
+    globals.add_builtin_instance_method(class, "between?", between);
+    globals.add_builtin_instance_method(class, "clamp", clamp);
+    Value::module(globals, class)
+}
+
+/// Compare `self_val` with the argument by `<=>`, and examine the result with `f`.
+fn compare_with(
+    vm: &mut VM,
+    self_val: Value,
+    args: &Args,
+    f: impl Fn(Ordering) -> bool,
+) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let ord = vm.compare_values(self_val, args[0])?;
+    Ok(Value::bool(f(ord)))
+}
+
+/// Return true if `<=>` returns 0. Return false if the objects are not comparable.
+fn eq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    if self_val.id() == args[0].id() {
+        return Ok(Value::true_val());
+    }
+    let res = vm.eval_cmp(args[0], self_val)?;
+    Ok(Value::bool(res.as_fixnum() == Some(0)))
+}
+
+fn lt(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    compare_with(vm, self_val, args, |ord| ord == Ordering::Less)
+}
+
+fn le(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    compare_with(vm, self_val, args, |ord| ord != Ordering::Greater)
+}
+
+fn gt(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    compare_with(vm, self_val, args, |ord| ord == Ordering::Greater)
+}
+
+fn ge(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    compare_with(vm, self_val, args, |ord| ord != Ordering::Less)
+}
+
+fn between(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 2)?;
+    let res = vm.compare_values(self_val, args[0])? != Ordering::Less
+        && vm.compare_values(self_val, args[1])? != Ordering::Greater;
+    Ok(Value::bool(res))
+}
+
+/// clamp(min, max) / clamp(range)
+/// Return `min` if self < `min`, `max` if self > `max`, otherwise self.
+fn clamp(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let (min, max) = if args.len() == 2 {
+        (args[0], args[1])
+    } else {
+        match args[0].as_range() {
+            Some(range) if range.exclude => {
+                return Err(vm.error_argument("Cannot clamp with an exclusive range."))
+            }
+            Some(range) => (range.start, range.end),
+            None => {
+                let inspect = vm.val_inspect(args[0]);
+                return Err(
+                    vm.error_type(format!("Wrong argument type {} (expected Range).", inspect))
+                );
+            }
+        }
+    };
+    if vm.compare_values(min, max)? == Ordering::Greater {
+        return Err(vm.error_argument("Min argument must be smaller than max argument."));
+    }
+    if vm.compare_values(self_val, min)? == Ordering::Less {
+        Ok(min)
+    } else if vm.compare_values(self_val, max)? == Ordering::Greater {
+        Ok(max)
+    } else {
+        Ok(self_val)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn comparable() {
+        let program = r#"
+        class Version
+          include Comparable
+          attr_reader :major, :minor
+          def initialize(major, minor)
+            @major = major
+            @minor = minor
+          end
+          def <=>(other)
+            return nil unless other.is_a?(Version)
+            [major, minor] <=> [other.major, other.minor]
+          end
+        end
+        v1 = Version.new(1, 2)
+        v2 = Version.new(1, 10)
+        v3 = Version.new(2, 0)
+        assert(true, v1.is_a?(Comparable))
+        assert(true, v1 < v2)
+        assert(true, v1 <= v2)
+        assert(false, v1 > v2)
+        assert(true, v3 >= v2)
+        assert(true, v1 == Version.new(1, 2))
+        assert(false, v1 != Version.new(1, 2))
+        assert(false, v1 == 3)
+        assert(true, v2.between?(v1, v3))
+        assert(false, v3.between?(v1, v2))
+        assert(v2, v3.clamp(v1, v2))
+        assert(v1, Version.new(0, 1).clamp(v1, v3))
+        assert([v1, v2, v3], [v3, v1, v2].sort)
+        assert([v3, v2, v1], [v3, v1, v2].sort { |a, b| b <=> a })
+        assert(v1, [v3, v1, v2].min)
+        assert(v3, [v1, v3, v2].max)
+        assert(5, 7.clamp(1, 5))
+        assert(1, -3.clamp(1..5))
+        assert(3, 3.clamp(1..5))
+        assert(true, 3.between?(1, 5))
+        e = begin
+          v1 < 3
+        rescue ArgumentError => e
+          e
+        end
+        assert("Comparison of Version with Integer failed.", e.message)
+        "#;
+        assert_script(program);
+    }
+}
@@ -0,0 +1,925 @@
+use crate::*;
+use std::cmp::Ordering;
+
+pub fn init_enumerable(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("Enumerable");
+    let class = ClassRef::from(id, None);
+    globals.add_builtin_instance_method(class, "to_a", to_a);
+    globals.add_builtin_instance_method(class, "entries", to_a);
+    globals.add_builtin_instance_method(class, "to_h", to_h);
+    globals.add_builtin_instance_method(class, "map", map);
+    globals.add_builtin_instance_method(class, "collect", map);
+    globals.add_builtin_instance_method(class, "flat_map", flat_map);
+    globals.add_builtin_instance_method(class, "collect_concat", flat_map);
+    globals.add_builtin_instance_method(class, "select", select);
+    globals.add_builtin_instance_method(class, "filter", select);
+    globals.add_builtin_instance_method(class, "reject", reject);
+    globals.add_builtin_instance_method(class, "filter_map", filter_map);
+    globals.add_builtin_instance_method(class, "partition", partition);
+    globals.add_builtin_instance_method(class, "group_by", group_by);
+    globals.add_builtin_instance_method(class, "tally", tally);
+    globals.add_builtin_instance_method(class, "find", find);
+    globals.add_builtin_instance_method(class, "detect", find);
+    globals.add_builtin_instance_method(class, "find_index", find_index);
+    globals.add_builtin_instance_method(class, "all?", all);
+    globals.add_builtin_instance_method(class, "any?", any);
+    globals.add_builtin_instance_method(class, "none?", none);
+    globals.add_builtin_instance_method(class, "include?", include);
+    globals.add_builtin_instance_method(class, "member?", include);
+    globals.add_builtin_instance_method(class, "count", count);
+    globals.add_builtin_instance_method(class, "first", first);
+    globals.add_builtin_instance_method(class, "take", take);
+    globals.add_builtin_instance_method(class, "take_while", take_while);
+    globals.add_builtin_instance_method(class, "drop_while", drop_while);
+    globals.add_builtin_instance_method(class, "reduce", inject);
+    globals.add_builtin_instance_method(class, "inject", inject);
+    globals.add_builtin_instance_method(class, "sum", sum);
+    globals.add_builtin_instance_method(class, "min", min);
+    globals.add_builtin_instance_method(class, "max", max);
+    globals.add_builtin_instance_method(class, "min_by", min_by);
+    globals.add_builtin_instance_method(class, "max_by", max_by);
+    globals.add_builtin_instance_method(class, "sort", sort);
+    globals.add_builtin_instance_method(class, "sort_by", sort_by);
+    globals.add_builtin_instance_method(class, "uniq", uniq);
+    globals.add_builtin_instance_method(class, "each_with_index", each_with_index);
+    globals.add_builtin_instance_method(class, "each_with_object", each_with_object);
+    globals.add_builtin_instance_method(class, "each_slice", each_slice);
+    globals.add_builtin_instance_method(class, "each_cons", each_cons);
+    globals.add_builtin_instance_method(class, "zip", zip);
+    Value::module(globals, class)
+}
+
+/// Call `f` with each element which `each` of `self_val` yields, until `f` returns false.
+/// Elements yielded with multiple values are packed into an array.
+///
+/// `each` is called with a native block which calls `f`, and is exited like `break`
+/// when `f` returns false. Arrays and Ranges of Integer are iterated directly.
+fn each_elem(
+    vm: &mut VM,
+    self_val: Value,
+    mut f: impl FnMut(&mut VM, Value) -> Result<bool, RubyError>,
+) -> Result<(), RubyError> {
+    if let Some(aref) = self_val.as_array() {
+        for elem in aref.elements.clone() {
+            if !f(vm, elem)? {
+                break;
+            }
+        }
+        return Ok(());
+    }
+    if let Some(range) = self_val.as_range() {
+        if let (Some(start), Some(end)) = (range.start.as_fixnum(), range.end.as_fixnum()) {
+            let end = if range.exclude { end } else { end + 1 };
+            for i in start..end {
+                if !f(vm, Value::fixnum(i))? {
+                    break;
+                }
+            }
+            return Ok(());
+        }
+    }
+    let id = vm.globals.get_ident_id("each");
+    let mut f = |vm: &mut VM, val| Ok(f(vm, val)?.then(Value::nil));
+    vm.eval_with_native_block(self_val, id, &Args::new0(), &mut f)?;
+    Ok(())
+}
+
+/// Collect all elements of `self_val`.
+fn collect_elems(vm: &mut VM, self_val: Value) -> Result<Vec<Value>, RubyError> {
+    let mut v = vec![];
+    each_elem(vm, self_val, |vm, val| {
+        vm.temp_push(val);
+        v.push(val);
+        Ok(true)
+    })?;
+    Ok(v)
+}
+
+/// Return an Enumerator for `method` if no block is given.
+/// `size` is the size of the Enumerator, which is unknown if omitted.
+macro_rules! block_or_enum {
+    ($vm:ident, $self_val:ident, $args:ident, $method:expr) => {
+        block_or_enum!($vm, $self_val, $args, $method, EnumSize::Unknown)
+    };
+    ($vm:ident, $self_val:ident, $args:ident, $method:expr, $size:expr) => {
+        match $args.block {
+            Some(block) => block,
+            None => {
+                let id = $vm.globals.get_ident_id($method);
+                let args = $args.clone();
+                let e = Value::enumerator_with_size(&$vm.globals, id, $self_val, args, $size);
+                return Ok(e);
+            }
+        }
+    };
+}
+
+fn call_block(vm: &mut VM, block: MethodRef, val: Value) -> VMResult {
+    vm.eval_block(block, &Args::new1(val))
+}
+
+fn new_hash(vm: &mut VM, map: OrderedMap<HashKey>) -> Value {
+    Value::hash(&vm.globals, HashRef::from(map))
+}
+
+fn to_a(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let v = collect_elems(vm, self_val)?;
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+/// Convert pairs of [key, value] to a Hash. The block converts each element to a pair.
+fn to_h(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut map = OrderedMap::new();
+    each_elem(vm, self_val, |vm, val| {
+        let pair = match args.block {
+            Some(block) => call_block(vm, block, val)?,
+            None => val,
+        };
+        match pair.as_array() {
+            Some(aref) if aref.elements.len() == 2 => {
+                vm.temp_push(pair);
+                HashInfo::catch_key_error(|| {
+                    map.insert(HashKey(aref.elements[0]), aref.elements[1])
+                })?;
+                Ok(true)
+            }
+            _ => {
+                let inspect = vm.val_inspect(pair);
+                Err(vm.error_type(format!(
+                    "Wrong element type {} (expected array of 2 elements).",
+                    inspect
+                )))
+            }
+        }
+    })?;
+    Ok(new_hash(vm, map))
+}
+
+fn map(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let size = EnumSize::Func(receiver_size);
+    let block = block_or_enum!(vm, self_val, args, "map", size);
+    let mut v = vec![];
+    each_elem(vm, self_val, |vm, val| {
+        let res = call_block(vm, block, val)?;
+        vm.temp_push(res);
+        v.push(res);
+        Ok(true)
+    })?;
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+fn flat_map(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let size = EnumSize::Func(receiver_size);
+    let block = block_or_enum!(vm, self_val, args, "flat_map", size);
+    let mut v = vec![];
+    each_elem(vm, self_val, |vm, val| {
+        let res = call_block(vm, block, val)?;
+        vm.temp_push(res);
+        match res.as_array() {
+            Some(aref) => v.extend_from_slice(&aref.elements),
+            None => v.push(res),
+        }
+        Ok(true)
+    })?;
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+/// Get elements for which the block returns `cond`.
+fn filter_elems(vm: &mut VM, self_val: Value, block: MethodRef, cond: bool) -> VMResult {
+    let mut v = vec![];
+    each_elem(vm, self_val, |vm, val| {
+        let res = call_block(vm, block, val)?;
+        if vm.val_to_bool(res) == cond {
+            vm.temp_push(val);
+            v.push(val);
+        }
+        Ok(true)
+    })?;
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+fn select(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let block = block_or_enum!(vm, self_val, args, "select");
+    filter_elems(vm, self_val, block, true)
+}
+
+fn reject(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let block = block_or_enum!(vm, self_val, args, "reject");
+    filter_elems(vm, self_val, block, false)
+}
+
+fn filter_map(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let block = block_or_enum!(vm, self_val, args, "filter_map");
+    let mut v = vec![];
+    each_elem(vm, self_val, |vm, val| {
+        let res = call_block(vm, block, val)?;
+        if vm.val_to_bool(res) {
+            vm.temp_push(res);
+            v.push(res);
+        }
+        Ok(true)
+    })?;
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+fn partition(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let block = block_or_enum!(vm, self_val, args, "partition");
+    let mut t = vec![];
+    let mut f = vec![];
+    each_elem(vm, self_val, |vm, val| {
+        let res = call_block(vm, block, val)?;
+        vm.temp_push(val);
+        if vm.val_to_bool(res) {
+            t.push(val);
+        } else {
+            f.push(val);
+        }
+        Ok(true)
+    })?;
+    let t = Value::array_from(&vm.globals, t);
+    let f = Value::array_from(&vm.globals, f);
+    Ok(Value::array_from(&vm.globals, vec![t, f]))
+}
+
+fn group_by(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let block = block_or_enum!(vm, self_val, args, "group_by");
+    let mut map: OrderedMap<HashKey> = OrderedMap::new();
+    each_elem(vm, self_val, |vm, val| {
+        let key = call_block(vm, block, val)?;
+        match HashInfo::catch_key_error(|| map.get(&HashKey(key)).cloned())? {
+            Some(group) => group.as_array().unwrap().elements.push(val),
+            None => {
+                let group = Value::array_from(&vm.globals, vec![val]);
+                vm.temp_push(key);
+                vm.temp_push(group);
+                HashInfo::catch_key_error(|| map.insert(HashKey(key), group))?;
+            }
+        }
+        Ok(true)
+    })?;
+    Ok(new_hash(vm, map))
+}
+
+fn tally(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut map: OrderedMap<HashKey> = OrderedMap::new();
+    each_elem(vm, self_val, |vm, val| {
+        vm.temp_push(val);
+        HashInfo::catch_key_error(|| {
+            let count = match map.get(&HashKey(val)) {
+                Some(count) => count.as_fixnum().unwrap(),
+                None => 0,
+            };
+            map.insert(HashKey(val), Value::fixnum(count + 1));
+        })?;
+        Ok(true)
+    })?;
+    Ok(new_hash(vm, map))
+}
+
+fn find(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let block = block_or_enum!(vm, self_val, args, "find");
+    let mut found = Value::nil();
+    each_elem(vm, self_val, |vm, val| {
+        let res = call_block(vm, block, val)?;
+        if vm.val_to_bool(res) {
+            found = val;
+            return Ok(false);
+        }
+        Ok(true)
+    })?;
+    Ok(found)
+}
+
+/// Get the index of the first element which is equal to the argument,
+/// or for which the block returns true.
+fn find_index(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let block = if args.len() == 0 {
+        Some(block_or_enum!(vm, self_val, args, "find_index"))
+    } else {
+        None
+    };
+    let mut index = 0;
+    let mut found = Value::nil();
+    each_elem(vm, self_val, |vm, val| {
+        let res = match block {
+            Some(block) => {
+                let res = call_block(vm, block, val)?;
+                vm.val_to_bool(res)
+            }
+            None => vm.eval_eq(val, args[0])?,
+        };
+        if res {
+            found = Value::fixnum(index);
+            return Ok(false);
+        }
+        index += 1;
+        Ok(true)
+    })?;
+    Ok(found)
+}
+
+/// Test each element with the pattern (with `===`), the block, or the element itself.
+fn test_elem(vm: &mut VM, args: &Args, val: Value) -> Result<bool, RubyError> {
+    if args.len() == 1 {
+        vm.eval_teq(val, args[0])
+    } else if let Some(block) = args.block {
+        let res = call_block(vm, block, val)?;
+        Ok(vm.val_to_bool(res))
+    } else {
+        Ok(vm.val_to_bool(val))
+    }
+}
+
+fn all(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let mut res = true;
+    each_elem(vm, self_val, |vm, val| {
+        res = test_elem(vm, args, val)?;
+        Ok(res)
+    })?;
+    Ok(Value::bool(res))
+}
+
+fn any(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let mut res = false;
+    each_elem(vm, self_val, |vm, val| {
+        res = test_elem(vm, args, val)?;
+        Ok(!res)
+    })?;
+    Ok(Value::bool(res))
+}
+
+fn none(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let mut res = true;
+    each_elem(vm, self_val, |vm, val| {
+        res = !test_elem(vm, args, val)?;
+        Ok(res)
+    })?;
+    Ok(Value::bool(res))
+}
+
+fn include(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let mut res = false;
+    each_elem(vm, self_val, |vm, val| {
+        res = vm.eval_eq(val, args[0])?;
+        Ok(!res)
+    })?;
+    Ok(Value::bool(res))
+}
+
+/// Count elements which are equal to the argument, or for which the block returns true.
+fn count(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let mut count = 0;
+    each_elem(vm, self_val, |vm, val| {
+        let res = if args.len() == 1 {
+            vm.eval_eq(val, args[0])?
+        } else if let Some(block) = args.block {
+            let res = call_block(vm, block, val)?;
+            vm.val_to_bool(res)
+        } else {
+            true
+        };
+        if res {
+            count += 1;
+        }
+        Ok(true)
+    })?;
+    Ok(Value::fixnum(count))
+}
+
+/// Take the first `num` elements.
+fn take_elems(vm: &mut VM, self_val: Value, num: i64) -> Result<Vec<Value>, RubyError> {
+    if num < 0 {
+        return Err(vm.error_argument("Attempt to take negative size."));
+    }
+    let mut v = vec![];
+    if num == 0 {
+        return Ok(v);
+    }
+    each_elem(vm, self_val, |vm, val| {
+        vm.temp_push(val);
+        v.push(val);
+        Ok((v.len() as i64) < num)
+    })?;
+    Ok(v)
+}
+
+fn first(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    if args.len() == 0 {
+        let v = take_elems(vm, self_val, 1)?;
+        return Ok(v.first().cloned().unwrap_or_default());
+    }
+    let num = vm.expect_integer(args[0], "Argument")?;
+    let v = take_elems(vm, self_val, num)?;
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+fn take(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let num = vm.expect_integer(args[0], "Argument")?;
+    let v = take_elems(vm, self_val, num)?;
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+fn take_while(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let block = block_or_enum!(vm, self_val, args, "take_while");
+    let mut v = vec![];
+    each_elem(vm, self_val, |vm, val| {
+        let res = call_block(vm, block, val)?;
+        if !vm.val_to_bool(res) {
+            return Ok(false);
+        }
+        vm.temp_push(val);
+        v.push(val);
+        Ok(true)
+    })?;
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+fn drop_while(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let block = block_or_enum!(vm, self_val, args, "drop_while");
+    let mut v = vec![];
+    let mut dropping = true;
+    each_elem(vm, self_val, |vm, val| {
+        if dropping {
+            let res = call_block(vm, block, val)?;
+            dropping = vm.val_to_bool(res);
+        }
+        if !dropping {
+            vm.temp_push(val);
+            v.push(val);
+        }
+        Ok(true)
+    })?;
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+/// inject(init = nil, sym) or inject(init = nil) { |memo, elem| ... }
+/// When `init` is not given, the first element is used as the initial value.
+fn inject(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 2)?;
+    let (init, op) = match args.len() {
+        2 => (Some(args[0]), args[1].as_symbol()),
+        1 if args.block.is_none() => (None, args[0].as_symbol()),
+        1 => (Some(args[0]), None),
+        _ => (None, None),
+    };
+    if args.len() == 2 || (args.len() == 1 && args.block.is_none()) {
+        if op.is_none() {
+            let inspect = vm.val_inspect(args[args.len() - 1]);
+            return Err(vm.error_type(format!("{} is not a symbol.", inspect)));
+        }
+    } else if args.block.is_none() {
+        return Err(vm.error_argument("Needs a block or a symbol."));
+    }
+    let mut memo = init;
+    each_elem(vm, self_val, |vm, val| {
+        let res = match memo {
+            None => val,
+            Some(memo) => match op {
+                Some(op) => vm.send1(memo, op, val)?,
+                None => vm.eval_block(args.block.unwrap(), &Args::new2(memo, val))?,
+            },
+        };
+        vm.temp_push(res);
+        memo = Some(res);
+        Ok(true)
+    })?;
+    Ok(memo.unwrap_or_default())
+}
+
+fn sum(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let mut sum = if args.len() == 1 {
+        args[0]
+    } else {
+        Value::fixnum(0)
+    };
+    each_elem(vm, self_val, |vm, val| {
+        let val = match args.block {
+            Some(block) => call_block(vm, block, val)?,
+            None => val,
+        };
+        sum = match (sum.as_fixnum(), val.as_fixnum()) {
+            (Some(lhs), Some(rhs)) => match lhs.checked_add(rhs) {
+                Some(res) => Value::fixnum(res),
+                None => vm.send1(sum, IdentId::_ADD, val)?,
+            },
+            _ => vm.send1(sum, IdentId::_ADD, val)?,
+        };
+        vm.temp_push(sum);
+        Ok(true)
+    })?;
+    Ok(sum)
+}
+
+/// Get the element which is the minimum (`ord` is Less) or the maximum (`ord` is Greater).
+/// The block compares two elements if given.
+fn min_max(vm: &mut VM, self_val: Value, args: &Args, ord: Ordering) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut res: Option<Value> = None;
+    each_elem(vm, self_val, |vm, val| {
+        // The current candidate is preserved from GC until the iteration finishes.
+        vm.temp_push(val);
+        res = match res {
+            None => Some(val),
+            Some(current) => {
+                let cmp = match args.block {
+                    Some(block) => {
+                        let cmp = vm.eval_block(block, &Args::new2(val, current))?;
+                        cmp.expect_integer(vm, "Result of the block")?.cmp(&0)
+                    }
+                    None => vm.compare_values(val, current)?,
+                };
+                if cmp == ord {
+                    Some(val)
+                } else {
+                    Some(current)
+                }
+            }
+        };
+        Ok(true)
+    })?;
+    Ok(res.unwrap_or_default())
+}
+
+fn min(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    min_max(vm, self_val, args, Ordering::Less)
+}
+
+fn max(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    min_max(vm, self_val, args, Ordering::Greater)
+}
+
+/// Get the element for which the block returns the minimum (`ord` is Less)
+/// or the maximum (`ord` is Greater) value.
+fn min_max_by(vm: &mut VM, self_val: Value, block: MethodRef, ord: Ordering) -> VMResult {
+    let mut res: Option<(Value, Value)> = None;
+    each_elem(vm, self_val, |vm, val| {
+        // The current candidate and its key are preserved from GC until the iteration finishes.
+        vm.temp_push(val);
+        let key = call_block(vm, block, val)?;
+        vm.temp_push(key);
+        res = match res {
+            None => Some((val, key)),
+            Some((current, current_key)) => {
+                if vm.compare_values(key, current_key)? == ord {
+                    Some((val, key))
+                } else {
+                    Some((current, current_key))
+                }
+            }
+        };
+        Ok(true)
+    })?;
+    Ok(res.map(|(val, _)| val).unwrap_or_default())
+}
+
+fn min_by(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let block = block_or_enum!(vm, self_val, args, "min_by");
+    min_max_by(vm, self_val, block, Ordering::Less)
+}
+
+fn max_by(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let block = block_or_enum!(vm, self_val, args, "max_by");
+    min_max_by(vm, self_val, block, Ordering::Greater)
+}
+
+fn sort(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut v = collect_elems(vm, self_val)?;
+    vm.sort_values(&mut v, |vm, a, b| match args.block {
+        Some(block) => {
+            let res = vm.eval_block(block, &Args::new2(a, b))?;
+            Ok(res.expect_integer(vm, "Result of the block")?.cmp(&0))
+        }
+        None => vm.compare_values(a, b),
+    })?;
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+fn sort_by(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let block = block_or_enum!(vm, self_val, args, "sort_by");
+    // Pairs of [key, element] are kept in an array, which is preserved from GC
+    // during the iteration and the comparisons.
+    let pairs = Value::array_from(&vm.globals, vec![]);
+    vm.temp_push(pairs);
+    let mut pairs = pairs.as_array().unwrap();
+    each_elem(vm, self_val, |vm, val| {
+        vm.temp_push(val);
+        let key = call_block(vm, block, val)?;
+        let pair = Value::array_from(&vm.globals, vec![key, val]);
+        pairs.elements.push(pair);
+        Ok(true)
+    })?;
+    let mut sorted = pairs.elements.clone();
+    vm.sort_values(&mut sorted, |vm, a, b| {
+        let a = a.as_array().unwrap().elements[0];
+        let b = b.as_array().unwrap().elements[0];
+        vm.compare_values(a, b)
+    })?;
+    let v = sorted
+        .iter()
+        .map(|pair| pair.as_array().unwrap().elements[1])
+        .collect();
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+/// Remove duplicated elements. The block gives the value to be compared if given.
+fn uniq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut set = std::collections::HashSet::new();
+    let mut v = vec![];
+    each_elem(vm, self_val, |vm, val| {
+        let key = match args.block {
+            Some(block) => call_block(vm, block, val)?,
+            None => val,
+        };
+        vm.temp_push(key);
+        if HashInfo::catch_key_error(|| set.insert(HashKey(key)))? {
+            vm.temp_push(val);
+            v.push(val);
+        }
+        Ok(true)
+    })?;
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+fn each_with_index(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let size = EnumSize::Func(receiver_size);
+    let block = block_or_enum!(vm, self_val, args, "each_with_index", size);
+    let mut index = 0;
+    each_elem(vm, self_val, |vm, val| {
+        vm.eval_block(block, &Args::new2(val, Value::fixnum(index)))?;
+        index += 1;
+        Ok(true)
+    })?;
+    Ok(self_val)
+}
+
+fn each_with_object(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let block = block_or_enum!(vm, self_val, args, "each_with_object");
+    let memo = args[0];
+    each_elem(vm, self_val, |vm, val| {
+        vm.eval_block(block, &Args::new2(val, memo))?;
+        Ok(true)
+    })?;
+    Ok(memo)
+}
+
+/// Iterate each slice of `num` elements. If `cons` is true, the slices are consecutive.
+fn each_slice_cons(vm: &mut VM, self_val: Value, args: &Args, cons: bool) -> VMResult {
+    let method = if cons { "each_cons" } else { "each_slice" };
+    vm.check_args_num(args.len(), 1)?;
+    let num = vm.expect_integer(args[0], "Argument")?;
+    if num <= 0 {
+        return Err(vm.error_argument("Invalid size."));
+    }
+    let num = num as usize;
+    let block = block_or_enum!(vm, self_val, args, method);
+    let mut slice: Vec<Value> = vec![];
+    each_elem(vm, self_val, |vm, val| {
+        vm.temp_push(val);
+        slice.push(val);
+        if slice.len() == num {
+            let ary = Value::array_from(&vm.globals, slice.clone());
+            call_block(vm, block, ary)?;
+            if cons {
+                slice.remove(0);
+            } else {
+                slice.clear();
+            }
+        }
+        Ok(true)
+    })?;
+    if !cons && !slice.is_empty() {
+        let ary = Value::array_from(&vm.globals, slice);
+        call_block(vm, block, ary)?;
+    }
+    Ok(self_val)
+}
+
+fn each_slice(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    each_slice_cons(vm, self_val, args, false)
+}
+
+fn each_cons(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    each_slice_cons(vm, self_val, args, true)
+}
+
+fn zip(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let mut others = vec![];
+    for arg in args.iter() {
+        let v = match arg.as_array() {
+            Some(aref) => aref.elements.clone(),
+            None => collect_elems(vm, *arg)?,
+        };
+        others.push(v);
+    }
+    let mut res = vec![];
+    let mut index = 0;
+    each_elem(vm, self_val, |vm, val| {
+        let mut tuple = vec![val];
+        for other in &others {
+            tuple.push(other.get(index).cloned().unwrap_or_default());
+        }
+        let tuple = Value::array_from(&vm.globals, tuple);
+        match args.block {
+            Some(block) => {
+                call_block(vm, block, tuple)?;
+            }
+            None => {
+                vm.temp_push(tuple);
+                res.push(tuple);
+            }
+        }
+        index += 1;
+        Ok(true)
+    })?;
+    match args.block {
+        Some(_) => Ok(Value::nil()),
+        None => Ok(Value::array_from(&vm.globals, res)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn enumerable_range_hash() {
+        let program = r#"
+        r = 1..6
+        assert([2, 4, 6], r.select { |x| x.even? })
+        assert([1, 3, 5], r.reject { |x| x.even? })
+        assert(21, r.inject { |s, x| s + x })
+        assert(121, r.reduce(100) { |s, x| s + x })
+        assert(21, r.sum)
+        assert([[1, 2], [3, 4], [5, 6]], r.each_slice(2).to_a)
+        a = []
+        r.each_slice(4) { |x| a << x }
+        assert([[1, 2, 3, 4], [5, 6]], a)
+        a = []
+        (1..4).each_cons(2) { |x| a << x }
+        assert([[1, 2], [2, 3], [3, 4]], a)
+        assert(3, r.find { |x| x > 2 })
+        assert(nil, r.find { |x| x > 10 })
+        assert(2, r.find_index(3))
+        assert([[2, 4, 6], [1, 3, 5]], r.partition { |x| x.even? })
+        assert([1, 2], r.take_while { |x| x < 3 })
+        assert([5, 6], r.drop_while { |x| x < 5 })
+        assert([1, 2, 3], r.first(3))
+        assert(1, r.first)
+        assert(3, r.count { |x| x.even? })
+        assert(true, r.include?(4))
+        assert(false, r.any? { |x| x > 6 })
+        assert(true, r.all?(Integer))
+        g = r.group_by { |x| x % 3 }
+        assert([3, 6], g[0])
+        assert([1, 4], g[1])
+        h = {a: 1, b: 2, c: 3}
+        assert(6, h.sum { |k, v| v })
+        assert([:a, 1], h.min_by { |k, v| v })
+        assert([:c, 3], h.max_by { |k, v| v })
+        assert([[:a, 1], [:b, 2], [:c, 3]], h.sort_by { |k, v| v })
+        assert(3, h.count)
+        t = [1, 2, 1, 3, 1].tally
+        assert(3, t[1])
+        assert(1, t[3])
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn enumerable_break_ensure() {
+        let program = r#"
+        class Foo
+          include Enumerable
+          attr_reader :log
+          def initialize
+            @log = []
+          end
+          def each
+            begin
+              yield 1
+              yield 2
+              yield 3
+            ensure
+              @log << :ensure
+            end
+          end
+        end
+        foo = Foo.new
+        assert(2, foo.find { |x| x == 2 })
+        assert([:ensure], foo.log)
+        assert([1, 2], foo.first(2))
+        assert([:ensure, :ensure], foo.log)
+        assert(true, foo.include?(1))
+        assert(3, foo.log.size)
+        r = foo.each_with_index { |x, i| break x * 10 if i == 1 }
+        assert(20, r)
+        assert(4, foo.log.size)
+        class Bar
+          include Enumerable
+          def initialize(*a)
+            @a = a
+          end
+          def each(&b)
+            @a.each(&b)
+            self
+          end
+        end
+        assert([1, 2], Bar.new(1, 2, 3).take(2))
+        assert(6, Bar.new(1, 2, 3).sum)
+        assert([[1, 0], [2, 1]], Bar.new(1, 2).each_with_index.to_a)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn enumerable_gc_stress() {
+        let program = r##"
+        a = (0...30000).to_a
+        sorted = a.sort_by { |x| "k#{-x}" }
+        assert([1, 10, 100], sorted[0, 3])
+        class Gen
+          include Enumerable
+          def each
+            20000.times { |i| yield "s#{i}" }
+          end
+        end
+        g = Gen.new
+        assert("s0", g.min)
+        assert("s9999", g.max)
+        assert("s10000", g.min_by { |x| -x.size })
+        assert("s9999", g.max_by { |x| x })
+        sorted = g.sort_by { |x| "k#{x}" }
+        assert(["s0", "s1", "s10"], sorted[0, 3])
+        assert("s9999", sorted[-1])
+        "##;
+        assert_script(program);
+    }
+
+    #[test]
+    fn enumerable_user_class() {
+        let program = r#"
+        class Foo
+          include Enumerable
+          def initialize(*a)
+            @a = a
+          end
+          def each
+            @a.each { |x| yield x }
+            self
+          end
+        end
+        foo = Foo.new(3, 1, 2)
+        assert(true, foo.is_a?(Enumerable))
+        assert([3, 1, 2], foo.to_a)
+        assert([6, 2, 4], foo.map { |x| x * 2 })
+        assert([1, 2, 3], foo.sort)
+        assert([3, 2, 1], foo.sort { |a, b| b <=> a })
+        assert([3, 2, 1], foo.sort_by { |x| -x })
+        assert(1, foo.min)
+        assert(3, foo.max)
+        assert([[3, 0], [1, 1], [2, 2]], foo.each_with_index.to_a)
+        a = []
+        foo.each_with_index { |x, i| a << [x, i] }
+        assert([[3, 0], [1, 1], [2, 2]], a)
+        assert([3, 1, 2], foo.each_with_object([]) { |x, memo| memo << x })
+        assert([[3, 4], [1, 5], [2, 6]], foo.zip([4, 5, 6]))
+        assert({3 => true, 1 => false, 2 => false}, foo.to_h { |x| [x, x > 2] })
+        assert(6, foo.sum)
+        assert(true, foo.include?(2))
+        assert(2, foo.find { |x| x.even? })
+        assert(2, foo.first(2).size)
+        r = foo.each_slice(2) { |x| break x }
+        assert([3, 1], r)
+        "#;
+        assert_script(program);
+    }
+}
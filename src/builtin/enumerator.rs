@@ -1,28 +1,89 @@
 use crate::*;
 
-#[derive(Debug, Clone)]
+/// How to get the size of an Enumerator without running the iteration.
+#[derive(Debug, Clone, Copy)]
+pub enum EnumSize {
+    /// The size is unknown, and Enumerator#size returns nil.
+    Unknown,
+    /// The size given by Enumerator.new.
+    Value(Value),
+    /// The size is computed from the receiver by the function.
+    Func(fn(&mut VM, Value) -> VMResult),
+}
+
+#[derive(Debug)]
 pub struct EnumInfo {
     method: IdentId,
     receiver: Value,
     args: Args,
+    size: EnumSize,
+    /// The fiber which runs the iteration for Enumerator#next.
+    fiber: Option<Value>,
+    /// The value prefetched by Enumerator#peek.
+    peeked: Option<Value>,
+    /// The return value of the iteration, which is set when the iteration reached the end.
+    result: Value,
 }
 
 impl EnumInfo {
-    pub fn new(method: IdentId, receiver: Value, mut args: Args) -> Self {
+    pub fn new(method: IdentId, receiver: Value, mut args: Args, size: EnumSize) -> Self {
         args.block = Some(MethodRef::from(0));
         EnumInfo {
             method,
             receiver,
             args,
+            size,
+            fiber: None,
+            peeked: None,
+            result: Value::nil(),
+        }
+    }
+
+    /// Discard the state of external iteration.
+    fn rewind(&mut self) {
+        self.fiber = None;
+        self.peeked = None;
+        self.result = Value::nil();
+    }
+}
+
+/// The state of external iteration is not copied.
+impl Clone for EnumInfo {
+    fn clone(&self) -> Self {
+        EnumInfo {
+            method: self.method,
+            receiver: self.receiver,
+            args: self.args.clone(),
+            size: self.size,
+            fiber: None,
+            peeked: None,
+            result: Value::nil(),
+        }
+    }
+}
+
+impl GC for EnumInfo {
+    fn mark(&self, alloc: &mut Allocator) {
+        self.receiver.mark(alloc);
+        self.args.mark(alloc);
+        if let EnumSize::Value(size) = self.size {
+            size.mark(alloc);
         }
+        if let Some(fiber) = self.fiber {
+            fiber.mark(alloc);
+        }
+        if let Some(val) = self.peeked {
+            val.mark(alloc);
+        }
+        self.result.mark(alloc);
     }
 }
 
 pub type EnumRef = Ref<EnumInfo>;
 
 impl EnumRef {
-    pub fn from(method: IdentId, receiver: Value, args: Args) -> Self {
-        EnumRef::new(EnumInfo::new(method, receiver, args))
+    pub fn from(method: IdentId, receiver: Value, args: Args, size: EnumSize) -> Self {
+        EnumRef::new(EnumInfo::new(method, receiver, args, size))
     }
 
     pub fn eval(&self, vm: &mut VM) -> VMResult {
@@ -30,24 +91,76 @@ impl EnumRef {
         let method = vm.get_method(receiver, self.method)?;
         vm.eval_send(method, receiver, &self.args)
     }
+
+    /// Call the method of the receiver with `block`.
+    pub fn eval_with_block(&self, vm: &mut VM, block: MethodRef) -> VMResult {
+        let receiver = self.receiver;
+        let method = vm.get_method(receiver, self.method)?;
+        let mut args = self.args.clone();
+        args.block = Some(block);
+        vm.eval_send(method, receiver, &args)
+    }
 }
 
 pub fn init_enumerator(globals: &mut Globals) -> Value {
     let id = globals.get_ident_id("Enumerator");
     let class = ClassRef::from(id, globals.builtins.object);
     globals.add_builtin_instance_method(class, "each", each);
-    globals.add_builtin_instance_method(class, "map", map);
-    globals.add_builtin_instance_method(class, "collect", map);
     globals.add_builtin_instance_method(class, "with_index", with_index);
     globals.add_builtin_instance_method(class, "inspect", inspect);
-    let class = Value::class(globals, class);
+    globals.add_builtin_instance_method(class, "next", next);
+    globals.add_builtin_instance_method(class, "peek", peek);
+    globals.add_builtin_instance_method(class, "rewind", rewind);
+    globals.add_builtin_instance_method(class, "size", size);
+    let mut class = Value::class(globals, class);
     globals.add_builtin_class_method(class, "new", enum_new);
+
+    let id = globals.get_ident_id("Generator");
+    let generator = Value::class_from(globals, id, globals.builtins.object);
+    globals.add_builtin_instance_method(generator.as_class(), "each", generator_each);
+    class.set_var(id, generator);
+
+    let id = globals.get_ident_id("Yielder");
+    let yielder = Value::class_from(globals, id, globals.builtins.object);
+    let yielder_class = yielder.as_class();
+    globals.add_builtin_instance_method(yielder_class, "<<", yielder_push);
+    globals.add_builtin_instance_method(yielder_class, "yield", yielder_yield);
+    globals.add_builtin_instance_method(yielder_class, "call", yielder_yield);
+    class.set_var(id, yielder);
     class
 }
 
+fn get_enumerator_class(vm: &mut VM, name: &str) -> Value {
+    let id = vm.globals.get_ident_id(name);
+    vm.globals.builtins.enumerator.get_var(id).unwrap()
+}
+
+/// Create StopIteration error with the return value of the iteration.
+fn error_stop_iteration(vm: &mut VM, result: Value) -> RubyError {
+    let err = vm.error_stop_iteration("iteration reached an end");
+    let exception = vm.exception_from_error(&err);
+    let mut eref = exception.as_exception().unwrap();
+    eref.result = result;
+    vm.error_from_exception(exception)
+}
+
 // Class methods
 
 fn enum_new(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    if let Some(block) = args.block {
+        // Enumerator.new(size = nil) { |yielder| ... }
+        vm.check_args_range(args.len(), 0, 1)?;
+        let procobj = vm.create_proc(block)?;
+        let generator = Value::generator(get_enumerator_class(vm, "Generator"), procobj);
+        let method = vm.globals.get_ident_id("each");
+        let size = if args.len() == 1 {
+            EnumSize::Value(args[0])
+        } else {
+            EnumSize::Unknown
+        };
+        let val = Value::enumerator_with_size(&vm.globals, method, generator, Args::new0(), size);
+        return Ok(val);
+    }
     vm.check_args_min(args.len(), 1)?;
     let (receiver, method, new_args) = if args.len() == 1 {
         let method = vm.globals.get_ident_id("each");
@@ -99,81 +212,208 @@ fn each(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 0)?;
     let eref = vm.expect_enumerator(self_val, "Expect Enumerator.")?;
     let block = match args.block {
-        Some(method) => method,
-        None => {
-            return Ok(self_val);
-        }
+        Some(block) => block,
+        None => return Ok(self_val),
     };
-
-    let val = vm.eval_enumerator(eref)?;
-
-    let ary = vm.expect_array(val, "Base object")?;
-    let mut args = Args::new1(Value::nil());
-    for elem in &ary.elements {
-        args[0] = *elem;
-        let _ = vm.eval_block(block, &args)?;
+    if eref.receiver.as_generator().is_none() {
+        return eref.eval_with_block(vm, block);
+    }
+    // The block of Enumerator.new is run in a fiber, so that `break` in `block`
+    // exits from this method regardless of the block of Enumerator.new.
+    let fiber = new_enum_fiber(vm, self_val);
+    vm.temp_push(fiber);
+    let fref = fiber.as_fiber().unwrap();
+    let mut arg = Args::new0();
+    loop {
+        let val = resume_fiber(vm, fref, &arg)?;
+        if fref.state == FiberState::Dead {
+            return Ok(val);
+        }
+        let res = vm.eval_block(block, &Args::new1(val))?;
+        arg = Args::new1(res);
     }
-    Ok(val)
 }
 
-fn map(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+/// Call the block with each element and its index, and return the value of the iteration,
+/// to which the value of the block is passed for each element.
+fn with_index(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 0)?;
-    let eref = vm.expect_enumerator(self_val, "Expect Enumerator.")?;
     let block = match args.block {
         Some(method) => method,
         None => {
             // return Enumerator
-            let id = vm.globals.get_ident_id("map");
-            let e = Value::enumerator(&vm.globals, id, self_val, args.clone());
+            let id = vm.globals.get_ident_id("with_index");
+            let size = EnumSize::Func(receiver_size);
+            let e = Value::enumerator_with_size(&vm.globals, id, self_val, args.clone(), size);
             return Ok(e);
         }
     };
-    let val = vm.eval_enumerator(eref)?;
+    let mut index = 0;
+    let id = vm.globals.get_ident_id("each");
+    vm.eval_with_native_block(self_val, id, &Args::new0(), &mut |vm, val| {
+        let res = vm.eval_block(block, &Args::new2(val, Value::fixnum(index)))?;
+        index += 1;
+        Ok(Some(res))
+    })
+}
 
-    let ary = vm.expect_array(val, "Base object")?;
-    let mut args = Args::new1(Value::nil());
-    let mut res = vec![];
-    for elem in &ary.elements {
-        args[0] = *elem;
-        res.push(vm.eval_block(block, &args)?);
+/// Get the next element of the iteration, running the iteration in a fiber.
+/// Raise StopIteration when the iteration reached the end.
+fn next(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut eref = vm.expect_enumerator(self_val, "Expect Enumerator.")?;
+    match eref.peeked.take() {
+        Some(val) => Ok(val),
+        None => fetch_next(vm, self_val),
     }
-    Ok(Value::array_from(&vm.globals, res))
 }
 
-fn with_index(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+/// Get the next element of the iteration without advancing the position.
+fn peek(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut eref = vm.expect_enumerator(self_val, "Expect Enumerator.")?;
+    if let Some(val) = eref.peeked {
+        return Ok(val);
+    }
+    let val = fetch_next(vm, self_val)?;
+    eref.peeked = Some(val);
+    Ok(val)
+}
+
+fn fetch_next(vm: &mut VM, enumerator: Value) -> VMResult {
+    let mut eref = enumerator.as_enumerator().unwrap();
+    let fiber = match eref.fiber {
+        Some(fiber) => fiber,
+        None => {
+            let fiber = new_enum_fiber(vm, enumerator);
+            eref.fiber = Some(fiber);
+            fiber
+        }
+    };
+    let fref = fiber.as_fiber().unwrap();
+    if fref.state == FiberState::Dead {
+        return Err(error_stop_iteration(vm, eref.result));
+    }
+    let val = resume_fiber(vm, fref, &Args::new0())?;
+    if fref.state == FiberState::Dead {
+        eref.result = val;
+        return Err(error_stop_iteration(vm, val));
+    }
+    Ok(val)
+}
+
+/// Rewind the external iteration to the beginning.
+fn rewind(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut eref = vm.expect_enumerator(self_val, "Expect Enumerator.")?;
+    eref.rewind();
+    Ok(self_val)
+}
+
+/// Get the number of elements without running the iteration, or nil if it is unknown.
+fn size(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 0)?;
     let eref = vm.expect_enumerator(self_val, "Expect Enumerator.")?;
+    match eref.size {
+        EnumSize::Unknown => Ok(Value::nil()),
+        EnumSize::Value(size) => Ok(size),
+        EnumSize::Func(func) => func(vm, eref.receiver),
+    }
+}
+
+/// Get the number of elements of `receiver` without running the iteration, or nil if it is unknown.
+/// This is used as the size function of enumerators which iterate each element of the receiver once.
+pub fn receiver_size(vm: &mut VM, receiver: Value) -> VMResult {
+    if let Some(aref) = receiver.as_array() {
+        Ok(Value::fixnum(aref.elements.len() as i64))
+    } else if let Some(href) = receiver.as_hash() {
+        Ok(Value::fixnum(href.len() as i64))
+    } else if let Some(range) = receiver.as_range() {
+        match (range.start.as_fixnum(), range.end.as_fixnum()) {
+            (Some(start), Some(end)) => {
+                let end = if range.exclude { end } else { end + 1 };
+                Ok(Value::fixnum(std::cmp::max(end - start, 0)))
+            }
+            _ => Ok(Value::nil()),
+        }
+    } else if receiver.as_enumerator().is_some() {
+        size(vm, receiver, &Args::new0())
+    } else {
+        Ok(Value::nil())
+    }
+}
+
+// Enumerator::Generator
+
+/// Call the block of Enumerator.new with a yielder, which passes values to `block`.
+/// When the block is a null block, the values are collected to an array and returned.
+fn generator_each(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let block = match args.block {
-        Some(method) => method,
+        Some(block) => block,
         None => {
-            // return Enumerator
-            let id = vm.globals.get_ident_id("with_index");
-            let e = Value::enumerator(&vm.globals, id, self_val, args.clone());
-            return Ok(e);
+            let id = vm.globals.get_ident_id("each");
+            let val = Value::enumerator(&vm.globals, id, self_val, args.clone());
+            return Ok(val);
         }
     };
+    let procobj = match self_val.as_generator() {
+        Some(procobj) => procobj,
+        None => return Err(vm.error_argument("Uninitialized generator.")),
+    };
+    let target = if block.is_none() {
+        Value::array_from(&vm.globals, vec![])
+    } else {
+        match vm.globals.get_method_info(block) {
+            MethodInfo::RubyFunc { .. } => vm.create_proc(block)?,
+            MethodInfo::BuiltinFunc { name, .. } => {
+                let name = name.clone();
+                let id = vm.globals.get_ident_id(name);
+                Value::method(&vm.globals, id, Value::nil(), block)
+            }
+            _ => return Err(vm.error_internal("Illegal block.")),
+        }
+    };
+    let yielder = Value::yielder(get_enumerator_class(vm, "Yielder"), target);
+    vm.temp_push(yielder);
+    let id = vm.globals.get_ident_id("call");
+    let method = vm.get_method(procobj, id)?;
+    let res = vm.eval_send(method, procobj, &Args::new1(yielder))?;
+    if block.is_none() {
+        Ok(target)
+    } else {
+        Ok(res)
+    }
+}
 
-    let val = vm.eval_enumerator(eref)?;
-    let res_ary: Vec<(Value, Value)> = vm
-        .expect_array(val, "Base object")?
-        .elements
-        .iter()
-        .enumerate()
-        .map(|(i, v)| (v.clone(), Value::fixnum(i as i64)))
-        .collect();
-
-    let mut res = vec![];
-    let mut arg = Args::new(2);
+// Enumerator::Yielder
 
-    for (v, i) in &res_ary {
-        arg[0] = *v;
-        arg[1] = *i;
-        let val = vm.eval_block(block, &arg)?;
-        res.push(val);
+fn yielder_yield(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let target = match self_val.as_yielder() {
+        Some(target) => target,
+        None => return Err(vm.error_argument("Uninitialized yielder.")),
+    };
+    match target.as_array() {
+        Some(mut aref) => {
+            let val = match args.len() {
+                0 => Value::nil(),
+                1 => args[0],
+                _ => Value::array_from(&vm.globals, args.to_vec()),
+            };
+            aref.elements.push(val);
+            Ok(Value::nil())
+        }
+        None => {
+            let id = vm.globals.get_ident_id("call");
+            let method = vm.get_method(target, id)?;
+            vm.eval_send(method, target, args)
+        }
     }
+}
 
-    let res = Value::array_from(&vm.globals, res);
-    Ok(res)
+fn yielder_push(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    yielder_yield(vm, self_val, args)?;
+    Ok(self_val)
 }
 
 #[cfg(test)]
@@ -189,6 +429,107 @@ mod test {
         assert [["This", 0], ["is", 1], ["a", 2], ["Ruby.", 3]], ans
         ans = %w(This is a Ruby.).map.with_index {|x,y,z| [x,y,z] }
         assert [["This", 0, nil], ["is", 1, nil], ["a", 2, nil], ["Ruby.", 3, nil]], ans
+        assert [2, 4], [1, 2, 3, 4].select.with_index {|x, i| i % 2 == 1 }
+        assert [[1, 2], [3, 4], [5]], [1, 2, 3, 4, 5].each_slice(2).map {|x| x }
+        assert [0, 2, 6], [1, 2, 3].each_with_index.map {|x, i| x * i }
+        assert [3, 5, 7], (1..4).each_cons(2).map(&:sum)
+        assert [[[1, 2], 0], [[3], 1]], [1, 2, 3].each_slice(2).with_index.map {|x, i| [x, i] }
+        class Foo
+          include Enumerable
+          def each
+            yield 1
+            yield 2
+            yield 3
+          end
+        end
+        assert [[1, 2], [3]], Foo.new.each_slice(2).map {|x| x }
+        assert [2, 1], [1, 2, 3].each.with_index {|x, i| break [x, i] if i == 1 }
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn enumerator_next() {
+        let program = r#"
+        e = [1, 2, 3].each
+        assert(3, e.size)
+        assert(1, e.next)
+        assert(2, e.peek)
+        assert(2, e.next)
+        assert(3, e.next)
+        r = begin
+            e.next
+        rescue StopIteration => err
+            err
+        end
+        assert("iteration reached an end", r.message)
+        assert([1, 2, 3], r.result)
+        e.rewind
+        assert(1, e.next)
+        a = []
+        e = 3.times
+        assert(3, e.size)
+        r = loop do
+            a << e.next
+        end
+        assert([0, 1, 2], a)
+        assert(3, r)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn enumerator_yielder() {
+        let program = r#"
+        fib = Enumerator.new do |y|
+            a, b = 0, 1
+            loop do
+                y << a
+                a, b = b, a + b
+            end
+        end
+        assert(nil, fib.size)
+        assert([0, 1, 1, 2, 3, 5, 8], 7.times.map { fib.next })
+        a = []
+        fib.each do |x|
+            break if x > 10
+            a << x
+        end
+        assert([0, 1, 1, 2, 3, 5, 8], a)
+        e = Enumerator.new(2) do |y|
+            y.yield 1, 2
+            y << 3 << 4
+            :done
+        end
+        assert(2, e.size)
+        assert([[1, 2], 3, 4], e.map { |x| x })
+        assert([1, 2], e.next)
+        assert(3, e.next)
+        assert(4, e.next)
+        r = loop { e.next }
+        assert(:done, r)
+        e = Enumerator.new do |y|
+            assert([], y.instance_variables)
+            y << 1
+        end
+        assert([1], e.to_a)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn enumerator_size() {
+        let program = r#"
+        assert(4, [1, 2, 3, 4].map.size)
+        assert(5, (1..5).each.size)
+        assert(4, (1...5).each_with_index.size)
+        assert(2, {a: 1, b: 2}.map.size)
+        assert(3, [1, 2, 3].each.with_index.size)
+        assert(0, -3.times.size)
+        assert(nil, [1, 2, 3].select.size)
+        assert(nil, 3.step(10).size)
+        S = Struct.new(:a, :b)
+        assert(nil, S.new(1, 2).each.size)
         "#;
         assert_script(program);
     }
@@ -1,7 +1,250 @@
 use crate::*;
 
+/// The internal state of an exception object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExceptionInfo {
+    /// The message given to Exception.new, or nil.
+    pub message: Value,
+    /// The backtrace, which is set when the exception is raised. nil until then.
+    pub backtrace: Value,
+    /// The exception which was being handled when the exception was raised.
+    /// None until the exception is raised.
+    pub cause: Option<Value>,
+    /// The return value of the iteration. (StopIteration only)
+    pub result: Value,
+}
+
+impl ExceptionInfo {
+    pub fn new(message: Value) -> Self {
+        ExceptionInfo {
+            message,
+            backtrace: Value::nil(),
+            cause: None,
+            result: Value::nil(),
+        }
+    }
+}
+
+impl GC for ExceptionInfo {
+    fn mark(&self, alloc: &mut Allocator) {
+        self.message.mark(alloc);
+        self.backtrace.mark(alloc);
+        if let Some(cause) = self.cause {
+            cause.mark(alloc);
+        }
+        self.result.mark(alloc);
+    }
+}
+
+pub type ExceptionRef = Ref<ExceptionInfo>;
+
+impl ExceptionRef {
+    pub fn from(message: Value) -> Self {
+        ExceptionRef::new(ExceptionInfo::new(message))
+    }
+}
+
 pub fn init_error(globals: &mut Globals) -> Value {
-    let id = globals.get_ident_id("RuntimeError");
-    let class = ClassRef::from(id, globals.builtins.object);
-    Value::class(globals, class)
+    let exception = define_error_class(globals, "Exception", globals.builtins.object);
+    let class = exception.as_class();
+    globals.add_builtin_instance_method(class, "initialize", initialize);
+    globals.add_builtin_instance_method(class, "message", message);
+    globals.add_builtin_instance_method(class, "to_s", to_s);
+    globals.add_builtin_instance_method(class, "inspect", inspect);
+    globals.add_builtin_instance_method(class, "backtrace", backtrace);
+    globals.add_builtin_instance_method(class, "full_message", full_message);
+    globals.add_builtin_instance_method(class, "cause", cause);
+
+    let script_error = define_error_class(globals, "ScriptError", exception);
+    define_error_class(globals, "NotImplementedError", script_error);
+    let standard_error = define_error_class(globals, "StandardError", exception);
+    define_error_class(globals, "RuntimeError", standard_error);
+    define_error_class(globals, "ArgumentError", standard_error);
+    let name_error = define_error_class(globals, "NameError", standard_error);
+    define_error_class(globals, "NoMethodError", name_error);
+    define_error_class(globals, "TypeError", standard_error);
+    let index_error = define_error_class(globals, "IndexError", standard_error);
+    define_error_class(globals, "KeyError", index_error);
+    let stop_iteration = define_error_class(globals, "StopIteration", index_error);
+    globals.add_builtin_instance_method(stop_iteration.as_class(), "result", result);
+    define_error_class(globals, "RangeError", standard_error);
+    define_error_class(globals, "ZeroDivisionError", standard_error);
+    define_error_class(globals, "RegexpError", standard_error);
+    define_error_class(globals, "FiberError", standard_error);
+    define_error_class(globals, "LocalJumpError", standard_error);
+    exception
+}
+
+fn define_error_class(globals: &mut Globals, name: &str, superclass: Value) -> Value {
+    let id = globals.get_ident_id(name);
+    let class = Value::class_from(globals, id, superclass);
+    globals.builtins.object.set_var(id, class);
+    class
+}
+
+/// Get the exception class which corresponds to `kind`.
+pub fn get_error_class(globals: &mut Globals, kind: &RuntimeErrKind) -> Value {
+    let id = globals.get_ident_id(kind.class_name());
+    globals.builtins.object.get_var(id).unwrap()
+}
+
+/// Examine whether instances of `class` are exception objects.
+pub fn is_exception_class(globals: &Globals, class: Value) -> bool {
+    let exception = globals.builtins.exception;
+    let mut class = class;
+    while !class.is_nil() {
+        if class.id() == exception.id() {
+            return true;
+        }
+        class = class.as_class().superclass;
+    }
+    false
+}
+
+/// Get the message of `exception`, or the class name if the message is not set.
+pub fn get_message(vm: &mut VM, exception: Value) -> String {
+    match exception.as_exception() {
+        Some(eref) if !eref.message.is_nil() => vm.val_to_s(eref.message),
+        _ => vm.globals.get_class_name(exception),
+    }
+}
+
+fn expect_exception(vm: &mut VM, val: Value) -> Result<ExceptionRef, RubyError> {
+    match val.as_exception() {
+        Some(eref) => Ok(eref),
+        None => Err(vm.error_type("Receiver must be an Exception.")),
+    }
+}
+
+// Instance methods
+
+fn initialize(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let mut eref = expect_exception(vm, self_val)?;
+    if args.len() == 1 {
+        eref.message = args[0];
+    }
+    Ok(Value::nil())
+}
+
+fn message(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let id = vm.globals.get_ident_id("to_s");
+    vm.send0(self_val, id)
+}
+
+fn to_s(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let message = get_message(vm, self_val);
+    Ok(Value::string(&vm.globals, message))
+}
+
+fn inspect(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let class_name = vm.globals.get_class_name(self_val);
+    let id = vm.globals.get_ident_id("to_s");
+    let message = vm.send0(self_val, id)?;
+    let message = vm.val_to_s(message);
+    let s = if message.is_empty() {
+        class_name
+    } else {
+        format!("#<{}: {}>", class_name, message)
+    };
+    Ok(Value::string(&vm.globals, s))
+}
+
+/// Get the backtrace of `self`, or nil if `self` has not been raised.
+fn backtrace(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let eref = expect_exception(vm, self_val)?;
+    Ok(eref.backtrace)
+}
+
+/// Get the exception which was being handled when `self` was raised.
+fn cause(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let eref = expect_exception(vm, self_val)?;
+    Ok(eref.cause.unwrap_or_default())
+}
+
+/// Get the return value of the iteration which raised StopIteration.
+fn result(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let eref = expect_exception(vm, self_val)?;
+    Ok(eref.result)
+}
+
+/// Get the formatted message of `self` with its backtrace.
+fn full_message(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let class_name = vm.globals.get_class_name(self_val);
+    let message = get_message(vm, self_val);
+    let eref = expect_exception(vm, self_val)?;
+    let backtrace = match eref.backtrace.as_array() {
+        Some(aref) => aref.elements.iter().map(|val| vm.val_to_s(*val)).collect(),
+        None => vec![],
+    };
+    let mut s = match backtrace.first() {
+        Some(pos) => format!("{}: {} ({})", pos, message, class_name),
+        None => format!("{} ({})", message, class_name),
+    };
+    for pos in backtrace.iter().skip(1) {
+        s = format!("{}\n\tfrom {}", s, pos);
+    }
+    Ok(Value::string(&vm.globals, s))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn exception_class() {
+        let program = r##"
+        assert(StandardError, RuntimeError.superclass)
+        assert(Exception, StandardError.superclass)
+        assert(NameError, NoMethodError.superclass)
+        assert(IndexError, StopIteration.superclass)
+        e = RuntimeError.new("boo")
+        assert("boo", e.message)
+        assert(RuntimeError, e.class)
+        assert(nil, e.backtrace)
+        assert("#<RuntimeError: boo>", e.inspect)
+        assert("TypeError", TypeError.new.message)
+        assert([], e.instance_variables)
+        assert(ArgumentError, (Object.new(1) rescue $!).class)
+        "##;
+        assert_script(program);
+    }
+
+    #[test]
+    fn exception_rescue() {
+        let program = r##"
+        def foo
+          1 + nil
+        rescue NoMethodError => e
+          :nomethod
+        rescue TypeError => e
+          e
+        end
+        e = foo
+        assert(TypeError, e.class)
+        assert(1, e.backtrace.size)
+        assert(e.backtrace[0] + ": " + e.message + " (TypeError)", e.full_message)
+        assert("#<TypeError: nil can't be coerced into Integer>", e.inspect)
+        e = begin
+          1 / 0
+        rescue ZeroDivisionError => e
+          e
+        end
+        assert("divided by 0", e.message)
+        r = begin
+          [[1], [2, 3]].transpose
+        rescue StandardError => e
+          e.class
+        end
+        assert(IndexError, r)
+        "##;
+        assert_script(program);
+    }
 }
@@ -1,29 +1,148 @@
+use crate::coroutine::{Cancelled, Coroutine};
 use crate::*;
-use std::sync::mpsc::{Receiver, SyncSender};
-use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FiberState {
+    Created,
+    Running,
+    Suspended,
+    Dead,
+}
+
+#[derive(Debug)]
+pub enum FiberKind {
+    /// The root fiber of the main VM.
+    Root,
+    /// A fiber which runs the block context.
+    Block(ContextRef),
+    /// A fiber which runs the iteration of the Enumerator for external iteration.
+    /// Each element is passed to the resumer with Fiber.yield.
+    /// The context is used as the caller context of the iteration.
+    Enum(Value, ContextRef),
+}
 
 #[derive(Debug)]
 pub struct FiberInfo {
-    vm: VMRef,
-    context: ContextRef,
-    rec: Receiver<VMResult>,
-    tx: SyncSender<usize>,
+    pub vm: VMRef,
+    kind: FiberKind,
+    pub state: FiberState,
+    /// Created when the fiber is resumed for the first time.
+    coroutine: Option<Coroutine>,
+    /// Arguments of the first resume, which are passed to the block.
+    args: Args,
+    /// The value passed between Fiber#resume and Fiber.yield.
+    transfer: Option<VMResult>,
+}
+
+impl GC for FiberInfo {
+    fn mark(&self, alloc: &mut Allocator) {
+        match self.kind {
+            FiberKind::Root => {}
+            FiberKind::Block(context) => {
+                self.vm.mark(alloc);
+                context.mark(alloc);
+            }
+            FiberKind::Enum(enumerator, context) => {
+                self.vm.mark(alloc);
+                enumerator.mark(alloc);
+                context.mark(alloc);
+            }
+        }
+        self.args.mark(alloc);
+        if let Some(Ok(val)) = &self.transfer {
+            val.mark(alloc);
+        }
+    }
+}
+
+impl Drop for FiberInfo {
+    fn drop(&mut self) {
+        // The VM of the root fiber is not owned by the fiber.
+        match self.kind {
+            FiberKind::Root => {}
+            // The context of a fiber is owned by the fiber.
+            FiberKind::Block(context) | FiberKind::Enum(_, context) => {
+                context.free();
+                self.vm.free();
+            }
+        }
+    }
 }
 
 pub type FiberRef = Ref<FiberInfo>;
 
 impl FiberInfo {
-    pub fn new(
-        vm: VMRef,
-        context: ContextRef,
-        rec: Receiver<VMResult>,
-        tx: SyncSender<usize>,
-    ) -> Self {
+    pub fn new(vm: VMRef, kind: FiberKind) -> Self {
+        let state = match kind {
+            FiberKind::Root => FiberState::Running,
+            _ => FiberState::Created,
+        };
         FiberInfo {
             vm,
-            context,
-            rec,
-            tx,
+            kind,
+            state,
+            coroutine: None,
+            args: Args::new0(),
+            transfer: None,
+        }
+    }
+
+    /// Release the stack and the frames of a dead fiber, which are never used again.
+    fn free_frames(&mut self) {
+        self.coroutine = None;
+        self.vm.clear();
+    }
+}
+
+/// Entry point of the coroutine of a fiber. `arg` is a pointer to FiberInfo.
+/// A panic must not unwind across this function, so it is converted to an error
+/// which is passed to the resumer. Only `Cancelled` of a dropped coroutine unwinds
+/// across this function, and the fiber must not be touched in that case.
+extern "C-unwind" fn fiber_start(arg: usize) -> ! {
+    let mut fiber = FiberRef::from_ref(unsafe { &*(arg as *const FiberInfo) });
+    let mut vm = fiber.vm;
+    // The coroutine may run on its own thread.
+    Allocator::set_current(vm.globals.allocator);
+    let _current = CurrentVM::enter(vm);
+    let args = std::mem::replace(&mut fiber.args, Args::new0());
+    let res = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        run_fiber(&mut vm, &fiber.kind, &args)
+    })) {
+        Ok(res) => res,
+        Err(payload) if payload.is::<Cancelled>() => std::panic::resume_unwind(payload),
+        Err(_) => Err(vm.error_internal("Panic occured in the fiber.")),
+    };
+    fiber.state = FiberState::Dead;
+    fiber.transfer = Some(res);
+    unsafe { fiber.coroutine.as_mut().unwrap().suspend() };
+    unreachable!("Dead fiber was resumed.");
+}
+
+fn run_fiber(vm: &mut VM, kind: &FiberKind, args: &Args) -> VMResult {
+    match *kind {
+        FiberKind::Root => unreachable!("Root fiber was started."),
+        FiberKind::Block(context) => {
+            let mut context = Context::from_args(
+                vm,
+                context.self_value,
+                context.iseq_ref,
+                args,
+                context.outer,
+            )?;
+            context.is_fiber = true;
+            vm.run_context(ContextRef::from_local(&context))
+        }
+        FiberKind::Enum(enumerator, context) => {
+            let eref = enumerator.as_enumerator().unwrap();
+            vm.context_push(context);
+            let id = vm.globals.get_ident_id("yield");
+            let fiber_class = vm.globals.builtins.fiber;
+            let res = match vm.get_method(fiber_class, id) {
+                Ok(block) => eref.eval_with_block(vm, block),
+                Err(err) => Err(err),
+            };
+            vm.context_pop();
+            res
         }
     }
 }
@@ -34,88 +153,115 @@ pub fn init_fiber(globals: &mut Globals) -> Value {
     let val = Value::class(globals, class);
     globals.add_builtin_instance_method(class, "inspect", inspect);
     globals.add_builtin_instance_method(class, "resume", resume);
+    globals.add_builtin_instance_method(class, "alive?", alive);
     globals.add_builtin_class_method(val, "new", new);
     globals.add_builtin_class_method(val, "yield", yield_);
+    globals.add_builtin_class_method(val, "current", current);
     val
 }
 
+fn pack_args(vm: &VM, args: &Args) -> Value {
+    match args.len() {
+        0 => Value::nil(),
+        1 => args[0],
+        _ => Value::array_from(&vm.globals, args.to_vec()),
+    }
+}
+
 // Class methods
 
 fn new(vm: &mut VM, _: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 0)?;
     let method = vm.expect_block(args.block)?;
-    let mut context = vm.create_block_context(method)?;
-    context.is_fiber = true;
-    let (tx0, rx0) = std::sync::mpsc::sync_channel(0);
-    let (tx1, rx1) = std::sync::mpsc::sync_channel(0);
-    let new_vm = vm.dup_fiber(tx0, rx1);
-    let val = Value::fiber(&vm.globals, VMRef::new(new_vm), context, rx0, tx1);
-    Ok(val)
+    let context = vm.create_block_context(method)?;
+    Ok(new_fiber(vm, FiberKind::Block(context)))
 }
 
+fn new_fiber(vm: &mut VM, kind: FiberKind) -> Value {
+    let mut fiber_vm = VMRef::new(vm.dup_fiber());
+    let val = Value::fiber(&vm.globals, fiber_vm, kind);
+    fiber_vm.fiber = Some(val);
+    val
+}
+
+/// Create a new fiber which runs the iteration of `enumerator`.
+/// Each element of the iteration is returned by Fiber#resume.
+pub fn new_enum_fiber(vm: &mut VM, enumerator: Value) -> Value {
+    let context = vm.dup_context();
+    new_fiber(vm, FiberKind::Enum(enumerator, context))
+}
+
+/// Suspend the current fiber, and return `args` to the resumer.
+/// Return the arguments of the next Fiber#resume.
 fn yield_(vm: &mut VM, _: Value, args: &Args) -> VMResult {
-    let val = match args.len() {
-        0 => Value::nil(),
-        1 => args[0],
-        _ => Value::array_from(&vm.globals, args.to_vec()),
-    };
-    if vm.channel.is_none() {
-        return Err(vm.error_fiber("Can not yield from main fiber."));
+    let mut fiber = match vm.fiber.and_then(|fiber| fiber.as_fiber()) {
+        Some(fiber) if fiber.coroutine.is_some() => fiber,
+        _ => return Err(vm.error_fiber("Can not yield from main fiber.")),
     };
-    vm.fiber_send_to_parent(Ok(val));
-    Ok(Value::nil())
+    fiber.transfer = Some(Ok(pack_args(vm, args)));
+    unsafe { fiber.coroutine.as_mut().unwrap().suspend() };
+    fiber.transfer.take().unwrap()
+}
+
+/// Get the current fiber.
+fn current(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    match vm.fiber {
+        Some(fiber) => Ok(fiber),
+        None => {
+            let fiber = Value::fiber(&vm.globals, VMRef::from_ref(vm), FiberKind::Root);
+            vm.fiber = Some(fiber);
+            Ok(fiber)
+        }
+    }
 }
 
 // Instance methods
 
 fn inspect(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
     let fref = vm.expect_fiber(self_val, "Expect Fiber.")?;
-    let inspect = format!(
-        "#<Fiber:0x{:<016x} ({:?})>",
-        fref.id(),
-        fref.vm.fiberstate(),
-    );
+    let inspect = format!("#<Fiber:0x{:<016x} ({:?})>", fref.id(), fref.state);
     Ok(Value::string(&vm.globals, inspect))
 }
 
+/// Resume the fiber with `args`, and return the value passed to Fiber.yield,
+/// or the value of the block when the fiber finished.
+/// An exception raised in the fiber is propagated to the resumer.
 fn resume(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
-    vm.check_args_num(args.len(), 0)?;
-    match self_val.unpack() {
-        RV::Object(obj) => match &obj.kind {
-            ObjKind::Fiber(fiber) => {
-                let mut context = fiber.context;
-                context.is_fiber = true;
-                let mut fiber_vm = fiber.vm;
-                match fiber_vm.fiberstate() {
-                    FiberState::Dead => {
-                        return Err(vm.error_fiber("Dead fiber called."));
-                    }
-                    FiberState::Created => {
-                        fiber_vm.fiberstate_running();
-                        #[cfg(feature = "trace")]
-                        {
-                            println!("===> resume(spawn)");
-                        }
-                        let mut vm2 = fiber_vm;
-                        thread::spawn(move || vm2.run_context(context));
-                        let res = fiber.rec.recv().unwrap()?;
-                        return Ok(res);
-                    }
-                    FiberState::Running => {
-                        #[cfg(feature = "trace")]
-                        {
-                            println!("===> resume");
-                        }
-                        fiber.tx.send(1).unwrap();
-                        let res = fiber.rec.recv().unwrap()?;
-                        return Ok(res);
-                    }
-                }
-            }
-            _ => unreachable!(),
-        },
+    let fiber = vm.expect_fiber(self_val, "Expect Fiber.")?;
+    resume_fiber(vm, fiber, args)
+}
+
+pub fn resume_fiber(vm: &mut VM, mut fiber: FiberRef, args: &Args) -> VMResult {
+    match fiber.state {
+        FiberState::Dead => return Err(vm.error_fiber("Dead fiber called.")),
+        FiberState::Running => return Err(vm.error_fiber("Double resume.")),
+        FiberState::Created => {
+            fiber.args = args.clone();
+            let arg = fiber.id() as usize;
+            fiber.coroutine = Some(Coroutine::new(fiber_start, arg));
+        }
+        FiberState::Suspended => fiber.transfer = Some(Ok(pack_args(vm, args))),
+    }
+    fiber.vm.parent_vm = Some(VMRef::from_ref(vm));
+    fiber.state = FiberState::Running;
+    {
+        let _current = CurrentVM::enter(fiber.vm);
+        unsafe { fiber.coroutine.as_mut().unwrap().resume() };
+    }
+    fiber.vm.parent_vm = None;
+    match fiber.state {
+        FiberState::Running => fiber.state = FiberState::Suspended,
+        FiberState::Dead => fiber.free_frames(),
         _ => unreachable!(),
-    };
+    }
+    fiber.transfer.take().unwrap()
+}
+
+fn alive(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let fiber = vm.expect_fiber(self_val, "Expect Fiber.")?;
+    Ok(Value::bool(fiber.state != FiberState::Dead))
 }
 
 #[cfg(test)]
@@ -159,4 +305,129 @@ mod test {
         "#;
         assert_script(program);
     }
+
+    #[test]
+    fn fiber_resume_args() {
+        let program = r#"
+        f = Fiber.new do |a, b|
+            c = Fiber.yield(a + b)
+            d, e = Fiber.yield(c * 2)
+            d + e
+        end
+        assert(true, f.alive?)
+        assert(3, f.resume(1, 2))
+        assert(20, f.resume(10))
+        assert(true, f.alive?)
+        assert(7, f.resume(3, 4))
+        assert(false, f.alive?)
+        e = begin
+            f.resume
+        rescue FiberError => e
+            e
+        end
+        assert("Dead fiber called.", e.message)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn fiber_many() {
+        let program = r#"
+        res = 0
+        fibers = []
+        3000.times do |i|
+            f = Fiber.new do
+                Fiber.yield i
+                i * 2
+            end
+            res += f.resume
+            res += f.resume
+            fibers << Fiber.new { Fiber.yield i } if i % 3 == 0
+        end
+        assert(3 * 2999 * 3000 / 2, res)
+        fibers.each {|f| f.resume }
+        fibers = nil
+        GC.start
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn fiber_current() {
+        let program = r#"
+        root = Fiber.current
+        assert(root, Fiber.current)
+        assert(true, root.alive?)
+        inner = nil
+        f = Fiber.new do
+            inner = Fiber.current
+            Fiber.yield
+        end
+        f.resume
+        assert(f, inner)
+        assert(root, Fiber.current)
+        e = begin
+            Fiber.yield
+        rescue FiberError => e
+            e
+        end
+        assert("Can not yield from main fiber.", e.message)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn fiber_error() {
+        let program = r#"
+        f = Fiber.new do
+            Fiber.yield 1
+            raise ArgumentError, "boo"
+        end
+        assert(1, f.resume)
+        e = begin
+            f.resume
+        rescue ArgumentError => e
+            e
+        end
+        assert("boo", e.message)
+        assert(false, f.alive?)
+        f = Fiber.new { f.resume }
+        e = begin
+            f.resume
+        rescue FiberError => e
+            e
+        end
+        assert("Double resume.", e.message)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn fiber_nested() {
+        let program = r#"
+        inner = Fiber.new do
+            Fiber.yield :a
+            :b
+        end
+        outer = Fiber.new do
+            x = inner.resume
+            Fiber.yield x
+            y = inner.resume
+            Fiber.yield y
+            :c
+        end
+        assert(:a, outer.resume)
+        assert(:b, outer.resume)
+        assert(:c, outer.resume)
+        a = []
+        f = Fiber.new do
+            10000.times {|i| a << [i.to_s] }
+            GC.start
+            a.size
+        end
+        assert(10000, f.resume)
+        assert(["9999"], a[9999])
+        "#;
+        assert_script(program);
+    }
 }
@@ -1,27 +1,145 @@
 use crate::*;
+use num::ToPrimitive;
+use std::cmp::Ordering;
 
 pub fn init(globals: &mut Globals) -> Value {
     let id = globals.get_ident_id("Float");
     let class = ClassRef::from(id, globals.builtins.object);
+    globals.add_builtin_instance_method(class, "+", add);
+    globals.add_builtin_instance_method(class, "-", sub);
+    globals.add_builtin_instance_method(class, "*", mul);
+    globals.add_builtin_instance_method(class, "/", div);
+    globals.add_builtin_instance_method(class, "%", rem);
+    globals.add_builtin_instance_method(class, "**", pow);
+    globals.add_builtin_instance_method(class, "==", eq);
+    globals.add_builtin_instance_method(class, "!=", neq);
+    globals.add_builtin_instance_method(class, ">=", ge);
+    globals.add_builtin_instance_method(class, ">", gt);
+    globals.add_builtin_instance_method(class, "<=", le);
+    globals.add_builtin_instance_method(class, "<", lt);
     globals.add_builtin_instance_method(class, "<=>", cmp);
     globals.add_builtin_instance_method(class, "floor", floor);
     Value::class(globals, class)
 }
 
+/// Convert `val` to f64 if `val` is a number.
+fn to_f64(val: Value) -> Option<f64> {
+    match val.unpack() {
+        RV::Integer(i) => Some(i as f64),
+        RV::Float(f) => Some(f),
+        _ => val.as_bignum().map(|n| n.to_f64().unwrap()),
+    }
+}
+
+/// Compare Float `lhs` with `rhs`.
+/// Return None if `rhs` is not a number or the comparison is not possible (e.g. NaN).
+fn partial_cmp(lhs: f64, rhs: Value) -> Option<Ordering> {
+    match rhs.as_bignum() {
+        Some(rhs) => builtin::integer::cmp_bigint_float(rhs, lhs).map(|ord| ord.reverse()),
+        None => lhs.partial_cmp(&to_f64(rhs)?),
+    }
+}
+
+/// The remainder of `lhs` / `rhs` which has the same sign as `rhs`.
+pub fn rem_floor(lhs: f64, rhs: f64) -> f64 {
+    let rem = lhs % rhs;
+    if rem != 0.0 && (rem < 0.0) != (rhs < 0.0) {
+        rem + rhs
+    } else {
+        rem
+    }
+}
+
 // Class methods
 
 // Instance methods
 
-fn cmp(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
-    //use std::cmp::Ordering;
+macro_rules! define_arith {
+    ($vm:ident, $self_val:ident, $args:ident, $op:expr) => {
+        $vm.check_args_num($args.len(), 1)?;
+        let lhs = $vm.expect_flonum($self_val, "Receiver")?;
+        match to_f64($args[0]) {
+            Some(rhs) => return Ok(Value::flonum($op(lhs, rhs))),
+            None => return Err($vm.error_cant_coerce($args[0], "Float")),
+        }
+    };
+}
+
+fn add(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_arith!(vm, self_val, args, |lhs, rhs| lhs + rhs);
+}
+
+fn sub(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_arith!(vm, self_val, args, |lhs, rhs| lhs - rhs);
+}
+
+fn mul(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_arith!(vm, self_val, args, |lhs, rhs| lhs * rhs);
+}
+
+fn div(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_arith!(vm, self_val, args, |lhs, rhs| lhs / rhs);
+}
+
+fn rem(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_arith!(vm, self_val, args, rem_floor);
+}
+
+fn pow(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_arith!(vm, self_val, args, f64::powf);
+}
+
+fn eq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let lhs = vm.expect_flonum(self_val, "Receiver")?;
+    let res = partial_cmp(lhs, args[0]) == Some(Ordering::Equal);
+    Ok(Value::bool(res))
+}
+
+fn neq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 1)?;
     let lhs = vm.expect_flonum(self_val, "Receiver")?;
-    let res = match args[0].unpack() {
-        RV::Integer(rhs) => lhs.partial_cmp(&(rhs as f64)),
-        RV::Float(rhs) => lhs.partial_cmp(&rhs),
-        _ => return Ok(Value::nil()),
+    let res = partial_cmp(lhs, args[0]) != Some(Ordering::Equal);
+    Ok(Value::bool(res))
+}
+
+macro_rules! define_cmp {
+    ($vm:ident, $self_val:ident, $args:ident, $op:ident) => {
+        $vm.check_args_num($args.len(), 1)?;
+        let lhs = $vm.expect_flonum($self_val, "Receiver")?;
+        match partial_cmp(lhs, $args[0]) {
+            Some(ord) => return Ok(Value::bool(ord.$op(&Ordering::Equal))),
+            None if to_f64($args[0]).is_some() => return Ok(Value::false_val()),
+            None => {
+                return Err($vm.error_argument(format!(
+                    "Comparison of Float with {} failed.",
+                    $vm.globals.get_class_name($args[0])
+                )))
+            }
+        }
     };
-    match res {
+}
+
+fn ge(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_cmp!(vm, self_val, args, ge);
+}
+
+fn gt(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_cmp!(vm, self_val, args, gt);
+}
+
+fn le(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_cmp!(vm, self_val, args, le);
+}
+
+fn lt(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_cmp!(vm, self_val, args, lt);
+}
+
+fn cmp(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let lhs = vm.expect_flonum(self_val, "Receiver")?;
+    match partial_cmp(lhs, args[0]) {
         Some(ord) => Ok(Value::fixnum(ord as i64)),
         None => Ok(Value::nil()),
     }
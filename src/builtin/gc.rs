@@ -0,0 +1,96 @@
+use crate::*;
+
+pub fn init_gc(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("GC");
+    let class = ClassRef::from(id, globals.builtins.object);
+    let obj = Value::class(globals, class);
+    globals.add_builtin_class_method(obj, "start", start);
+    globals.add_builtin_class_method(obj, "count", count);
+    globals.add_builtin_class_method(obj, "stat", stat);
+    globals.add_builtin_class_method(obj, "disable", disable);
+    globals.add_builtin_class_method(obj, "enable", enable);
+    obj
+}
+
+// Class methods
+
+fn start(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    vm.gc_start();
+    Ok(Value::nil())
+}
+
+fn count(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let count = vm.globals.allocator.count();
+    Ok(Value::fixnum(count as i64))
+}
+
+fn stat(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut hash = OrderedMap::new();
+    for (name, num) in vm.globals.allocator.stat() {
+        let id = vm.globals.get_ident_id(name);
+        hash.insert(HashKey(Value::symbol(id)), Value::fixnum(num as i64));
+    }
+    Ok(Value::hash_from(&vm.globals, hash))
+}
+
+/// Disable GC, and return true if GC was already disabled.
+fn disable(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let prev = vm.globals.allocator.set_disabled(true);
+    Ok(Value::bool(prev))
+}
+
+/// Enable GC, and return true if GC was disabled.
+fn enable(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let prev = vm.globals.allocator.set_disabled(false);
+    Ok(Value::bool(prev))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn gc() {
+        let program = r#"
+        a = []
+        10000.times { |i| a << "string#{i}"; "garbage#{i}" }
+        count = GC.count
+        GC.start
+        assert(count + 1, GC.count)
+        assert("string9999", a[9999])
+        assert(true, GC.stat[:heap_live_slots] >= 10000)
+        assert(false, GC.disable)
+        assert(true, GC.disable)
+        GC.start
+        assert(count + 1, GC.count)
+        assert(true, GC.enable)
+        assert(false, GC.enable)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn gc_free_objects() {
+        let program = r#"
+        keep = []
+        3000.times do |i|
+          c = Class.new
+          pr = proc { i }
+          keep << pr.dup if i % 100 == 0
+          keep << [1, 2].each if i % 100 == 0
+          1.method(:+)
+          RuntimeError.new("e#{i}")
+        end
+        GC.start
+        assert(0, keep[0].call)
+        assert(2900, keep[58].call)
+        assert(1, keep[59].next)
+        "#;
+        assert_script(program);
+    }
+}
@@ -1,5 +1,4 @@
 use crate::*;
-use std::collections::HashMap;
 
 pub fn init_hash(globals: &mut Globals) -> Value {
     let id = globals.get_ident_id("Hash");
@@ -66,7 +65,7 @@ fn compact(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
 fn delete(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 1)?;
     let mut hash = vm.expect_hash(self_val, "Receiver")?;
-    let res = match hash.remove(args[0]) {
+    let res = match hash.remove(args[0])? {
         Some(v) => v,
         None => Value::nil(),
     };
@@ -82,14 +81,16 @@ fn empty(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
 fn select(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let hash = vm.expect_hash(self_val, "Receiver")?;
     let method = vm.expect_block(args.block)?;
-    let mut res = HashMap::new();
+    let mut res = OrderedMap::new();
     let mut arg = Args::new2(Value::nil(), Value::nil());
     for (k, v) in hash.iter() {
         arg[0] = k;
         arg[1] = v;
         let b = vm.eval_block(method, &arg)?;
         if vm.val_to_bool(b) {
-            res.insert(HashKey(k), v);
+            vm.temp_push(k);
+            vm.temp_push(v);
+            HashInfo::catch_key_error(|| res.insert(HashKey(k), v))?;
         };
     }
 
@@ -99,7 +100,7 @@ fn select(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
 fn has_key(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 1)?;
     let hash = vm.expect_hash(self_val, "Receiver")?;
-    let res = hash.contains_key(args[0]);
+    let res = hash.contains_key(args[0])?;
     Ok(Value::bool(res))
 }
 
@@ -176,7 +177,7 @@ fn merge(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     for arg in args.iter() {
         let other = vm.expect_hash(*arg, "First arg")?;
         for (k, v) in other.iter() {
-            new.insert(k, v);
+            new.insert(k, v)?;
         }
     }
 
@@ -192,8 +193,8 @@ fn fetch(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
         Value::nil()
     };
     let hash = vm.expect_hash(self_val, "Receiver")?;
-    let val = match hash.get(&key) {
-        Some(val) => val.clone(),
+    let val = match hash.get(&key)? {
+        Some(val) => val,
         None => default,
     };
 
@@ -206,7 +207,7 @@ fn compare_by_identity(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let inner = hash.inner_mut();
     match inner {
         HashInfo::Map(map) => {
-            let new_map = map.into_iter().map(|(k, v)| (IdentKey(k.0), *v)).collect();
+            let new_map = map.iter().map(|(k, v)| (IdentKey(k.0), *v)).collect();
             *inner = HashInfo::IdentMap(new_map);
         }
         HashInfo::IdentMap(_) => {}
@@ -230,10 +231,12 @@ fn sort(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
 fn invert(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 0)?;
     let hash = vm.expect_hash(self_val, "Receiver")?;
-    let mut new_hash = HashMap::new();
-    for (k, v) in hash.iter() {
-        new_hash.insert(HashKey(v), k);
-    }
+    let mut new_hash = OrderedMap::new();
+    HashInfo::catch_key_error(|| {
+        for (k, v) in hash.iter() {
+            new_hash.insert(HashKey(v), k);
+        }
+    })?;
     Ok(Value::hash_from(&vm.globals, new_hash))
 }
 
@@ -347,4 +350,118 @@ mod test {
         "#;
         assert_script(program);
     }
+
+    #[test]
+    fn hash_order() {
+        let program = r#"
+        h = {z: 1, a: 2, m: 3, b: 4}
+        assert([:z, :a, :m, :b], h.keys)
+        assert([1, 2, 3, 4], h.values)
+        h.delete(:a)
+        h[:c] = 5
+        h[:z] = 9
+        assert([[:z, 9], [:m, 3], [:b, 4], [:c, 5]], h.to_a)
+        assert("{:z=>9, :m=>3, :b=>4, :c=>5}", h.inspect)
+        a = []
+        h.each { |k, v| a << k }
+        assert([:z, :m, :b, :c], a)
+        assert({1=>3}, {1=>2, 1=>3})
+        h.compare_by_identity
+        assert([:z, :m, :b, :c], h.keys)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn hash_delete_many() {
+        let program = r#"
+        h = {}
+        30000.times { |i| h[i] = i }
+        29990.times { |i| h.delete(i) }
+        assert([29990, 29991, 29992, 29993, 29994, 29995, 29996, 29997, 29998, 29999], h.keys)
+        h.delete(29995)
+        h[0] = 0
+        h[29993] = 7
+        assert([29990, 29991, 29992, 29993, 29994, 29996, 29997, 29998, 29999, 0], h.keys)
+        assert(7, h[29993])
+        assert(nil, h[29995])
+        assert(10, h.size)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn hash_key_error() {
+        let program = r#"
+        class BadHash
+            def hash
+                raise "hash failed"
+            end
+        end
+        class BadEql
+            def hash
+                1
+            end
+            def eql?(other)
+                raise "eql? failed"
+            end
+        end
+        h = {0 => 0}
+        def check(msg)
+            yield
+            raise "not raised"
+        rescue => e
+            assert(msg, e.message)
+        end
+        check("hash failed") { h[BadHash.new] = 1 }
+        check("hash failed") { h[BadHash.new] }
+        check("hash failed") { {BadHash.new => 1} }
+        check("hash failed") { [BadHash.new].uniq }
+        h[BadEql.new] = 1
+        check("eql? failed") { h[BadEql.new] }
+        check("eql? failed") { h.delete(BadEql.new) }
+        assert(2, h.size)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn hash_user_key() {
+        let program = r#"
+        class Key
+            attr_reader :name
+            def initialize(name)
+                @name = name
+            end
+            def eql?(other)
+                other.is_a?(Key) && name == other.name
+            end
+            def hash
+                name.hash
+            end
+        end
+        h = {}
+        h[Key.new("a")] = 1
+        h[Key.new("b")] = 2
+        h[Key.new("a")] = 3
+        assert(2, h.size)
+        assert(3, h[Key.new("a")])
+        assert(true, h.has_key?(Key.new("b")))
+        assert(nil, h[Key.new("c")])
+        assert(3, h[[Key.new("a")]] = 3)
+        assert(3, h[[Key.new("a")]])
+        o = Object.new
+        h[o] = 4
+        assert(4, h[o])
+        assert(nil, h[Object.new])
+        Point = Struct.new(:x, :y)
+        s = {Point.new(1, 2) => :p}
+        assert(:p, s[Point.new(1, 2)])
+        assert(nil, s[Point.new(2, 1)])
+        assert(true, Point.new(1, 2) == Point.new(1, 2))
+        assert(true, Point.new(1, 2).eql?(Point.new(1, 2)))
+        assert(false, Point.new(1, 2) == Point.new(1, 3))
+        "#;
+        assert_script(program);
+    }
 }
@@ -1,8 +1,18 @@
 use crate::*;
+use num::{BigInt, FromPrimitive, Integer, ToPrimitive, Zero};
+use std::cmp::Ordering;
 
 pub fn init(globals: &mut Globals) -> Value {
     let id = globals.get_ident_id("Integer");
     let class = ClassRef::from(id, globals.builtins.object);
+    globals.add_builtin_instance_method(class, "+", add);
+    globals.add_builtin_instance_method(class, "-", sub);
+    globals.add_builtin_instance_method(class, "*", mul);
+    globals.add_builtin_instance_method(class, "/", div);
+    globals.add_builtin_instance_method(class, "%", rem);
+    globals.add_builtin_instance_method(class, "**", pow);
+    globals.add_builtin_instance_method(class, "<<", shl_);
+    globals.add_builtin_instance_method(class, ">>", shr);
     globals.add_builtin_instance_method(class, "==", eq);
     globals.add_builtin_instance_method(class, "!=", neq);
     globals.add_builtin_instance_method(class, ">=", ge);
@@ -17,46 +27,191 @@ pub fn init(globals: &mut Globals) -> Value {
     globals.add_builtin_instance_method(class, "to_f", tof);
     globals.add_builtin_instance_method(class, "floor", floor);
     globals.add_builtin_instance_method(class, "even?", even);
+    globals.add_builtin_instance_method(class, "to_s", to_s);
+    globals.add_builtin_instance_method(class, "inspect", to_s);
     Value::class(globals, class)
 }
 
+/// Convert `val` to BigInt if `val` is an Integer.
+fn to_bigint(val: Value) -> Option<BigInt> {
+    match val.as_fixnum() {
+        Some(i) => Some(BigInt::from(i)),
+        None => val.as_bignum().cloned(),
+    }
+}
+
+/// Compare Integer `lhs` with `rhs`.
+/// Return None if `rhs` is not a number or the comparison is not possible (e.g. NaN).
+fn partial_cmp(lhs: Value, rhs: Value) -> Option<Ordering> {
+    match (lhs.as_fixnum(), rhs.unpack()) {
+        (Some(lhs), RV::Integer(rhs)) => lhs.partial_cmp(&rhs),
+        (Some(lhs), RV::Float(rhs)) => (lhs as f64).partial_cmp(&rhs),
+        _ => {
+            let lhs = to_bigint(lhs)?;
+            match rhs.unpack() {
+                RV::Float(rhs) => cmp_bigint_float(&lhs, rhs),
+                _ => Some(lhs.cmp(&to_bigint(rhs)?)),
+            }
+        }
+    }
+}
+
+/// Compare `lhs` with `rhs` exactly, without rounding `lhs` to f64.
+/// Return None if `rhs` is NaN.
+pub fn cmp_bigint_float(lhs: &BigInt, rhs: f64) -> Option<Ordering> {
+    if rhs.is_nan() {
+        return None;
+    }
+    if rhs.is_infinite() {
+        return Some(if rhs > 0.0 {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        });
+    }
+    let trunc = rhs.trunc();
+    match lhs.cmp(&BigInt::from_f64(trunc)?) {
+        Ordering::Equal => 0.0.partial_cmp(&(rhs - trunc)),
+        ord => Some(ord),
+    }
+}
+
+/// Shift `lhs` left by `rhs` bits (or right if `rhs` is negative).
+/// The result is promoted to Bignum when it overflows i64.
+pub fn shl(lhs: i64, rhs: i64) -> Value {
+    if rhs < 0 {
+        Value::fixnum(lhs >> std::cmp::min(rhs.unsigned_abs(), 63))
+    } else if rhs < 64 && (lhs << rhs) >> rhs == lhs {
+        Value::fixnum(lhs << rhs)
+    } else {
+        Value::bignum(BigInt::from(lhs) << rhs as usize)
+    }
+}
+
 // Class methods
 
 // Instance methods
 
-fn eq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+macro_rules! define_arith {
+    ($vm:ident, $self_val:ident, $args:ident, $op:ident) => {
+        use std::ops::*;
+        $vm.check_args_num($args.len(), 1)?;
+        let lhs = to_bigint($self_val).unwrap();
+        match to_bigint($args[0]) {
+            Some(rhs) => return Ok(Value::bignum(lhs.$op(rhs))),
+            None => match $args[0].unpack() {
+                RV::Float(rhs) => return Ok(Value::flonum(lhs.to_f64().unwrap().$op(rhs))),
+                _ => return Err($vm.error_cant_coerce($args[0], "Integer")),
+            },
+        }
+    };
+}
+
+fn add(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_arith!(vm, self_val, args, add);
+}
+
+fn sub(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_arith!(vm, self_val, args, sub);
+}
+
+fn mul(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    define_arith!(vm, self_val, args, mul);
+}
+
+fn div(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 1)?;
-    let lhs = vm.expect_integer(self_val, "Receiver")?;
-    match args[0].unpack() {
-        RV::Integer(rhs) => Ok(Value::bool(lhs == rhs)),
-        RV::Float(rhs) => Ok(Value::bool(lhs as f64 == rhs)),
-        _ => Ok(Value::bool(false)),
+    let lhs = to_bigint(self_val).unwrap();
+    match to_bigint(args[0]) {
+        Some(rhs) if rhs.is_zero() => Err(vm.error_zero_divide()),
+        Some(rhs) => Ok(Value::bignum(lhs.div_floor(&rhs))),
+        None => match args[0].unpack() {
+            RV::Float(rhs) => Ok(Value::flonum(lhs.to_f64().unwrap() / rhs)),
+            _ => Err(vm.error_cant_coerce(args[0], "Integer")),
+        },
     }
 }
 
-fn neq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+fn rem(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let lhs = to_bigint(self_val).unwrap();
+    match to_bigint(args[0]) {
+        Some(rhs) if rhs.is_zero() => Err(vm.error_zero_divide()),
+        Some(rhs) => Ok(Value::bignum(lhs.mod_floor(&rhs))),
+        None => match args[0].unpack() {
+            RV::Float(rhs) => Ok(Value::flonum(builtin::float::rem_floor(
+                lhs.to_f64().unwrap(),
+                rhs,
+            ))),
+            _ => Err(vm.error_cant_coerce(args[0], "Integer")),
+        },
+    }
+}
+
+fn pow(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 1)?;
-    let lhs = vm.expect_integer(self_val, "Receiver")?;
+    let lhs = to_bigint(self_val).unwrap();
     match args[0].unpack() {
-        RV::Integer(rhs) => Ok(Value::bool(lhs != rhs)),
-        RV::Float(rhs) => Ok(Value::bool(lhs as f64 != rhs)),
-        _ => Ok(Value::bool(true)),
+        RV::Integer(rhs) if 0 <= rhs && rhs <= std::u32::MAX as i64 => {
+            Ok(Value::bignum(num::pow(lhs, rhs as usize)))
+        }
+        RV::Integer(rhs) => Ok(Value::flonum(lhs.to_f64().unwrap().powf(rhs as f64))),
+        RV::Float(rhs) => Ok(Value::flonum(lhs.to_f64().unwrap().powf(rhs))),
+        _ => Err(vm.error_cant_coerce(args[0], "Integer")),
+    }
+}
+
+fn shl_(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let rhs = vm.expect_integer(args[0], "Shift width")?;
+    match self_val.as_fixnum() {
+        Some(lhs) => Ok(shl(lhs, rhs)),
+        None => {
+            let lhs = to_bigint(self_val).unwrap();
+            if rhs < 0 {
+                Ok(Value::bignum(lhs >> rhs.unsigned_abs() as usize))
+            } else {
+                Ok(Value::bignum(lhs << rhs as usize))
+            }
+        }
+    }
+}
+
+fn shr(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let rhs = vm.expect_integer(args[0], "Shift width")?;
+    match rhs.checked_neg() {
+        Some(rhs) => shl_(vm, self_val, &Args::new1(Value::fixnum(rhs))),
+        None => Err(vm.error_range("shift width too big")),
     }
 }
 
+fn eq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let res = partial_cmp(self_val, args[0]) == Some(Ordering::Equal);
+    Ok(Value::bool(res))
+}
+
+fn neq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let res = partial_cmp(self_val, args[0]) != Some(Ordering::Equal);
+    Ok(Value::bool(res))
+}
+
 macro_rules! define_cmp {
     ($vm:ident, $self_val:ident, $args:ident, $op:ident) => {
         $vm.check_args_num($args.len(), 1)?;
-        let lhs = $vm.expect_integer($self_val, "Receiver")?;
-        match $args[0].unpack() {
-            RV::Integer(rhs) => return Ok(Value::bool(lhs.$op(&rhs))),
-            RV::Float(rhs) => return Ok(Value::bool((lhs as f64).$op(&rhs))),
-            _ => {
-                return Err($vm.error_argument(format!(
-                    "Comparison of Integer with {} failed.",
-                    $vm.globals.get_class_name($args[0])
-                )))
-            }
+        match partial_cmp($self_val, $args[0]) {
+            Some(ord) => return Ok(Value::bool(ord.$op(&Ordering::Equal))),
+            None => match $args[0].unpack() {
+                RV::Float(_) => return Ok(Value::false_val()),
+                _ => {
+                    return Err($vm.error_argument(format!(
+                        "Comparison of Integer with {} failed.",
+                        $vm.globals.get_class_name($args[0])
+                    )))
+                }
+            },
         }
     };
 }
@@ -78,15 +233,8 @@ fn lt(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
 }
 
 fn cmp(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
-    //use std::cmp::Ordering;
     vm.check_args_num(args.len(), 1)?;
-    let lhs = vm.expect_integer(self_val, "Receiver")?;
-    let res = match args[0].unpack() {
-        RV::Integer(rhs) => lhs.partial_cmp(&rhs),
-        RV::Float(rhs) => (lhs as f64).partial_cmp(&rhs),
-        _ => return Ok(Value::nil()),
-    };
-    match res {
+    match partial_cmp(self_val, args[0]) {
         Some(ord) => Ok(Value::fixnum(ord as i64)),
         None => Ok(Value::nil()),
     }
@@ -98,11 +246,16 @@ fn times(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
         Some(method) => method,
         None => {
             let id = vm.globals.get_ident_id("times");
-            let val = Value::enumerator(&vm.globals, id, self_val, args.clone());
+            let size = EnumSize::Func(times_size);
+            let val = Value::enumerator_with_size(&vm.globals, id, self_val, args.clone(), size);
             return Ok(val);
         }
     };
     let num = vm.expect_integer(self_val, "Receiver")?;
+    if method.is_none() {
+        let ary = (0..num).map(|i| Value::fixnum(i)).collect();
+        return Ok(Value::array_from(&vm.globals, ary));
+    }
     if num < 1 {
         return Ok(self_val);
     };
@@ -114,6 +267,14 @@ fn times(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     Ok(self_val)
 }
 
+/// The size of the Enumerator of Integer#times.
+fn times_size(_vm: &mut VM, receiver: Value) -> VMResult {
+    match receiver.as_fixnum() {
+        Some(num) => Ok(Value::fixnum(std::cmp::max(num, 0))),
+        None => Ok(Value::nil()),
+    }
+}
+
 fn step(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_range(args.len(), 1, 2)?;
     let method = match args.block {
@@ -166,27 +327,43 @@ fn step(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
 
 /// Built-in function "chr".
 fn chr(vm: &mut VM, self_val: Value, _: &Args) -> VMResult {
-    let num = self_val.as_fixnum().unwrap();
-    if 0 > num || num > 255 {
-        return Err(vm.error_unimplemented("Currently, receiver must be 0..255."));
-    };
-    Ok(Value::bytes(&vm.globals, vec![num as u8]))
+    match self_val.as_fixnum() {
+        Some(num) if (0..=255).contains(&num) => Ok(Value::bytes(&vm.globals, vec![num as u8])),
+        _ => {
+            let num = vm.val_inspect(self_val);
+            Err(vm.error_range(format!("{} out of char range", num)))
+        }
+    }
 }
 
 fn floor(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 0)?;
-    self_val.as_fixnum().unwrap();
     Ok(self_val)
 }
 
 fn tof(_vm: &mut VM, self_val: Value, _: &Args) -> VMResult {
-    let num = self_val.as_fixnum().unwrap();
-    Ok(Value::flonum(num as f64))
+    let num = to_bigint(self_val).unwrap();
+    Ok(Value::flonum(num.to_f64().unwrap()))
 }
 
 fn even(_vm: &mut VM, self_val: Value, _: &Args) -> VMResult {
-    let num = self_val.as_fixnum().unwrap();
-    Ok(Value::bool(num % 2 == 0))
+    let num = to_bigint(self_val).unwrap();
+    Ok(Value::bool(num.is_even()))
+}
+
+fn to_s(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let base = if args.len() == 1 {
+        let base = vm.expect_integer(args[0], "Base")?;
+        if base < 2 || 36 < base {
+            return Err(vm.error_argument(format!("Invalid radix {}.", base)));
+        }
+        base as u32
+    } else {
+        10
+    };
+    let num = to_bigint(self_val).unwrap();
+    Ok(Value::string(&vm.globals, num.to_str_radix(base)))
 }
 
 #[cfg(test)]
@@ -263,4 +440,105 @@ mod tests {
         "#;
         assert_script(program);
     }
+
+    #[test]
+    fn integer_bignum() {
+        let program = r#"
+        assert "1267650600228229401496703205376", (2**100).to_s
+        assert "9223372036854775808", (9223372036854775807 + 1).to_s
+        assert 9223372036854775807, 9223372036854775807 + 1 - 1
+        assert "-9223372036854775809", (-9223372036854775807 - 2).to_s
+        assert 2**126, 9223372036854775807 * 9223372036854775807 + 9223372036854775807 * 2 + 1
+        assert 0, 2**64 - 2**64
+        assert "1180591620717411303424", (1 << 70).to_s
+        assert 2, (1 << 70) >> 69
+        assert 600, (2**100 + 600) % (2**100)
+        assert 2**99, 2**100 / 2
+        def fact(n)
+          n <= 1 ? 1 : n * fact(n - 1)
+        end
+        assert "265252859812191058636308480000000", fact(30).to_s
+        assert 600, fact(25) / fact(23)
+        assert "10000000000000000000000000", (2**100).to_s(16)
+        assert "11111111", 255.to_s(2)
+        assert "-zz", (-1295).to_s(36)
+        assert true, 2**100 > 2**99
+        assert true, 2**100 == 2**100
+        assert false, 2**100 == 2**99
+        assert 1, 2**100 <=> 3
+        assert(-1, 3 <=> 2**100)
+        assert false, 2**100 < 1.0
+        assert([-(2**65), 1, 3, 2**70], [3, 2**70, -(2**65), 1].sort)
+        h = {2**100 => :big}
+        assert :big, h[2**99 * 2]
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn integer_chr_shift() {
+        let program = r#"
+        assert "256 out of char range", (begin; 256.chr; rescue RangeError => e; e.message; end)
+        assert "-1 out of char range", (begin; -1.chr; rescue RangeError => e; e.message; end)
+        assert RangeError, (begin; (2**64).chr; rescue => e; e.class; end)
+        min = -9223372036854775807 - 1
+        assert 0, 1 << min
+        assert(-1, -1 << min)
+        assert 0, 2**70 << min
+        assert RangeError, (begin; 1 >> min; rescue => e; e.class; end)
+        assert 4, 16 >> 2
+        assert 2**68, 2**70 >> 2
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn integer_div_rem() {
+        let program = r#"
+        assert 3, 7 / 2
+        assert(-4, -7 / 2)
+        assert(-4, 7 / -2)
+        assert 3, -7 / -2
+        assert 1, 7 % 2
+        assert 1, -7 % 2
+        assert(-1, 7 % -2)
+        assert(-1, -7 % -2)
+        assert 0.5, -7.5 % 2
+        assert(-0.5, 7.5 % -2)
+        min = -9223372036854775807 - 1
+        assert "-9223372036854775808", min.to_s
+        assert "9223372036854775808", (min / -1).to_s
+        assert 0, min % -1
+        assert(-1, min / 9223372036854775807 + 1)
+        assert 2**62, 2**63 / 2
+        assert(-1, -(2**64) / 2**64)
+        assert(-2, (-(2**64) - 1) / 2**64)
+        assert 2**64 - 1, -1 % 2**64
+        assert(-(2**64) + 1, 1 % -(2**64))
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn integer_bignum_float() {
+        let program = r#"
+        assert true, 2**64 == 2.0**64
+        assert true, 2.0**64 == 2**64
+        assert false, 2**64 + 1 == 2.0**64
+        assert false, 2.0**64 != 2**64
+        assert true, 2**64 + 1 > 2.0**64
+        assert true, 2.0**64 < 2**64 + 1
+        assert false, 2.0**64 > 2**64
+        assert 0, 2.0**64 <=> 2**64
+        assert(-1, 2.0**64 <=> 2**64 + 1)
+        assert 1, 2**64 + 1 <=> 2.0**64
+        assert 2.0**100 + 1.5, 1.5 + 2**100
+        assert 1.5 - 2.0**100, 1.5 - 2**100
+        assert 2.0**101, 2.0 * 2**100
+        assert 0.5, 2.0**99 / 2**100
+        assert 1.0, 2**100 % 1.5
+        assert 2.0**100, 2**100 * 1.0
+        "#;
+        assert_script(program);
+    }
 }
@@ -22,7 +22,11 @@ pub fn init(globals: &mut Globals) -> Value {
     globals.add_builtin_instance_method(kernel_class, "rand", rand);
     globals.add_builtin_instance_method(kernel_class, "loop", loop_);
     globals.add_builtin_instance_method(kernel_class, "exit", exit);
-    let kernel = Value::class(globals, kernel_class);
+    globals.add_builtin_instance_method(kernel_class, "at_exit", at_exit);
+    globals.add_builtin_instance_method(kernel_class, "lambda", lambda);
+    globals.add_builtin_instance_method(kernel_class, "proc", proc);
+    globals.add_builtin_instance_method(kernel_class, "`", command);
+    let kernel = Value::module(globals, kernel_class);
     return kernel;
 
     /// Built-in function "puts".
@@ -163,16 +167,7 @@ pub fn init(globals: &mut Globals) -> Value {
 
     fn isa(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
         vm.check_args_num(args.len(), 1)?;
-        let mut recv_class = self_val.get_class_object(&vm.globals);
-        loop {
-            if recv_class.id() == args[0].id() {
-                return Ok(Value::true_val());
-            }
-            recv_class = recv_class.as_class().superclass;
-            if recv_class.is_nil() {
-                return Ok(Value::false_val());
-            }
-        }
+        Ok(Value::bool(vm.kind_of(self_val, args[0])))
     }
 
     fn integer(vm: &mut VM, _: Value, args: &Args) -> VMResult {
@@ -236,12 +231,61 @@ pub fn init(globals: &mut Globals) -> Value {
         ))
     }
 
+    /// Built-in function "raise".
+    /// raise
+    /// raise(message, cause: $!)
+    /// raise(exception_class, message = nil, cause: $!)
+    /// raise(exception, cause: $!)
     fn raise(vm: &mut VM, _: Value, args: &Args) -> VMResult {
         vm.check_args_range(args.len(), 0, 2)?;
-        for arg in args.iter() {
-            eprintln!("{}", vm.val_inspect(*arg));
+        let err_id = vm.globals.get_ident_id("$!");
+        let current = vm.get_global_var(err_id);
+        let exception = if args.len() == 0 {
+            if current.is_nil() {
+                let id = vm.globals.get_ident_id("RuntimeError");
+                let class = vm.globals.builtins.object.get_var(id).unwrap();
+                vm.create_exception(class, "unhandled exception")
+            } else {
+                return Err(vm.error_from_exception(current));
+            }
+        } else if args.len() == 1 && args[0].as_string().is_some() {
+            let id = vm.globals.get_ident_id("RuntimeError");
+            let class = vm.globals.builtins.object.get_var(id).unwrap();
+            let message = args[0].as_string().unwrap().to_string();
+            vm.create_exception(class, &message)
+        } else if args[0].is_class().is_some() {
+            let new_args = if args.len() == 2 {
+                Args::new1(args[1])
+            } else {
+                Args::new0()
+            };
+            let method = vm.get_method(args[0], IdentId::NEW)?;
+            vm.eval_send(method, args[0], &new_args)?
+        } else {
+            args[0]
+        };
+        let mut eref = match exception.as_exception() {
+            Some(eref) => eref,
+            None => return Err(vm.error_type("exception class/object expected")),
+        };
+        if args.len() == 2 && args[0].is_class().is_none() {
+            eref.message = args[1];
+        }
+        let cause = match args.kw_arg {
+            Some(kw_arg) => {
+                let hash = vm.expect_hash(kw_arg, "Keyword arguments")?;
+                let cause_id = vm.globals.get_ident_id("cause");
+                match hash.get(&Value::symbol(cause_id))? {
+                    Some(cause) => cause,
+                    None => current,
+                }
+            }
+            None => current,
+        };
+        if eref.cause.is_none() && cause.id() != exception.id() {
+            eref.cause = Some(cause);
         }
-        Err(vm.error_unimplemented("error"))
+        Err(vm.error_from_exception(exception))
     }
 
     fn rand(_vm: &mut VM, _: Value, _args: &Args) -> VMResult {
@@ -249,11 +293,28 @@ pub fn init(globals: &mut Globals) -> Value {
         Ok(Value::flonum(num))
     }
 
+    /// Repeat the block until StopIteration is raised,
+    /// and return the result of the iteration which raised it.
     fn loop_(vm: &mut VM, _: Value, args: &Args) -> VMResult {
         let method = vm.expect_block(args.block)?;
         let arg = Args::new0();
+        let id = vm.globals.get_ident_id("StopIteration");
+        let stop_iteration = vm.globals.builtins.object.get_var(id).unwrap();
         loop {
-            vm.eval_block(method, &arg)?;
+            let err = match vm.eval_block(method, &arg) {
+                Ok(_) => continue,
+                Err(err) => err,
+            };
+            let is_stop = match &err.kind {
+                RubyErrorKind::RuntimeErr(RuntimeErrKind::StopIteration(_)) => true,
+                RubyErrorKind::Exception(exception, _) => vm.kind_of(*exception, stop_iteration),
+                _ => false,
+            };
+            if !is_stop {
+                return Err(err);
+            }
+            let exception = vm.exception_from_error(&err);
+            return Ok(exception.as_exception().unwrap().result);
         }
     }
 
@@ -264,13 +325,57 @@ pub fn init(globals: &mut Globals) -> Value {
         } else {
             args[0].expect_integer(vm, "Expect Integer.")?
         };
+        if let Err(err) = vm.exec_at_exit() {
+            err.show_err();
+        }
         std::process::exit(code as i32);
     }
+
+    /// Register the block as a proc which is executed when the program exits.
+    fn at_exit(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+        vm.check_args_num(args.len(), 0)?;
+        let method = vm.expect_block(args.block)?;
+        let proc_obj = vm.create_proc(method)?;
+        vm.globals.at_exit_procs.push(proc_obj);
+        Ok(proc_obj)
+    }
+
+    /// Built-in function "lambda".
+    fn lambda(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+        vm.check_args_num(args.len(), 0)?;
+        let method = vm.expect_block(args.block)?;
+        vm.create_lambda(method)
+    }
+
+    /// Built-in function "proc".
+    fn proc(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+        vm.check_args_num(args.len(), 0)?;
+        let method = vm.expect_block(args.block)?;
+        vm.create_proc(method)
+    }
+
+    /// Built-in function "`".
+    /// Execute the command by the shell, and return the standard output.
+    fn command(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+        vm.check_args_num(args.len(), 1)?;
+        let command = vm.expect_string(&args[0], "1st arg")?;
+        let output = match std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .output()
+        {
+            Ok(output) => output,
+            Err(err) => return Err(vm.error_internal(format!("{}", err))),
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        Ok(Value::string(&vm.globals, stdout))
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::test::*;
+    use crate::*;
 
     #[test]
     fn is_a() {
@@ -292,4 +397,168 @@ mod test {
         ";
         assert_script(program);
     }
+
+    #[test]
+    fn raise() {
+        let program = r#"
+        def check
+          yield
+        rescue Exception => e
+          [e.class, e.message]
+        end
+        assert([RuntimeError, "boom"], check { raise "boom" })
+        assert([ArgumentError, "ArgumentError"], check { raise ArgumentError })
+        assert([TypeError, "type"], check { raise TypeError, "type" })
+        assert([IndexError, "obj"], check { raise IndexError.new("obj") })
+        assert([TypeError, "exception class/object expected"], check { raise 3 })
+        assert([RuntimeError, "unhandled exception"], check { raise })
+        class MyError < StandardError
+          def initialize(msg = "default")
+            super
+          end
+        end
+        assert([MyError, "default"], check { raise MyError })
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn raise_reraise() {
+        let program = r#"
+        begin
+          begin
+            raise "inner"
+          rescue => e
+            raise
+          end
+        rescue => e2
+          assert(true, e.equal?(e2))
+        end
+        begin
+          begin
+            raise "first"
+          rescue
+            raise ArgumentError, "second"
+          end
+        rescue => e
+          assert("first", e.cause.message)
+        end
+        begin
+          raise "no cause", cause: nil
+        rescue => e
+          assert(nil, e.cause)
+        end
+        assert(nil, $!)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn raise_errinfo() {
+        let program = r#"
+        def ret
+          begin
+            raise "ret"
+          rescue
+            return $!.message
+          end
+        end
+        def each_next
+          [1].each do |x|
+            begin
+              raise "next"
+            rescue
+              next
+            end
+          end
+          $!
+        end
+        begin
+          raise "outer"
+        rescue => e
+          begin
+            raise "inner"
+          rescue
+            assert("inner", $!.message)
+          end
+          assert(true, e.equal?($!))
+          assert("ret", ret)
+          assert(true, e.equal?($!))
+          assert(true, e.equal?(each_next))
+          while true
+            begin
+              raise "break"
+            rescue
+              break
+            end
+          end
+          assert(true, e.equal?($!))
+        end
+        assert(nil, $!)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn retry() {
+        let program = r#"
+        n = 0
+        begin
+          n += 1
+          raise "again" if n < 3
+        rescue
+          retry
+        end
+        assert(3, n)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn at_exit() {
+        let program = r#"
+        $r << :main
+        at_exit { $r << 1 }
+        END { $r << 2 }
+        at_exit do
+          $r << 3
+          at_exit { $r << 4 }
+        end
+        BEGIN { $r = [:begin] }
+        assert([:begin, :main], $r)
+        "#;
+        let mut vm = VM::new();
+        vm.run(std::path::PathBuf::from(""), program, None).unwrap();
+        let program = "assert([:begin, :main, 3, 4, 2, 1], $r)";
+        vm.run(std::path::PathBuf::from(""), program, None).unwrap();
+    }
+
+    #[test]
+    fn at_exit_result() {
+        let program = r#"
+        at_exit do
+          a = []
+          100000.times { |i| a << [i] }
+          GC.start
+        end
+        [1, 2, 3].map { |x| x * 10 }
+        "#;
+        let mut vm = VM::new();
+        let val = vm.run(std::path::PathBuf::from(""), program, None).unwrap();
+        assert_eq!("[10, 20, 30]", vm.val_inspect(val));
+        let program = r#"
+        at_exit { $r = :at_exit; raise "at_exit" }
+        1 / 0
+        "#;
+        let mut vm = VM::new();
+        let err = vm
+            .run(std::path::PathBuf::from(""), program, None)
+            .unwrap_err();
+        match err.kind {
+            RubyErrorKind::RuntimeErr(RuntimeErrKind::ZeroDivision(_)) => {}
+            _ => panic!("Unexpected error: {:?}", err.kind),
+        }
+        let program = "assert(:at_exit, $r)";
+        vm.run(std::path::PathBuf::from(""), program, None).unwrap();
+    }
 }
@@ -4,6 +4,7 @@ pub fn init_method(globals: &mut Globals) -> Value {
     let proc_id = globals.get_ident_id("Method");
     let class = ClassRef::from(proc_id, globals.builtins.object);
     globals.add_builtin_instance_method(class, "call", method_call);
+    globals.add_builtin_instance_method(class, "to_proc", method_to_proc);
     Value::class(globals, class)
 }
 
@@ -15,3 +16,12 @@ pub fn method_call(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let res = vm.eval_send(method.method, method.receiver, args)?;
     Ok(res)
 }
+
+pub fn method_to_proc(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let pref = ProcRef::new(ProcInfo {
+        is_lambda: true,
+        kind: ProcKind::Method(self_val),
+    });
+    Ok(Value::procobj_from(&vm.globals, pref))
+}
@@ -12,8 +12,26 @@ pub fn init(globals: &mut Globals) {
     globals.add_builtin_instance_method(class, "singleton_class?", singleton_class);
     globals.add_builtin_instance_method(class, "const_get", const_get);
     globals.add_builtin_instance_method(class, "include", include);
+    globals.add_builtin_instance_method(class, "prepend", prepend);
+    globals.add_builtin_instance_method(class, "included", hook);
+    globals.add_builtin_instance_method(class, "extended", hook);
+    globals.add_builtin_instance_method(class, "prepended", hook);
+    globals.add_builtin_instance_method(class, "method_added", hook);
+    globals.add_builtin_instance_method(class, "const_added", hook);
     globals.add_builtin_instance_method(class, "included_modules", included_modules);
     globals.add_builtin_instance_method(class, "ancestors", ancestors);
+    globals.add_builtin_instance_method(class, "alias_method", alias_method);
+    globals.add_builtin_instance_method(class, "define_method", define_method);
+    globals.add_builtin_instance_method(class, "remove_method", remove_method);
+    globals.add_builtin_instance_method(class, "undef_method", undef_method);
+    globals.add_builtin_instance_method(class, "public", public);
+    globals.add_builtin_instance_method(class, "protected", protected);
+    globals.add_builtin_instance_method(class, "private", private);
+    globals.add_builtin_instance_method(class, "private_constant", private_constant);
+    globals.add_builtin_instance_method(class, "class_variable_get", class_variable_get);
+    globals.add_builtin_instance_method(class, "class_variable_set", class_variable_set);
+    globals.add_builtin_instance_method(class, "class_variables", class_variables);
+    globals.add_builtin_instance_method(class, "class_variable_defined?", class_variable_defined);
 }
 
 fn constants(vm: &mut VM, self_val: Value, _: &Args) -> VMResult {
@@ -60,6 +78,12 @@ fn const_get(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     Ok(val)
 }
 
+/// Return true if `method` is listed by `instance_methods`, i.e. public or protected.
+fn is_visible_method(vm: &VM, method: MethodRef) -> bool {
+    !vm.globals.is_void_method(method)
+        && vm.globals.get_method_visibility(method) != Visibility::Private
+}
+
 fn instance_methods(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let mut class = vm.expect_module(self_val)?;
     vm.check_args_range(args.len(), 0, 1)?;
@@ -68,24 +92,24 @@ fn instance_methods(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
         false => {
             let v = class
                 .method_table
-                .keys()
-                .map(|k| Value::symbol(*k))
+                .iter()
+                .filter(|(_, method)| is_visible_method(vm, **method))
+                .map(|(k, _)| Value::symbol(*k))
                 .collect();
             Ok(Value::array_from(&vm.globals, v))
         }
         true => {
             let mut v = std::collections::HashSet::new();
+            // Methods undefined or made private in subclasses are hidden.
+            let mut undefined = std::collections::HashSet::new();
             loop {
-                v = v
-                    .union(
-                        &class
-                            .method_table
-                            .keys()
-                            .map(|k| Value::symbol(*k))
-                            .collect(),
-                    )
-                    .cloned()
-                    .collect();
+                for (id, method) in class.method_table.iter() {
+                    if !is_visible_method(vm, *method) {
+                        undefined.insert(*id);
+                    } else if !undefined.contains(id) {
+                        v.insert(Value::symbol(*id));
+                    }
+                }
                 match class.superclass() {
                     Some(superclass) => class = superclass,
                     None => break,
@@ -139,6 +163,8 @@ fn define_reader(vm: &mut VM, class: Value, id: IdentId) {
         id: instance_var_id,
     };
     let methodref = vm.globals.add_method(info);
+    let visibility = vm.default_visibility();
+    vm.globals.set_method_visibility(methodref, visibility);
     vm.add_instance_method(class, id, methodref);
 }
 
@@ -149,6 +175,8 @@ fn define_writer(vm: &mut VM, class: Value, id: IdentId) {
         id: instance_var_id,
     };
     let methodref = vm.globals.add_method(info);
+    let visibility = vm.default_visibility();
+    vm.globals.set_method_visibility(methodref, visibility);
     vm.add_instance_method(class, assign_id, methodref);
 }
 
@@ -168,65 +196,266 @@ fn singleton_class(vm: &mut VM, self_val: Value, _: &Args) -> VMResult {
     Ok(Value::bool(class.is_singleton))
 }
 
+/// include(module, ...)
 fn include(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_min(args.len(), 1)?;
+    vm.expect_module(self_val)?;
+    expect_modules(vm, args)?;
+    for module in args.iter().rev() {
+        vm.include_module(self_val, *module)?;
+        vm.call_hook(*module, "included", self_val)?;
+    }
+    Ok(self_val)
+}
+
+/// prepend(module, ...)
+fn prepend(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_min(args.len(), 1)?;
+    vm.expect_module(self_val)?;
+    expect_modules(vm, args)?;
+    for module in args.iter().rev() {
+        vm.prepend_module(self_val, *module)?;
+        vm.call_hook(*module, "prepended", self_val)?;
+    }
+    Ok(self_val)
+}
+
+/// Check that all of `args` are modules.
+pub fn expect_modules(vm: &mut VM, args: &Args) -> Result<(), RubyError> {
+    for module in args.iter() {
+        if module.is_module().is_none() {
+            let inspect = vm.val_inspect(*module);
+            return Err(vm.error_type(format!(
+                "Wrong argument type {} (expected Module).",
+                inspect
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// included(base), extended(obj), prepended(base), method_added(name), const_added(name)
+/// Hooks called on the change of the module. Do nothing by default.
+fn hook(vm: &mut VM, _: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 1)?;
-    let mut class = vm.expect_module(self_val)?;
-    let module = args[0];
-    class.include.push(module);
     Ok(Value::nil())
 }
 
 fn included_modules(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 0)?;
-    let mut class = self_val;
-    let mut ary = vec![];
-    loop {
-        if class.is_nil() {
-            break;
-        }
-        class = match class.as_module() {
-            Some(cref) => {
-                for included in &cref.include {
-                    ary.push(*included);
-                }
-                cref.superclass
-            }
-            None => {
-                let inspect = vm.val_inspect(class);
-                return Err(
-                    vm.error_internal(format!("Illegal value in superclass chain. {}", inspect))
-                );
-            }
-        };
-    }
+    vm.expect_module(self_val)?;
+    let ary = vm
+        .get_ancestors(self_val)
+        .into_iter()
+        .filter(|module| module.is_module().is_some())
+        .collect();
     Ok(Value::array_from(&vm.globals, ary))
 }
 
 fn ancestors(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 0)?;
-    let mut superclass = self_val;
-    let mut ary = vec![];
+    vm.expect_module(self_val)?;
+    let ary = vm.get_ancestors(self_val);
+    Ok(Value::array_from(&vm.globals, ary))
+}
+
+/// Convert a Symbol or a String to a method name.
+fn expect_method_name(vm: &mut VM, val: Value) -> Result<IdentId, RubyError> {
+    match val.as_symbol() {
+        Some(symbol) => Ok(symbol),
+        None => {
+            let name = vm.expect_string(&val, "Method name")?.to_owned();
+            Ok(vm.globals.get_ident_id(name))
+        }
+    }
+}
+
+/// alias_method(new_name, old_name)
+fn alias_method(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 2)?;
+    vm.expect_module(self_val)?;
+    let new = expect_method_name(vm, args[0])?;
+    let old = expect_method_name(vm, args[1])?;
+    vm.alias_method(self_val, new, old)?;
+    Ok(Value::symbol(new))
+}
+
+/// define_method(name) { block }
+/// define_method(name, proc_or_method)
+fn define_method(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    vm.expect_module(self_val)?;
+    let id = expect_method_name(vm, args[0])?;
+    let methodref = if args.len() == 2 {
+        let body = args[1];
+        if body.as_proc().is_some() {
+            vm.globals
+                .add_method(MethodInfo::ProcMethod { id, proc: body })
+        } else if let Some(method) = body.as_method() {
+            method.method
+        } else {
+            let inspect = vm.val_inspect(body);
+            return Err(vm.error_type(format!(
+                "Wrong argument type {} (expected Proc/Method).",
+                inspect
+            )));
+        }
+    } else {
+        let block = match args.block {
+            Some(block) => block,
+            None => return Err(vm.error_argument("tried to create Proc object without a block")),
+        };
+        let proc = vm.create_proc(block)?;
+        vm.globals.add_method(MethodInfo::ProcMethod { id, proc })
+    };
+    let visibility = vm.default_visibility();
+    vm.globals.set_method_visibility(methodref, visibility);
+    vm.add_instance_method(self_val, id, methodref);
+    vm.call_method_added(self_val, id)?;
+    Ok(Value::symbol(id))
+}
+
+/// remove_method(name, ...)
+fn remove_method(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.expect_module(self_val)?;
+    for arg in args.iter() {
+        let id = expect_method_name(vm, *arg)?;
+        vm.remove_method(self_val, id)?;
+    }
+    Ok(self_val)
+}
+
+/// undef_method(name, ...)
+fn undef_method(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.expect_module(self_val)?;
+    for arg in args.iter() {
+        let id = expect_method_name(vm, *arg)?;
+        vm.undef_method(self_val, id)?;
+    }
+    Ok(self_val)
+}
+
+/// Without arguments, set the default visibility of subsequently defined methods.
+/// With arguments, set the visibility of the given methods.
+fn set_visibility(vm: &mut VM, self_val: Value, args: &Args, visibility: Visibility) -> VMResult {
+    vm.expect_module(self_val)?;
+    if args.len() == 0 {
+        vm.set_default_visibility(visibility);
+        return Ok(Value::nil());
+    }
+    for arg in args.iter() {
+        let names = match arg.as_array() {
+            Some(aref) => aref.elements.clone(),
+            None => vec![*arg],
+        };
+        for name in names {
+            let id = expect_method_name(vm, name)?;
+            vm.set_method_visibility(self_val, id, visibility)?;
+        }
+    }
+    if args.len() == 1 {
+        Ok(args[0])
+    } else {
+        Ok(Value::array_from(
+            &vm.globals,
+            args.iter().cloned().collect(),
+        ))
+    }
+}
+
+fn public(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    set_visibility(vm, self_val, args, Visibility::Public)
+}
+
+fn protected(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    set_visibility(vm, self_val, args, Visibility::Protected)
+}
+
+fn private(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    set_visibility(vm, self_val, args, Visibility::Private)
+}
+
+/// private_constant(name, ...)
+fn private_constant(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let mut class = vm.expect_module(self_val)?;
+    for arg in args.iter() {
+        let id = expect_method_name(vm, *arg)?;
+        if self_val.get_var(id).is_none() {
+            let inspect = vm.val_inspect(self_val);
+            let name = vm.globals.get_ident_name(id);
+            return Err(vm.error_name(format!("constant {}::{} not defined", inspect, name)));
+        }
+        class.private_constants.insert(id);
+    }
+    Ok(Value::nil())
+}
+
+/// Convert a Symbol or a String to a class variable name.
+fn expect_class_var_name(vm: &mut VM, val: Value) -> Result<IdentId, RubyError> {
+    let id = expect_method_name(vm, val)?;
+    let name = vm.globals.get_ident_name(id);
+    if name.starts_with("@@") && name.len() > 2 {
+        Ok(id)
+    } else {
+        Err(vm.error_name(format!(
+            "`{}' is not allowed as a class variable name",
+            name
+        )))
+    }
+}
+
+/// class_variable_get(name)
+fn class_variable_get(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let id = expect_class_var_name(vm, args[0])?;
+    vm.get_class_var(self_val, id)
+}
+
+/// class_variable_set(name, val)
+fn class_variable_set(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 2)?;
+    vm.expect_module(self_val)?;
+    let id = expect_class_var_name(vm, args[0])?;
+    vm.set_class_var(self_val, id, args[1]);
+    Ok(args[1])
+}
+
+/// class_variables(inherit = true)
+fn class_variables(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let mut class = vm.expect_module(self_val)?;
+    vm.check_args_range(args.len(), 0, 1)?;
+    let inherited_too = args.len() == 0 || vm.val_to_bool(args[0]);
+    let mut v = vec![];
+    let mut push_vars = |class: ClassRef| {
+        for id in class.class_vars.keys() {
+            let sym = Value::symbol(*id);
+            if !v.contains(&sym) {
+                v.push(sym);
+            }
+        }
+    };
     loop {
-        if superclass.is_nil() {
+        push_vars(class);
+        if !inherited_too {
             break;
         }
-        ary.push(superclass);
-        superclass = match superclass.as_module() {
-            Some(cref) => {
-                for included in &cref.include {
-                    ary.push(*included);
-                }
-                cref.superclass
-            }
-            None => {
-                let inspect = vm.val_inspect(superclass);
-                return Err(
-                    vm.error_internal(format!("Illegal value in superclass chain. {}", inspect))
-                );
-            }
+        for module in &class.include {
+            push_vars(module.as_module().unwrap());
+        }
+        match class.superclass() {
+            Some(superclass) => class = superclass,
+            None => break,
         };
     }
-    Ok(Value::array_from(&vm.globals, ary))
+    Ok(Value::array_from(&vm.globals, v))
+}
+
+/// class_variable_defined?(name)
+fn class_variable_defined(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let class = vm.expect_module(self_val)?;
+    let id = expect_class_var_name(vm, args[0])?;
+    Ok(Value::bool(class.class_var_owner(id).is_some()))
 }
 
 #[cfg(test)]
@@ -315,4 +544,306 @@ mod test {
     "#;
         assert_script(program);
     }
+
+    #[test]
+    fn alias_and_undef() {
+        let program = r#"
+    class Foo
+        def foo
+            "foo"
+        end
+        def bar
+            "bar"
+        end
+        alias baz foo
+        alias :qux :bar
+        alias_method :quux, :foo
+        alias_method "corge", "bar"
+    end
+    class Bar < Foo
+        undef bar
+        undef_method :baz
+        def foo
+            "Bar#foo"
+        end
+    end
+    f = Foo.new
+    b = Bar.new
+    assert("foo", f.baz)
+    assert("bar", f.qux)
+    assert("foo", f.quux)
+    assert("bar", f.corge)
+    assert(false, b.respond_to?(:bar))
+    assert(false, b.respond_to?(:baz))
+    assert(true, f.respond_to?(:bar))
+    assert(true, Bar.instance_methods.include?(:qux))
+    assert(false, Bar.instance_methods.include?(:bar))
+    assert("Bar#foo", b.foo)
+    Bar.remove_method(:foo)
+    assert("foo", b.foo)
+    e = begin
+      b.bar
+    rescue NoMethodError => e
+      e
+    end
+    assert(NoMethodError, e.class)
+    e = begin
+      Bar.remove_method(:foo)
+    rescue NameError => e
+      e
+    end
+    assert("method `foo' not defined in Bar", e.message)
+    e = begin
+      Bar.remove_method(:bar)
+    rescue NameError => e
+      e
+    end
+    assert("method `bar' not defined in Bar", e.message)
+    assert(false, b.respond_to?(:bar))
+    e = begin
+      Foo.alias_method(:x, :nothing)
+    rescue NameError => e
+      e
+    end
+    assert("undefined method `nothing' for class `Foo'", e.message)
+    $old = 5
+    alias $new $old
+    assert(5, $new)
+    $new = 8
+    assert(8, $old)
+    "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn visibility() {
+        let program = r#"
+    class Foo
+        SECRET = 42
+        private_constant :SECRET
+        def call_secret
+            secret + SECRET
+        end
+        def call_self_secret
+            self.secret
+        end
+        def compare(other)
+            other.prot
+        end
+        private
+        def secret
+            1
+        end
+        public
+        def open
+            2
+        end
+        def prot
+            3
+        end
+        protected :prot
+        attr_accessor :acc
+        private :acc
+        def pub_acc
+            acc
+        end
+        private def inline
+            4
+        end
+    end
+    class Bar < Foo
+        public :secret
+    end
+    foo = Foo.new
+    assert(43, foo.call_secret)
+    assert(1, foo.call_self_secret)
+    assert(2, foo.open)
+    assert(3, foo.compare(Foo.new))
+    assert(nil, foo.pub_acc)
+    assert(1, Bar.new.secret)
+    assert(false, foo.respond_to?(:secret))
+    assert(true, foo.respond_to?(:secret, true))
+    assert(1, foo.send(:secret))
+    assert(false, Foo.instance_methods(false).include?(:secret))
+    assert(true, Foo.instance_methods(false).include?(:open))
+    e = begin
+      foo.secret
+    rescue NoMethodError => e
+      e
+    end
+    assert(NoMethodError, e.class)
+    assert(true, e.message.start_with?("private method `secret' called"))
+    e = begin
+      foo.prot
+    rescue NoMethodError => e
+      e
+    end
+    assert(true, e.message.start_with?("protected method `prot' called"))
+    e = begin
+      foo.inline
+    rescue NoMethodError => e
+      e
+    end
+    assert(true, e.message.start_with?("private method `inline' called"))
+    e = begin
+      Foo::SECRET
+    rescue NameError => e
+      e
+    end
+    assert("private constant Foo::SECRET referenced", e.message)
+    class Counter
+        attr_reader :count
+        def initialize
+            self.count = 0
+        end
+        def incr
+            self.count += 1
+        end
+        private
+        attr_writer :count
+    end
+    c = Counter.new
+    c.incr
+    assert(1, c.count)
+    e = begin
+      c.count = 5
+    rescue NoMethodError => e
+      e
+    end
+    assert(true, e.message.start_with?("private method `count=' called"))
+    "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn class_variables() {
+        let program = r#"
+    class Foo
+      @@a = 1
+    end
+    class Bar < Foo
+      @@b = 2
+    end
+    assert(1, Bar.class_variable_get(:@@a))
+    assert(3, Bar.class_variable_set("@@c", 3))
+    assert(3, Bar.class_variable_get("@@c"))
+    assert(5, Bar.class_variable_set(:@@a, 5))
+    assert(5, Foo.class_variable_get(:@@a))
+    assert([:@@b, :@@c, :@@a], Bar.class_variables)
+    assert([:@@b, :@@c], Bar.class_variables(false))
+    assert(true, Bar.class_variable_defined?(:@@a))
+    assert(false, Foo.class_variable_defined?(:@@b))
+    e = begin
+      Foo.class_variable_get(:@@b)
+    rescue NameError => e
+      e
+    end
+    assert("uninitialized class variable @@b in Foo", e.message)
+    e = begin
+      Foo.class_variable_get(:b)
+    rescue NameError => e
+      e
+    end
+    assert("`b' is not allowed as a class variable name", e.message)
+    "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn module_prepend() {
+        let program = r#"
+    module Loud
+      def speak
+        super.upcase + "!"
+      end
+    end
+    module Polite
+      def speak
+        "please, " + super
+      end
+    end
+    class Animal
+      def speak
+        "hello"
+      end
+    end
+    class Dog < Animal
+      prepend Loud
+      include Polite
+      def speak
+        "woof " + super
+      end
+    end
+    assert("WOOF PLEASE, HELLO!", Dog.new.speak)
+    assert([Loud, Dog, Polite, Animal, Object, Kernel], Dog.ancestors)
+    assert([Loud, Polite, Kernel], Dog.included_modules)
+    module A; end
+    module B; end
+    class C; include A; include B; end
+    assert([C, B, A, Object, Kernel], C.ancestors)
+    class D; include A, B; end
+    assert([D, A, B, Object, Kernel], D.ancestors)
+    class E < C; include A; end
+    assert([E, C, B, A, Object, Kernel], E.ancestors)
+    class Cat
+      def speak
+        "meow"
+      end
+    end
+    cat = Cat.new
+    assert("meow", cat.speak)
+    class Cat; prepend Loud; end
+    assert("MEOW!", cat.speak)
+    e = begin
+      B.include C
+    rescue TypeError => e
+      e
+    end
+    assert(TypeError, e.class)
+    module M1; end
+    module M2; include M1; end
+    e = begin
+      M1.include M2
+    rescue ArgumentError => e
+      e
+    end
+    assert("cyclic include detected", e.message)
+    "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn module_hooks() {
+        let program = r#"
+    $log = []
+    module Tracker
+      def self.included(base); $log << [:included, base]; end
+      def self.extended(obj); $log << [:extended, obj]; end
+      def self.prepended(base); $log << [:prepended, base]; end
+    end
+    class Base
+      def self.inherited(sub); $log << [:inherited, sub]; end
+      def self.method_added(name); $log << [:method_added, name]; end
+      def self.const_added(name); $log << [:const_added, name]; end
+      include Tracker
+      prepend Tracker
+      def foo; end
+      define_method(:bar) {}
+      alias_method :baz, :foo
+      X = 1
+    end
+    class Sub < Base; end
+    klass = Class.new(Base)
+    obj = Object.new
+    obj.extend(Tracker)
+    assert([
+      [:included, Base], [:prepended, Base],
+      [:method_added, :foo], [:method_added, :bar], [:method_added, :baz],
+      [:const_added, :X], [:inherited, Sub], [:inherited, klass], [:extended, obj]
+    ], $log)
+    assert(true, Sub.new.is_a?(Tracker))
+    assert(true, obj.is_a?(Tracker))
+    "#;
+        assert_script(program);
+    }
 }
@@ -2,25 +2,37 @@ use crate::*;
 
 pub fn init(globals: &mut Globals) {
     let object = globals.object_class;
+    globals.add_builtin_instance_method(object, "initialize", initialize);
     globals.add_builtin_instance_method(object, "class", class);
     globals.add_builtin_instance_method(object, "object_id", object_id);
     globals.add_builtin_instance_method(object, "to_s", to_s);
     globals.add_builtin_instance_method(object, "inspect", inspect);
     globals.add_builtin_instance_method(object, "singleton_class", singleton_class);
+    globals.add_builtin_instance_method(object, "extend", extend);
     globals.add_builtin_instance_method(object, "clone", dup);
     globals.add_builtin_instance_method(object, "dup", dup);
     globals.add_builtin_instance_method(object, "eql?", eql);
+    globals.add_builtin_instance_method(object, "hash", hash);
     globals.add_builtin_instance_method(object, "to_i", toi);
     globals.add_builtin_instance_method(object, "instance_variable_set", instance_variable_set);
     globals.add_builtin_instance_method(object, "instance_variable_get", instance_variable_get);
     globals.add_builtin_instance_method(object, "instance_variables", instance_variables);
     globals.add_builtin_instance_method(object, "freeze", freeze);
-    globals.add_builtin_instance_method(object, "super", super_);
     globals.add_builtin_instance_method(object, "equal?", equal);
+    globals.add_builtin_instance_method(object, "==", eq);
+    globals.add_builtin_instance_method(object, "!=", neq);
     globals.add_builtin_instance_method(object, "send", send);
+    globals.add_builtin_instance_method(object, "method_missing", method_missing);
+    globals.add_builtin_instance_method(object, "respond_to?", respond_to);
+    globals.add_builtin_instance_method(object, "respond_to_missing?", respond_to_missing);
     globals.add_builtin_instance_method(object, "eval", eval);
 }
 
+fn initialize(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::nil())
+}
+
 fn class(vm: &mut VM, self_val: Value, _: &Args) -> VMResult {
     let class = self_val.get_class_object(&vm.globals);
     Ok(class)
@@ -61,6 +73,19 @@ fn singleton_class(vm: &mut VM, self_val: Value, _: &Args) -> VMResult {
     vm.get_singleton_class(self_val)
 }
 
+/// extend(module, ...)
+/// Add the instance methods of each module to the singleton class of self.
+fn extend(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_min(args.len(), 1)?;
+    builtin::module::expect_modules(vm, args)?;
+    let singleton = vm.get_singleton_class(self_val)?;
+    for module in args.iter().rev() {
+        vm.include_module(singleton, *module)?;
+        vm.call_hook(*module, "extended", self_val)?;
+    }
+    Ok(self_val)
+}
+
 fn dup(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 0)?;
     let val = self_val.dup();
@@ -72,12 +97,27 @@ fn eql(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     Ok(Value::bool(self_val == args[0]))
 }
 
+/// Return the hash value which is consistent with the key comparison of Hash.
+/// The hash value of an ordinary object is derived from its identity.
+fn hash(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    vm.check_args_num(args.len(), 0)?;
+    let mut hasher = DefaultHasher::new();
+    match self_val.is_object() {
+        Some(oref) if oref.kind == ObjKind::Ordinary => self_val.id().hash(&mut hasher),
+        _ => HashKey(self_val).hash(&mut hasher),
+    }
+    Ok(Value::fixnum(hasher.finish() as i64))
+}
+
 fn toi(vm: &mut VM, self_val: Value, _: &Args) -> VMResult {
     //vm.check_args_num(args.len(), 1, 1)?;
     let self_ = self_val;
     let num = match &self_.as_rvalue() {
         Some(info) => match &info.kind {
             ObjKind::Integer(val) => *val,
+            ObjKind::BigNum(_) => return Ok(self_val),
             ObjKind::Float(val) => f64::trunc(*val) as i64,
             _ => return Err(vm.error_type("Must be a number.")),
         },
@@ -147,50 +187,25 @@ fn freeze(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     Ok(self_val)
 }
 
-fn super_(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
-    vm.check_args_num(args.len(), 0)?;
-    let context = vm.context();
-    let iseq = context.iseq_ref;
-    if let ISeqKind::Method(m) = context.kind {
-        let class = match iseq.class_defined {
-            Some(list) => list.class,
-            None => {
-                let inspect = vm.val_inspect(self_val);
-                return Err(vm.error_nomethod(format!(
-                    "no superclass method `{}' for {}.",
-                    vm.globals.get_ident_name(m),
-                    inspect,
-                )));
-            }
-        };
-        let method = match class.superclass() {
-            Some(class) => vm.get_instance_method(class, m)?,
-            None => {
-                let inspect = vm.val_inspect(self_val);
-                return Err(vm.error_nomethod(format!(
-                    "no superclass method `{}' for {}.",
-                    vm.globals.get_ident_name(m),
-                    inspect,
-                )));
-            }
-        };
-        let param_num = iseq.params.param_ident.len();
-        let mut args = Args::new0();
-        for i in 0..param_num {
-            args.push(context[i]);
-        }
-        let val = vm.eval_send(method, context.self_value, &args)?;
-        Ok(val)
-    } else {
-        return Err(vm.error_nomethod("super called outside of method"));
-    }
-}
-
 fn equal(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 1)?;
     Ok(Value::bool(self_val.id() == args[0].id()))
 }
 
+/// Ordinary objects are equal only to themselves.
+/// Built-in objects without their own `==` are compared by value.
+fn eq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    Ok(Value::bool(self_val.equal(args[0])))
+}
+
+/// The negation of `==`, which may be redefined by users.
+fn neq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let res = vm.eval_eq(args[0], self_val)?;
+    Ok(Value::bool(!res))
+}
+
 fn send(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_min(args.len(), 1)?;
     let receiver = self_val;
@@ -198,15 +213,60 @@ fn send(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
         Some(symbol) => symbol,
         None => return Err(vm.error_argument("Must be a symbol.")),
     };
-    let method = vm.get_method(receiver, method_id)?;
-
     let mut new_args = Args::new(args.len() - 1);
     for i in 0..args.len() - 1 {
         new_args[i] = args[i + 1];
     }
     new_args.block = args.block;
-    let res = vm.eval_send(method, self_val, &new_args)?;
-    Ok(res)
+    new_args.kw_arg = args.kw_arg;
+    vm.send(receiver, method_id, &new_args)
+}
+
+/// The default `method_missing`. Raise NoMethodError.
+fn method_missing(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_min(args.len(), 1)?;
+    let method_id = match args[0].as_symbol() {
+        Some(symbol) => symbol,
+        None => return Err(vm.error_argument("No method name given.")),
+    };
+    let name = vm.globals.get_ident_name(method_id).to_owned();
+    Err(vm.error_undefined_method(name, self_val))
+}
+
+/// respond_to?(name, include_all=false)
+/// Return true if the method `name` is found, otherwise the result of `respond_to_missing?`.
+/// Private and protected methods are found only if `include_all` is true.
+fn respond_to(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let method_id = match args[0].as_symbol() {
+        Some(symbol) => symbol,
+        None => {
+            let name = vm.expect_string(&args[0], "1st arg")?.to_owned();
+            vm.globals.get_ident_id(name)
+        }
+    };
+    let include_all = if args.len() == 2 {
+        args[1]
+    } else {
+        Value::false_val()
+    };
+    if let Ok(method) = vm.get_method(self_val, method_id) {
+        if vm.val_to_bool(include_all)
+            || vm.globals.get_method_visibility(method) == Visibility::Public
+        {
+            return Ok(Value::true_val());
+        }
+    }
+    let id = vm.globals.get_ident_id("respond_to_missing?");
+    let args = Args::new2(Value::symbol(method_id), include_all);
+    let res = vm.send(self_val, id, &args)?;
+    Ok(Value::bool(vm.val_to_bool(res)))
+}
+
+/// The default `respond_to_missing?`. Always return false.
+fn respond_to_missing(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    Ok(Value::false_val())
 }
 
 fn eval(vm: &mut VM, _: Value, args: &Args) -> VMResult {
@@ -251,6 +311,28 @@ mod test {
         assert_script(program);
     }
 
+    #[test]
+    fn object_eq() {
+        let program = r#"
+        class Foo
+        end
+        a = Foo.new
+        b = Foo.new
+        assert true, a == a
+        assert false, a == b
+        assert true, a != b
+        assert false, a != a
+        assert true, a.equal?(a)
+        assert false, a.equal?(b)
+        class Bar
+            def ==(other); true end
+        end
+        assert true, Bar.new == Bar.new
+        assert false, Bar.new != Bar.new
+        "#;
+        assert_script(program);
+    }
+
     #[test]
     fn object_send() {
         let program = r#"
@@ -273,6 +355,64 @@ mod test {
         assert_script(program);
     }
 
+    #[test]
+    fn object_method_missing() {
+        let program = r#"
+        class Ghost
+            def method_missing(name, *args)
+                if name.to_s.start_with?("get_")
+                    [name, args]
+                else
+                    super
+                end
+            end
+            def respond_to_missing?(name, include_all)
+                name.to_s.start_with?("get_") || super
+            end
+        end
+        g = Ghost.new
+        assert [:get_foo, [1, 2]], g.get_foo(1, 2)
+        assert [:get_bar, []], g.send(:get_bar)
+        assert true, g.respond_to?(:get_foo)
+        assert true, g.respond_to?(:to_s)
+        assert false, g.respond_to?(:foo)
+        e = begin
+            g.foo
+        rescue NoMethodError => e
+            e
+        end
+        assert "undefined method `foo' for Ghost", e.message
+        assert false, Object.new.respond_to?(:get_foo)
+        class Store
+            def method_missing(name, *args)
+                $missing = [name, args]
+            end
+        end
+        s = Store.new
+        assert [:[], [1, 2]], s[1, 2]
+        s[3] = 4
+        assert [:[]=, [3, 4]], $missing
+        class Sym
+            def [](i)
+                i * 2
+            end
+            def []=(i, v)
+                $set = [i, v]
+            end
+        end
+        assert 6, Sym.new[3]
+        Sym.new[1] = 5
+        assert [1, 5], $set
+        e = begin
+            Object.new[0] = 1
+        rescue NoMethodError => e
+            e
+        end
+        assert NoMethodError, e.class
+        "#;
+        assert_script(program);
+    }
+
     #[test]
     fn object_yield() {
         let program = r#"
@@ -338,4 +478,52 @@ mod test {
         "#;
         assert_script(program);
     }
+
+    #[test]
+    fn object_extend() {
+        let program = r#"
+        module Greet
+            def greet
+                "hello #{name}"
+            end
+        end
+        module Loud
+            def self.extended(obj)
+                $extended = obj
+            end
+            def greet
+                super_greet = "HELLO"
+                super_greet + "!"
+            end
+        end
+        class Person
+            def name
+                "Bob"
+            end
+        end
+        bob = Person.new
+        assert(true, bob.extend(Greet).equal?(bob))
+        assert("hello Bob", bob.greet)
+        assert(false, Person.new.respond_to?(:greet))
+        assert(true, bob.respond_to?(:greet))
+        alice = Person.new
+        alice.extend(Loud, Greet)
+        assert(true, $extended.equal?(alice))
+        assert("HELLO!", alice.greet)
+        class Person
+            extend Greet
+            def self.name
+                "Person"
+            end
+        end
+        assert("hello Person", Person.greet)
+        e = begin
+            bob.extend(Person)
+        rescue TypeError => e
+            e
+        end
+        assert(TypeError, e.class)
+        "#;
+        assert_script(program);
+    }
 }
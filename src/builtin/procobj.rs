@@ -2,12 +2,85 @@ use crate::*;
 
 #[derive(Debug, Clone)]
 pub struct ProcInfo {
-    pub context: ContextRef,
+    /// A lambda checks the number of arguments, and `return` in it exits the lambda itself.
+    pub is_lambda: bool,
+    pub kind: ProcKind,
+}
+
+#[derive(Debug)]
+pub enum ProcKind {
+    /// A proc which runs the block context.
+    Block(ContextRef),
+    /// A proc created by Symbol#to_proc, which calls the method of the first argument.
+    Symbol(IdentId),
+    /// A proc created by Method#to_proc.
+    Method(Value),
+    /// A proc created by Proc#curry.
+    /// `proc` is called when `arity` arguments in total are given.
+    Curry {
+        proc: Value,
+        arity: usize,
+        args: Vec<Value>,
+    },
+    /// A proc created by Proc#>> or Proc#<<.
+    /// The result of calling `first` is passed to `second`.
+    Compose { first: Value, second: Value },
+}
+
+/// The block context is owned by each proc, so a copy of the proc has its own copy of the context.
+impl Clone for ProcKind {
+    fn clone(&self) -> Self {
+        match self {
+            ProcKind::Block(context) => ProcKind::Block(context.dup()),
+            ProcKind::Symbol(id) => ProcKind::Symbol(*id),
+            ProcKind::Method(method) => ProcKind::Method(*method),
+            ProcKind::Curry { proc, arity, args } => ProcKind::Curry {
+                proc: *proc,
+                arity: *arity,
+                args: args.clone(),
+            },
+            ProcKind::Compose { first, second } => ProcKind::Compose {
+                first: *first,
+                second: *second,
+            },
+        }
+    }
 }
 
 impl ProcInfo {
     pub fn new(context: ContextRef) -> Self {
-        ProcInfo { context }
+        ProcInfo {
+            is_lambda: false,
+            kind: ProcKind::Block(context),
+        }
+    }
+
+    /// The block context, or None if the proc is not created from a block.
+    pub fn context(&self) -> Option<ContextRef> {
+        match self.kind {
+            ProcKind::Block(context) => Some(context),
+            _ => None,
+        }
+    }
+}
+
+impl GC for ProcInfo {
+    fn mark(&self, alloc: &mut Allocator) {
+        match &self.kind {
+            ProcKind::Block(context) => context.mark(alloc),
+            ProcKind::Symbol(_) => {}
+            ProcKind::Method(method) => method.mark(alloc),
+            ProcKind::Curry { proc, args, .. } => {
+                proc.mark(alloc);
+                for arg in args {
+                    arg.mark(alloc);
+                }
+            }
+            ProcKind::Compose { first, second } => {
+                first.mark(alloc);
+                second.mark(alloc);
+            }
+        }
     }
 }
 
@@ -17,6 +90,13 @@ impl ProcRef {
     pub fn from(context: ContextRef) -> Self {
         ProcRef::new(ProcInfo::new(context))
     }
+
+    pub fn lambda(context: ContextRef) -> Self {
+        ProcRef::new(ProcInfo {
+            is_lambda: true,
+            kind: ProcKind::Block(context),
+        })
+    }
 }
 
 pub fn init_proc(globals: &mut Globals) -> Value {
@@ -24,10 +104,79 @@ pub fn init_proc(globals: &mut Globals) -> Value {
     let class = ClassRef::from(proc_id, globals.builtins.object);
     let obj = Value::class(globals, class);
     globals.add_builtin_instance_method(class, "call", proc_call);
+    globals.add_builtin_instance_method(class, "lambda?", proc_lambda);
+    globals.add_builtin_instance_method(class, "arity", proc_arity);
+    globals.add_builtin_instance_method(class, "parameters", proc_parameters);
+    globals.add_builtin_instance_method(class, "curry", proc_curry);
+    globals.add_builtin_instance_method(class, ">>", proc_compose_right);
+    globals.add_builtin_instance_method(class, "<<", proc_compose_left);
+    globals.add_builtin_instance_method(class, "to_proc", proc_to_proc);
     globals.add_builtin_class_method(obj, "new", proc_new);
     obj
 }
 
+/// Returns the arity of the proc in the manner of Proc#arity.
+/// A negative value -n-1 means that n or more arguments are required.
+fn arity(vm: &VM, pref: ProcRef) -> i64 {
+    match pref.kind {
+        ProcKind::Block(context) => params_arity(&context.iseq_ref.params, pref.is_lambda),
+        ProcKind::Symbol(_) => -2,
+        ProcKind::Method(method) => {
+            let method = method.as_method().unwrap().method;
+            match vm.globals.get_method_info(method) {
+                MethodInfo::RubyFunc { iseq } => params_arity(&iseq.params, true),
+                MethodInfo::AttrReader { .. } => 0,
+                MethodInfo::AttrWriter { .. } => 1,
+                _ => -1,
+            }
+        }
+        ProcKind::Curry { .. } | ProcKind::Compose { .. } => -1,
+    }
+}
+
+fn params_arity(params: &ISeqParams, is_lambda: bool) -> i64 {
+    let req = (params.req_params + params.post_params) as i64;
+    if !is_lambda {
+        return if params.rest_param { -req - 1 } else { req };
+    }
+    let req_kw = !params.required_keywords.is_empty();
+    let opt_kw = !req_kw && (!params.keyword_params.is_empty() || params.kwrest_param.is_some());
+    let req = if req_kw { req + 1 } else { req };
+    if params.rest_param || params.opt_params != 0 || opt_kw {
+        -req - 1
+    } else {
+        req
+    }
+}
+
+fn expect_proc(vm: &VM, val: Value) -> Result<ProcRef, RubyError> {
+    match val.as_proc() {
+        Some(pref) => Ok(pref),
+        None => Err(vm.error_unimplemented("Expected Proc object.")),
+    }
+}
+
+/// Create a Proc object which calls `first` and `second` in order.
+/// Each of them must be a Proc, a Method or an object which responds to `call`.
+fn compose(vm: &mut VM, first: Value, second: Value, is_lambda: bool) -> VMResult {
+    let callable = if first.as_proc().is_some() {
+        second
+    } else {
+        first
+    };
+    if callable.as_proc().is_none() && callable.as_method().is_none() {
+        let call = vm.globals.get_ident_id("call");
+        if vm.get_method(callable, call).is_err() {
+            return Err(vm.error_type("callable object is expected"));
+        }
+    }
+    let pref = ProcRef::new(ProcInfo {
+        is_lambda,
+        kind: ProcKind::Compose { first, second },
+    });
+    Ok(Value::procobj_from(&vm.globals, pref))
+}
+
 // Class methods
 
 fn proc_new(vm: &mut VM, _: Value, args: &Args) -> VMResult {
@@ -39,17 +188,264 @@ fn proc_new(vm: &mut VM, _: Value, args: &Args) -> VMResult {
 // Instance methods
 
 fn proc_call(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
-    let pref = match self_val.as_proc() {
-        Some(pref) => pref,
-        None => return Err(vm.error_unimplemented("Expected Proc object.")),
+    vm.eval_proc(self_val, args)
+}
+
+fn proc_lambda(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let pref = expect_proc(vm, self_val)?;
+    Ok(Value::bool(pref.is_lambda))
+}
+
+fn proc_arity(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let pref = expect_proc(vm, self_val)?;
+    Ok(Value::fixnum(arity(vm, pref)))
+}
+
+fn proc_parameters(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let pref = expect_proc(vm, self_val)?;
+    let context = match pref.context() {
+        Some(context) => context,
+        None => {
+            let rest = Value::symbol(vm.globals.get_ident_id("rest"));
+            let param = Value::array_from(&vm.globals, vec![rest]);
+            return Ok(Value::array_from(&vm.globals, vec![param]));
+        }
+    };
+    let params = &context.iseq_ref.params;
+    let req = if pref.is_lambda { "req" } else { "opt" };
+    let opt_end = params.req_params + params.opt_params;
+    let rest_end = opt_end + if params.rest_param { 1 } else { 0 };
+    let post_end = rest_end + params.post_params;
+    let mut ary = vec![];
+    for (i, id) in params.param_ident.iter().enumerate() {
+        let kind = if i < params.req_params {
+            req
+        } else if i < opt_end {
+            "opt"
+        } else if i < rest_end {
+            "rest"
+        } else if i < post_end {
+            req
+        } else if params.required_keywords.contains(id) {
+            "keyreq"
+        } else if params.keyword_params.contains_key(id) {
+            "key"
+        } else if params.block_param && i == params.param_ident.len() - 1 {
+            "block"
+        } else {
+            "keyrest"
+        };
+        let kind = Value::symbol(vm.globals.get_ident_id(kind));
+        let param = Value::array_from(&vm.globals, vec![kind, Value::symbol(*id)]);
+        ary.push(param);
+    }
+    Ok(Value::array_from(&vm.globals, ary))
+}
+
+fn proc_curry(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let pref = expect_proc(vm, self_val)?;
+    let arity = arity(vm, pref);
+    let required = if arity < 0 {
+        (-arity - 1) as usize
+    } else {
+        arity as usize
+    };
+    let curry_arity = if args.len() == 0 || args[0].is_nil() {
+        required
+    } else {
+        let given = vm.expect_integer(args[0], "1st arg")?;
+        if given < 0 {
+            return Err(vm.error_argument(format!("negative arity {}.", given)));
+        }
+        let given = given as usize;
+        if pref.is_lambda {
+            if arity >= 0 {
+                vm.check_args_num(given, required)?;
+            } else {
+                vm.check_args_min(given, required)?;
+            }
+        }
+        given
     };
-    let context = Context::from_args(
-        vm,
-        self_val,
-        pref.context.iseq_ref,
-        args,
-        pref.context.outer,
-    )?;
-    let res = vm.run_context(ContextRef::from_local(&context))?;
-    Ok(res)
+    let curry = ProcRef::new(ProcInfo {
+        is_lambda: pref.is_lambda,
+        kind: ProcKind::Curry {
+            proc: self_val,
+            arity: curry_arity,
+            args: vec![],
+        },
+    });
+    Ok(Value::procobj_from(&vm.globals, curry))
+}
+
+fn proc_compose_right(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let is_lambda = expect_proc(vm, self_val)?.is_lambda;
+    compose(vm, self_val, args[0], is_lambda)
+}
+
+fn proc_compose_left(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let is_lambda = expect_proc(vm, self_val)?.is_lambda;
+    compose(vm, args[0], self_val, is_lambda)
+}
+
+fn proc_to_proc(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(self_val)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn lambda_and_proc() {
+        let program = r#"
+        l = lambda { |x, y| x + y }
+        p = proc { |x, y| [x, y] }
+        assert(true, l.lambda?)
+        assert(false, p.lambda?)
+        assert(true, ->(x){ x }.lambda?)
+        assert(false, Proc.new { 1 }.lambda?)
+        assert(3, l.call(1, 2))
+        assert([1, nil], p.call(1))
+        assert([1, 2], p.call(1, 2, 3))
+        assert([3, 4], p.call([3, 4]))
+        e = begin
+          l.call(1)
+        rescue ArgumentError => e
+          e
+        end
+        assert(ArgumentError, e.class)
+        e = begin
+          l.call(1, 2, 3)
+        rescue ArgumentError => e
+          e
+        end
+        assert(ArgumentError, e.class)
+        e = begin
+          ->(x){ x }.call([1, 2], 3)
+        rescue ArgumentError => e
+          e
+        end
+        assert(ArgumentError, e.class)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn lambda_return() {
+        let program = r#"
+        def lambda_ret
+          l = lambda { return 10 }
+          l.call + 1
+        end
+        def proc_ret
+          p = proc { return 10 }
+          p.call + 1
+        end
+        def nested_ret
+          l = -> { [1, 2].each { |x| return x * 100 }; 0 }
+          l.call + 5
+        end
+        def proc_in_lambda
+          pr = proc { return 1 }
+          l = lambda { pr.call; 2 }
+          l.call
+          3
+        end
+        def proc_inside_lambda
+          l = lambda { pr = proc { return 1 }; pr.call; 2 }
+          l.call + 10
+        end
+        def recursive_lambda
+          l = nil
+          l = lambda { |n| return n if n == 0; l.call(n - 1) + 1 }
+          l.call(3)
+        end
+        class Foo
+          define_method(:foo) { [1, 2].each { |x| return x * 10 }; 0 }
+        end
+        assert(11, lambda_ret)
+        assert(10, proc_ret)
+        assert(105, nested_ret)
+        assert(1, proc_in_lambda)
+        assert(11, proc_inside_lambda)
+        assert(3, recursive_lambda)
+        assert(10, Foo.new.foo)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn proc_arity_parameters() {
+        let program = r#"
+        assert(2, lambda { |x, y| }.arity)
+        assert(-2, lambda { |x, y = 1, &b| }.arity)
+        assert(-1, lambda { |*x| }.arity)
+        assert(2, lambda { |x, y:| }.arity)
+        assert(-2, lambda { |x, y: 1, &b| }.arity)
+        assert(0, proc {}.arity)
+        assert(2, proc { |x, y| }.arity)
+        assert(1, proc { |x, y = 0, &b| }.arity)
+        assert(-2, proc { |x, *y| }.arity)
+        assert([[:req, :x], [:opt, :y], [:rest, :z], [:req, :w], [:keyreq, :k], [:key, :o], [:keyrest, :r], [:block, :b]],
+          lambda { |x, y = 1, *z, w, k:, o: 2, **r, &b| }.parameters)
+        assert([[:opt, :x], [:opt, :y]], proc { |x, y| }.parameters)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn proc_curry() {
+        let program = r#"
+        add = lambda { |a, b, c| a + b + c }
+        c = add.curry
+        assert(true, c.lambda?)
+        assert(6, c.call(1).call(2).call(3))
+        assert(6, c.call(1, 2).call(3))
+        assert(6, c.call(1, 2, 3))
+        sum = proc { |*a| a.sum }
+        assert(6, sum.curry(3).call(1).call(2).call(3))
+        assert(false, sum.curry.lambda?)
+        e = begin
+          add.curry(2)
+        rescue ArgumentError => e
+          e
+        end
+        assert(ArgumentError, e.class)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn proc_compose() {
+        let program = r#"
+        f = proc { |x| x * 2 }
+        g = lambda { |x| x + 1 }
+        assert(7, (f >> g).call(3))
+        assert(8, (f << g).call(3))
+        assert(false, (f >> g).lambda?)
+        assert(true, (g >> f).lambda?)
+        class Callable
+          def call(x)
+            x * 10
+          end
+        end
+        assert(60, (f >> Callable.new).call(3))
+        e = begin
+          f >> 1
+        rescue TypeError => e
+          e
+        end
+        assert(TypeError, e.class)
+        assert(f, f.to_proc)
+        "#;
+        assert_script(program);
+    }
 }
@@ -37,10 +37,7 @@ pub fn init_range(globals: &mut Globals) -> Value {
     let obj = Value::class(globals, class);
     globals.add_builtin_instance_method(class, "to_s", to_s);
     globals.add_builtin_instance_method(class, "inspect", inspect);
-    globals.add_builtin_instance_method(class, "map", map);
-    globals.add_builtin_instance_method(class, "flat_map", flat_map);
     globals.add_builtin_instance_method(class, "each", each);
-    globals.add_builtin_instance_method(class, "all?", all);
     globals.add_builtin_instance_method(class, "begin", begin);
     globals.add_builtin_instance_method(class, "first", firat);
     globals.add_builtin_instance_method(class, "end", end);
@@ -126,47 +123,17 @@ fn last(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     Ok(Value::array_from(&vm.globals, v))
 }
 
-fn map(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
-    let range = self_val.as_range().unwrap();
-    let method = vm.expect_block(args.block)?;
-    let mut res = vec![];
-    let start = range.start.expect_integer(&vm, "Start")?;
-    let end = range.end.expect_integer(&vm, "End")? + if range.exclude { 0 } else { 1 };
-    let mut arg = Args::new1(Value::nil());
-    for i in start..end {
-        arg[0] = Value::fixnum(i);
-        let val = vm.eval_block(method, &arg)?;
-        res.push(val);
-    }
-    let res = Value::array_from(&vm.globals, res);
-    Ok(res)
-}
-
-fn flat_map(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
-    let range = self_val.as_range().unwrap();
-    let method = vm.expect_block(args.block)?;
-    let mut res = vec![];
-    let start = range.start.expect_integer(&vm, "Start")?;
-    let end = range.end.expect_integer(&vm, "End")? + if range.exclude { 0 } else { 1 };
-    let mut arg = Args::new1(Value::nil());
-    for i in start..end {
-        arg[0] = Value::fixnum(i);
-        let val = vm.eval_block(method, &arg)?;
-        match val.as_array() {
-            Some(aref) => {
-                let mut other = aref.elements.clone();
-                res.append(&mut other);
-            }
-            None => res.push(val),
-        };
-    }
-    let res = Value::array_from(&vm.globals, res);
-    Ok(res)
-}
-
 fn each(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let range = self_val.as_range().unwrap();
-    let method = vm.expect_block(args.block)?;
+    let method = match args.block {
+        Some(method) => method,
+        None => {
+            let id = vm.globals.get_ident_id("each");
+            let size = EnumSize::Func(receiver_size);
+            let val = Value::enumerator_with_size(&vm.globals, id, self_val, args.clone(), size);
+            return Ok(val);
+        }
+    };
     let start = range.start.expect_integer(&vm, "Start")?;
     let end = range.end.expect_integer(&vm, "End")? + if range.exclude { 0 } else { 1 };
     for i in start..end {
@@ -176,21 +143,6 @@ fn each(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     Ok(self_val)
 }
 
-fn all(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
-    let range = self_val.as_range().unwrap();
-    let method = vm.expect_block(args.block)?;
-    let start = range.start.expect_integer(&vm, "Start")?;
-    let end = range.end.expect_integer(&vm, "End")? + if range.exclude { 0 } else { 1 };
-    for i in start..end {
-        let arg = Args::new1(Value::fixnum(i));
-        let res = vm.eval_block(method, &arg)?;
-        if !vm.val_to_bool(res) {
-            return Ok(Value::false_val());
-        }
-    }
-    Ok(Value::true_val())
-}
-
 fn to_a(vm: &mut VM, self_val: Value, _: &Args) -> VMResult {
     let range = self_val.as_range().unwrap();
     let start = range.start.expect_integer(&vm, "Range.start")?;
@@ -515,12 +515,17 @@ fn string_scan(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     } else {
         return Err(vm.error_argument("1st arg must be RegExp or String."));
     };
+    for val in &vec {
+        vm.temp_push(*val);
+    }
     match args.block {
         Some(block) if block == MethodRef::from(0) => {
             let mut v = vec![];
             for arg in vec {
                 let block_args = Args::new1(arg);
-                v.push(vm.eval_block(block, &block_args)?);
+                let val = vm.eval_block(block, &block_args)?;
+                vm.temp_push(val);
+                v.push(val);
             }
             Ok(Value::array_from(&vm.globals, v))
         }
@@ -5,6 +5,13 @@ pub fn init_struct(globals: &mut Globals) -> Value {
     let class = ClassRef::from(id, globals.builtins.object);
     let class = Value::class(globals, class);
     globals.add_builtin_class_method(class, "new", struct_new);
+    globals.add_builtin_instance_method(class.as_class(), "each", each);
+    globals.add_builtin_instance_method(class.as_class(), "==", eq);
+    globals.add_builtin_instance_method(class.as_class(), "eql?", eql);
+    globals.add_builtin_instance_method(class.as_class(), "hash", hash);
+    let id = globals.get_ident_id("Enumerable");
+    let enumerable = globals.builtins.object.get_var(id).unwrap();
+    class.as_class().include.push(enumerable);
     class
 }
 
@@ -25,6 +32,7 @@ fn struct_new(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     };
 
     let mut val = Value::class_from(&mut vm.globals, name, self_val);
+    vm.temp_push(val);
     let class = val.as_class();
     vm.globals
         .add_builtin_instance_method(class, "initialize", initialize);
@@ -82,17 +90,21 @@ fn initialize(vm: &mut VM, mut self_val: Value, args: &Args) -> VMResult {
     Ok(Value::nil())
 }
 
-fn inspect(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
-    let members = match self_val
+fn get_members(vm: &mut VM, self_val: Value) -> Result<ArrayRef, RubyError> {
+    match self_val
         .get_class_object(&vm.globals)
         .get_var(vm.globals.get_ident_id("_members"))
     {
         Some(v) => match v.as_array() {
-            Some(aref) => aref,
-            None => return Err(vm.error_internal("Illegal _members value.")),
+            Some(aref) => Ok(aref),
+            None => Err(vm.error_internal("Illegal _members value.")),
         },
-        None => return Err(vm.error_internal("No _members.")),
-    };
+        None => Err(vm.error_internal("No _members.")),
+    }
+}
+
+fn inspect(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    let members = get_members(vm, self_val)?;
     let attrs: Vec<IdentId> = members
         .elements
         .iter()
@@ -120,6 +132,96 @@ fn inspect(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
     Ok(Value::string(&vm.globals, inspect))
 }
 
+/// Call the block with the value of each member.
+fn each(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let block = match args.block {
+        Some(block) => block,
+        None => {
+            let id = vm.globals.get_ident_id("each");
+            let val = Value::enumerator(&vm.globals, id, self_val, args.clone());
+            return Ok(val);
+        }
+    };
+    for val in get_values(vm, self_val)? {
+        vm.eval_block(block, &Args::new1(val))?;
+    }
+    Ok(self_val)
+}
+
+/// Get the values of the members.
+fn get_values(vm: &mut VM, self_val: Value) -> Result<Vec<Value>, RubyError> {
+    let members = get_members(vm, self_val)?;
+    let mut values = vec![];
+    for member in members.elements.iter() {
+        let name = format!(
+            "@{}",
+            vm.globals.get_ident_name(member.as_symbol().unwrap())
+        );
+        let id = vm.globals.get_ident_id(name);
+        values.push(self_val.get_var(id).unwrap_or_default());
+    }
+    Ok(values)
+}
+
+/// Get the values of the members of `self_val` and `other`.
+/// Return None if `other` is not an instance of the same class.
+fn get_pair_values(
+    vm: &mut VM,
+    self_val: Value,
+    other: Value,
+) -> Result<Option<(Vec<Value>, Vec<Value>)>, RubyError> {
+    if self_val.get_class_object(&vm.globals).id() != other.get_class_object(&vm.globals).id() {
+        return Ok(None);
+    }
+    let lhs = get_values(vm, self_val)?;
+    let rhs = get_values(vm, other)?;
+    Ok(Some((lhs, rhs)))
+}
+
+/// Return true if `other` is an instance of the same class and all members are equal by `==`.
+fn eq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let (lhs, rhs) = match get_pair_values(vm, self_val, args[0])? {
+        Some(values) => values,
+        None => return Ok(Value::false_val()),
+    };
+    for (l, r) in lhs.into_iter().zip(rhs) {
+        if !vm.eval_eq(r, l)? {
+            return Ok(Value::false_val());
+        }
+    }
+    Ok(Value::true_val())
+}
+
+/// Return true if `other` is an instance of the same class and all members are equal by `eql?`.
+fn eql(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let res = match get_pair_values(vm, self_val, args[0])? {
+        Some((lhs, rhs)) => lhs
+            .into_iter()
+            .zip(rhs)
+            .all(|(l, r)| HashKey(l) == HashKey(r)),
+        None => false,
+    };
+    Ok(Value::bool(res))
+}
+
+fn hash(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    vm.check_args_num(args.len(), 0)?;
+    let mut hasher = DefaultHasher::new();
+    self_val
+        .get_class_object(&vm.globals)
+        .id()
+        .hash(&mut hasher);
+    for val in get_values(vm, self_val)? {
+        HashKey(val).hash(&mut hasher);
+    }
+    Ok(Value::fixnum(hasher.finish() as i64))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::test::*;
@@ -134,6 +236,8 @@ mod tests {
         end
         assert "Hello Dave!", Customer.new("Dave", "123 Main").greeting
         assert "Hello Gave!", Customer["Gave", "456 Sub"].greeting
+        assert ["Dave", "123 Main"], Customer.new("Dave", "123 Main").to_a
+        assert ["DAVE"], Customer.new("Dave", nil).select { |x| x }.map { |x| x.upcase }
         "#;
         assert_script(program);
     }
@@ -0,0 +1,132 @@
+use crate::*;
+
+pub fn init_symbol(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("Symbol");
+    let class = ClassRef::from(id, globals.builtins.object);
+    globals.add_builtin_instance_method(class, "to_proc", to_proc);
+    Value::class(globals, class)
+}
+
+// Instance methods
+
+fn to_proc(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let id = self_val.as_symbol().unwrap();
+    let method = vm.globals.get_symbol_proc(id);
+    vm.create_proc(method)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+    use crate::*;
+
+    #[test]
+    fn symbol_to_proc() {
+        let program = r#"
+        assert(Symbol, :foo.class)
+        assert(["A", "B"], ["a", "b"].map(&:upcase))
+        assert(["1", "2"], [1, 2].map(&:to_s))
+        assert(10, [1, 2, 3, 4].inject(&:+))
+        pr = :upcase.to_proc
+        assert(true, pr.lambda?)
+        assert("FOO", pr.call("foo"))
+        assert(7, :+.to_proc.call(3, 4))
+        e = begin
+          pr.call
+        rescue ArgumentError => e
+          e
+        end
+        assert(ArgumentError, e.class)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn block_arg_to_proc() {
+        let program = r#"
+        class Doubler
+          def to_proc
+            proc { |x| x * 2 }
+          end
+        end
+        assert([2, 4], [1, 2].map(&Doubler.new))
+        def m(x)
+          x + 100
+        end
+        assert([101, 102], [1, 2].map(&method(:m)))
+        assert(true, method(:m).to_proc.lambda?)
+        assert(1, method(:m).to_proc.arity)
+        assert(105, method(:m).to_proc.call(5))
+        add = ->(x) { x + 1 }
+        assert([2, 3], [1, 2].map(&add))
+        assert([2, 3], [1, 2].map(&(add >> ->(x) { x * 1 })))
+        def give(&b)
+          b
+        end
+        assert(true, give(&:upcase).lambda?)
+        assert(add, give(&add))
+        assert(nil, give(&nil))
+        def kw(a, k: 0, &b)
+          [a, k, b.call(a)]
+        end
+        assert([3, 5, 6], kw(3, k: 5, &Doubler.new))
+        assert([3, 0, 6], kw(3, &Doubler.new))
+        e = begin
+          [1].map(&1)
+        rescue TypeError => e
+          e
+        end
+        assert(TypeError, e.class)
+        sum = ->(a, b) { a + b }
+        assert([3, 7], [[1, 2], [3, 4]].map(&sum))
+        assert([3, 7], [[1, 2], [3, 4]].flat_map(&sum))
+        assert([1, 2, 3, 4], [[1, 2], [3, 4]].flat_map { |a| a })
+        assert([1, 3], [[1, 2], [3, 4]].flat_map { |a, b| a })
+        assert([:x, :x], [[1, 2], [3, 4]].flat_map { :x })
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn block_arg_reuse() {
+        let program = r#"
+        $add = ->(x) { x + 1 }
+        def f
+          [1].map(&$add)
+        end
+        f
+        "#;
+        let mut vm = VM::new();
+        vm.run(std::path::PathBuf::from(""), program, None).unwrap();
+        let before: u32 = vm.globals.new_method().into();
+        let program = "1000.times { f }";
+        vm.run(std::path::PathBuf::from(""), program, None).unwrap();
+        let after: u32 = vm.globals.new_method().into();
+        assert!(after - before < 10);
+    }
+
+    #[test]
+    fn block_arg_free() {
+        let program = r#"
+        GC.start
+        live = GC.stat[:heap_live_slots]
+        20000.times { [1].each(&->(x) {}) }
+        GC.start
+        assert(true, GC.stat[:heap_live_slots] - live < 1000)
+        def gc_in_block
+          yield
+        end
+        assert(3, gc_in_block(&->() { GC.start; 3 }))
+        assert([2], [1].map(&->(x) { GC.start; x + 1 }))
+        def yield_later
+          proc { yield 5 }
+        end
+        pr = yield_later(&->(x) { x * 2 })
+        20000.times { [1].each(&->(x) {}) }
+        GC.start
+        assert(10, pr.call)
+        "#;
+        assert_script(program);
+    }
+}
@@ -0,0 +1,219 @@
+//! Stackful coroutine for Fiber.
+//!
+//! On x86_64 Unix, each coroutine runs on its own machine stack, and the execution
+//! is switched by saving and restoring the stack pointer and callee-saved registers.
+//! On other targets, each coroutine runs on its own thread, and the execution is
+//! handed over between the threads through channels.
+#[cfg(all(target_arch = "x86_64", unix))]
+pub use native::Coroutine;
+#[cfg(not(all(target_arch = "x86_64", unix)))]
+pub use thread::Coroutine;
+
+const STACK_SIZE: usize = 1024 * 1024 * 2;
+
+/// The panic payload which unwinds the stack of a coroutine dropped while suspended.
+/// It must not be caught, or must be resumed with `std::panic::resume_unwind` if caught.
+#[derive(Debug)]
+pub struct Cancelled;
+
+#[cfg(all(target_arch = "x86_64", unix))]
+mod native {
+    use super::STACK_SIZE;
+    use std::arch::asm;
+
+    #[derive(Debug)]
+    pub struct Coroutine {
+        /// The lowest address of the mapped stack, which starts with a guard page.
+        stack: *mut u8,
+        /// Size of the mapped stack including the guard page.
+        map_size: usize,
+        /// Stack pointer of the coroutine, saved while the coroutine is suspended.
+        sp: *mut u8,
+        /// Stack pointer of the resumer, saved while the coroutine is running.
+        parent_sp: *mut u8,
+    }
+
+    impl Coroutine {
+        /// Create a new coroutine which calls `entry(arg)` when resumed for the first time.
+        /// `entry` must not return. It must suspend the coroutine at the end instead,
+        /// and the coroutine must not be resumed after that.
+        pub fn new(entry: extern "C-unwind" fn(usize) -> !, arg: usize) -> Self {
+            let guard_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+            let map_size = STACK_SIZE + guard_size;
+            let stack = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    map_size,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+                    -1,
+                    0,
+                )
+            };
+            if stack == libc::MAP_FAILED {
+                panic!(
+                    "Failed to allocate a stack for the coroutine: {}",
+                    std::io::Error::last_os_error()
+                );
+            }
+            // The stack grows downward, so the lowest page is made inaccessible in order to
+            // turn a stack overflow into a segmentation fault instead of memory corruption.
+            if unsafe { libc::mprotect(stack, guard_size, libc::PROT_NONE) } != 0 {
+                panic!(
+                    "Failed to protect the guard page of the coroutine: {}",
+                    std::io::Error::last_os_error()
+                );
+            }
+            let stack = stack as *mut u8;
+            // Initial frame which is popped by switch(): rdi, rbx, rbp, and the return address.
+            // The return address is placed so that the stack is aligned as if `entry` was called.
+            let sp = unsafe {
+                let top = stack.add(map_size) as *mut usize;
+                *top.sub(1) = 0;
+                *top.sub(2) = entry as usize;
+                *top.sub(3) = 0;
+                *top.sub(4) = 0;
+                *top.sub(5) = arg;
+                top.sub(5) as *mut u8
+            };
+            Coroutine {
+                stack,
+                map_size,
+                sp,
+                parent_sp: std::ptr::null_mut(),
+            }
+        }
+
+        /// Switch to the coroutine. This returns when the coroutine is suspended.
+        ///
+        /// # Safety
+        /// The coroutine must not be running or finished.
+        pub unsafe fn resume(&mut self) {
+            switch(&mut self.parent_sp, self.sp);
+        }
+
+        /// Switch back to the resumer. This returns when the coroutine is resumed again.
+        ///
+        /// # Safety
+        /// This must be called on the stack of the coroutine.
+        pub unsafe fn suspend(&mut self) {
+            switch(&mut self.sp, self.parent_sp);
+        }
+    }
+
+    impl Drop for Coroutine {
+        fn drop(&mut self) {
+            unsafe { libc::munmap(self.stack as *mut libc::c_void, self.map_size) };
+        }
+    }
+
+    /// Save the current stack pointer to `save_sp`, and switch to the stack `sp`.
+    #[inline(never)]
+    unsafe fn switch(save_sp: *mut *mut u8, sp: *mut u8) {
+        asm!(
+            "lea rax, [rip + 2f]",
+            "push rax",
+            "push rbp",
+            "push rbx",
+            "push rdi",
+            "mov [rdi], rsp",
+            "mov rsp, rsi",
+            "pop rdi",
+            "pop rbx",
+            "pop rbp",
+            "ret",
+            "2:",
+            in("rdi") save_sp,
+            in("rsi") sp,
+            out("r12") _,
+            out("r13") _,
+            out("r14") _,
+            out("r15") _,
+            clobber_abi("C"),
+        );
+    }
+}
+
+#[cfg(not(all(target_arch = "x86_64", unix)))]
+mod thread {
+    use super::{Cancelled, STACK_SIZE};
+    use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+    use std::thread::JoinHandle;
+
+    #[derive(Debug)]
+    pub struct Coroutine {
+        entry: extern "C-unwind" fn(usize) -> !,
+        arg: usize,
+        /// The thread of the coroutine, which is spawned when resumed for the first time.
+        thread: Option<JoinHandle<()>>,
+        /// Used by the resumer to let the coroutine run.
+        resume_tx: Option<SyncSender<()>>,
+        /// Used by the coroutine to wait for the resumer.
+        resume_rx: Receiver<()>,
+        /// Used by the coroutine to hand the execution back to the resumer.
+        suspend_tx: SyncSender<()>,
+        /// Used by the resumer to wait for the coroutine.
+        suspend_rx: Receiver<()>,
+    }
+
+    impl Coroutine {
+        /// Create a new coroutine which calls `entry(arg)` when resumed for the first time.
+        /// `entry` must not return. It must suspend the coroutine at the end instead,
+        /// and the coroutine must not be resumed after that.
+        pub fn new(entry: extern "C-unwind" fn(usize) -> !, arg: usize) -> Self {
+            let (resume_tx, resume_rx) = sync_channel(0);
+            let (suspend_tx, suspend_rx) = sync_channel(0);
+            Coroutine {
+                entry,
+                arg,
+                thread: None,
+                resume_tx: Some(resume_tx),
+                resume_rx,
+                suspend_tx,
+                suspend_rx,
+            }
+        }
+
+        /// Switch to the coroutine. This returns when the coroutine is suspended.
+        ///
+        /// # Safety
+        /// The coroutine must not be running or finished.
+        pub unsafe fn resume(&mut self) {
+            match self.thread {
+                Some(_) => self.resume_tx.as_ref().unwrap().send(()).unwrap(),
+                None => {
+                    let (entry, arg) = (self.entry, self.arg);
+                    let thread = std::thread::Builder::new()
+                        .stack_size(STACK_SIZE)
+                        .spawn(move || entry(arg))
+                        .expect("Failed to spawn a thread for the coroutine.");
+                    self.thread = Some(thread);
+                }
+            }
+            self.suspend_rx.recv().unwrap();
+        }
+
+        /// Switch back to the resumer. This returns when the coroutine is resumed again.
+        /// When the coroutine is dropped instead, this unwinds the stack of the coroutine
+        /// with `Cancelled`.
+        ///
+        /// # Safety
+        /// This must be called on the thread of the coroutine.
+        pub unsafe fn suspend(&mut self) {
+            self.suspend_tx.send(()).unwrap();
+            if self.resume_rx.recv().is_err() {
+                std::panic::resume_unwind(Box::new(Cancelled));
+            }
+        }
+    }
+
+    impl Drop for Coroutine {
+        fn drop(&mut self) {
+            // Wake up the suspended coroutine, and wait until its stack is unwound.
+            self.resume_tx = None;
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
@@ -11,7 +11,11 @@ pub struct RubyError {
 pub enum RubyErrorKind {
     ParseErr(ParseErrKind),
     RuntimeErr(RuntimeErrKind),
+    /// Exception object raised in Ruby code, and its description for error reports.
+    Exception(Value, String),
     MethodReturn(MethodRef),
+    /// `return` in a lambda, which is caught by the lambda call with the id.
+    LambdaReturn(usize),
     BlockReturn,
 }
 
@@ -31,9 +35,49 @@ pub enum RuntimeErrKind {
     NoMethod(String),
     Argument(String),
     Index(String),
+    Range(String),
     Type(String),
     Regexp(String),
     Fiber(String),
+    ZeroDivision(String),
+    StopIteration(String),
+}
+
+impl RuntimeErrKind {
+    pub fn message(&self) -> &str {
+        match self {
+            RuntimeErrKind::Unimplemented(s)
+            | RuntimeErrKind::Internal(s)
+            | RuntimeErrKind::Name(s)
+            | RuntimeErrKind::NoMethod(s)
+            | RuntimeErrKind::Argument(s)
+            | RuntimeErrKind::Index(s)
+            | RuntimeErrKind::Range(s)
+            | RuntimeErrKind::Type(s)
+            | RuntimeErrKind::Regexp(s)
+            | RuntimeErrKind::Fiber(s)
+            | RuntimeErrKind::ZeroDivision(s)
+            | RuntimeErrKind::StopIteration(s) => s,
+        }
+    }
+
+    /// Name of the Ruby exception class which corresponds to the error.
+    pub fn class_name(&self) -> &'static str {
+        match self {
+            RuntimeErrKind::Unimplemented(_) => "NotImplementedError",
+            RuntimeErrKind::Internal(_) => "RuntimeError",
+            RuntimeErrKind::Name(_) => "NameError",
+            RuntimeErrKind::NoMethod(_) => "NoMethodError",
+            RuntimeErrKind::Argument(_) => "ArgumentError",
+            RuntimeErrKind::Index(_) => "IndexError",
+            RuntimeErrKind::Range(_) => "RangeError",
+            RuntimeErrKind::Type(_) => "TypeError",
+            RuntimeErrKind::Regexp(_) => "RegexpError",
+            RuntimeErrKind::Fiber(_) => "FiberError",
+            RuntimeErrKind::ZeroDivision(_) => "ZeroDivisionError",
+            RuntimeErrKind::StopIteration(_) => "StopIteration",
+        }
+    }
 }
 
 impl RubyError {
@@ -73,18 +117,9 @@ impl RubyError {
                 ParseErrKind::SyntaxError(n) => eprintln!("SyntaxError: {}", n),
                 ParseErrKind::LoadError(n) => eprintln!("LoadError: {}", n),
             },
-            RubyErrorKind::RuntimeErr(e) => match e {
-                RuntimeErrKind::Name(n) => eprintln!("NoNameError ({})", n),
-                RuntimeErrKind::NoMethod(n) => eprintln!("NoMethodError ({})", n),
-                RuntimeErrKind::Type(n) => eprintln!("TypeError ({})", n),
-                RuntimeErrKind::Unimplemented(n) => eprintln!("UnimplementedError ({})", n),
-                RuntimeErrKind::Internal(n) => eprintln!("InternalError ({})", n),
-                RuntimeErrKind::Argument(n) => eprintln!("ArgumentError ({})", n),
-                RuntimeErrKind::Index(n) => eprintln!("IndexError ({})", n),
-                RuntimeErrKind::Regexp(n) => eprintln!("RegexpError ({})", n),
-                RuntimeErrKind::Fiber(n) => eprintln!("FiberError ({})", n),
-            },
-            RubyErrorKind::MethodReturn(_) => {
+            RubyErrorKind::RuntimeErr(e) => eprintln!("{} ({})", e.class_name(), e.message()),
+            RubyErrorKind::Exception(_, desc) => eprintln!("{}", desc),
+            RubyErrorKind::MethodReturn(_) | RubyErrorKind::LambdaReturn(_) => {
                 eprintln!("LocalJumpError");
             }
             RubyErrorKind::BlockReturn => {
@@ -110,10 +145,28 @@ impl RubyError {
         RubyError::new(kind, source_info, level, loc)
     }
 
+    pub fn new_exception(
+        exception: Value,
+        desc: String,
+        source_info: SourceInfoRef,
+        loc: Loc,
+    ) -> Self {
+        RubyError::new(
+            RubyErrorKind::Exception(exception, desc),
+            source_info,
+            0,
+            loc,
+        )
+    }
+
     pub fn new_method_return(method: MethodRef, source_info: SourceInfoRef, loc: Loc) -> Self {
         RubyError::new(RubyErrorKind::MethodReturn(method), source_info, 0, loc)
     }
 
+    pub fn new_lambda_return(id: usize, source_info: SourceInfoRef, loc: Loc) -> Self {
+        RubyError::new(RubyErrorKind::LambdaReturn(id), source_info, 0, loc)
+    }
+
     pub fn new_block_return(source_info: SourceInfoRef, loc: Loc) -> Self {
         RubyError::new(RubyErrorKind::BlockReturn, source_info, 0, loc)
     }
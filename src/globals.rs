@@ -6,23 +6,54 @@ pub struct Globals {
     // Global info
     pub ident_table: IdentifierTable,
     pub global_var: ValueTable,
+    /// Aliases of global variables. (new name -> original name)
+    pub global_alias: HashMap<IdentId, IdentId>,
+    /// Procs registered by `at_exit`.
+    pub at_exit_procs: Vec<Value>,
     method_table: GlobalMethodTable,
+    /// Blocks created from Symbols by `&:sym`, which are shared among call sites.
+    symbol_procs: HashMap<IdentId, MethodRef>,
+    /// Blocks created from Proc objects by `&proc`, indexed by the id of the Proc.
+    /// A Proc is not kept alive by its block, and the block is removed when the Proc is freed.
+    proc_blocks: HashMap<u64, MethodRef>,
+    /// Blocks which call closures given to `VM::eval_with_native_block`, indexed by the nesting depth.
+    native_blocks: Vec<MethodRef>,
     inline_cache: InlineCache,
     method_cache: MethodCache,
     pub instant: std::time::Instant,
     /// version counter: increment when new instance / class methods are defined.
     pub class_version: usize,
+    /// Counter to give each lambda call a unique id.
+    pub lambda_count: usize,
     pub main_object: Value,
     pub builtins: BuiltinClass,
     pub class_class: ClassRef,
     pub module_class: ClassRef,
     pub object_class: ClassRef,
+    pub allocator: AllocatorRef,
 
     case_dispatch: CaseDispatchMap,
 }
 
 pub type GlobalsRef = Ref<Globals>;
 
+impl GC for Globals {
+    fn mark(&self, alloc: &mut Allocator) {
+        self.main_object.mark(alloc);
+        self.builtins.mark(alloc);
+        for val in self.global_var.values() {
+            val.mark(alloc);
+        }
+        for val in &self.at_exit_procs {
+            val.mark(alloc);
+        }
+        self.method_table.mark(alloc);
+        self.inline_cache.mark(alloc);
+        self.method_cache.mark(alloc);
+        self.case_dispatch.mark(alloc);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BuiltinClass {
     pub integer: Value,
@@ -36,9 +67,32 @@ pub struct BuiltinClass {
     pub hash: Value,
     pub regexp: Value,
     pub string: Value,
+    pub symbol: Value,
     pub fiber: Value,
     pub object: Value,
     pub enumerator: Value,
+    pub exception: Value,
+}
+
+impl GC for BuiltinClass {
+    fn mark(&self, alloc: &mut Allocator) {
+        self.integer.mark(alloc);
+        self.float.mark(alloc);
+        self.array.mark(alloc);
+        self.class.mark(alloc);
+        self.module.mark(alloc);
+        self.procobj.mark(alloc);
+        self.method.mark(alloc);
+        self.range.mark(alloc);
+        self.hash.mark(alloc);
+        self.regexp.mark(alloc);
+        self.string.mark(alloc);
+        self.symbol.mark(alloc);
+        self.fiber.mark(alloc);
+        self.object.mark(alloc);
+        self.enumerator.mark(alloc);
+        self.exception.mark(alloc);
+    }
 }
 
 impl BuiltinClass {
@@ -56,8 +110,10 @@ impl BuiltinClass {
             hash: nil,
             regexp: nil,
             string: nil,
+            symbol: nil,
             fiber: nil,
             enumerator: nil,
+            exception: nil,
             object,
         }
     }
@@ -66,6 +122,7 @@ impl BuiltinClass {
 impl Globals {
     pub fn new() -> Self {
         use builtin::*;
+        let allocator = Allocator::init();
         let mut ident_table = IdentifierTable::new();
         let object_id = IdentId::OBJECT;
         let module_id = ident_table.get_ident_id("Module");
@@ -86,16 +143,23 @@ impl Globals {
         let mut globals = Globals {
             ident_table,
             global_var: HashMap::new(),
+            global_alias: HashMap::new(),
+            at_exit_procs: vec![],
+            symbol_procs: HashMap::new(),
+            proc_blocks: HashMap::new(),
+            native_blocks: vec![],
             method_table: GlobalMethodTable::new(),
             inline_cache: InlineCache::new(),
             method_cache: MethodCache::new(),
             instant: std::time::Instant::now(),
             class_version: 0,
+            lambda_count: 0,
             main_object,
             object_class,
             module_class,
             class_class,
             builtins,
+            allocator,
             case_dispatch: CaseDispatchMap::new(),
         };
         // Generate singleton class for Object
@@ -113,6 +177,7 @@ impl Globals {
         globals.builtins.method = method::init_method(&mut globals);
         globals.builtins.range = range::init_range(&mut globals);
         globals.builtins.string = string::init_string(&mut globals);
+        globals.builtins.symbol = symbol::init_symbol(&mut globals);
         globals.builtins.hash = hash::init_hash(&mut globals);
         globals.builtins.regexp = regexp::init_regexp(&mut globals);
         globals.builtins.fiber = fiber::init_fiber(&mut globals);
@@ -120,6 +185,25 @@ impl Globals {
         object::init(&mut globals);
         let kernel = kernel::init(&mut globals);
         object_class.include.push(kernel);
+        let id = globals.get_ident_id("Kernel");
+        globals.builtins.object.set_var(id, kernel);
+        let enumerable = enumerable::init_enumerable(&mut globals);
+        let id = globals.get_ident_id("Enumerable");
+        globals.builtins.object.set_var(id, enumerable);
+        for class in &[
+            globals.builtins.array,
+            globals.builtins.hash,
+            globals.builtins.range,
+            globals.builtins.enumerator,
+        ] {
+            class.as_class().include.push(enumerable);
+        }
+        let comparable = comparable::init_comparable(&mut globals);
+        let id = globals.get_ident_id("Comparable");
+        globals.builtins.object.set_var(id, comparable);
+        for class in &[globals.builtins.integer, globals.builtins.float] {
+            class.as_class().include.push(comparable);
+        }
         globals
     }
 
@@ -155,6 +239,73 @@ impl Globals {
         self.method_table.get_method(method)
     }
 
+    pub fn get_method_visibility(&self, method: MethodRef) -> Visibility {
+        self.method_table.get_visibility(method)
+    }
+
+    pub fn set_method_visibility(&mut self, method: MethodRef, visibility: Visibility) {
+        self.method_table.set_visibility(method, visibility);
+    }
+
+    /// Get the block which calls the method `id` of the first argument.
+    pub fn get_symbol_proc(&mut self, id: IdentId) -> MethodRef {
+        match self.symbol_procs.get(&id) {
+            Some(method) => *method,
+            None => {
+                let method = self.add_method(MethodInfo::SymbolProc { id });
+                self.symbol_procs.insert(id, method);
+                method
+            }
+        }
+    }
+
+    /// Get the block which calls the Proc object `proc_obj`.
+    pub fn get_proc_block(&mut self, proc_obj: Value) -> MethodRef {
+        match self.proc_blocks.get(&proc_obj.id()) {
+            Some(method) => *method,
+            None => {
+                let method = self.add_method(MethodInfo::ProcBlock { proc: proc_obj });
+                self.proc_blocks.insert(proc_obj.id(), method);
+                method
+            }
+        }
+    }
+
+    /// Remove the blocks whose Procs were freed by the last GC.
+    /// This must be called right after GC, before the slots of the Procs are reused.
+    pub fn sweep_proc_blocks(&mut self) {
+        let alloc = self.allocator;
+        let method_table = &mut self.method_table;
+        self.proc_blocks.retain(|_, method| {
+            let alive = match method_table.get_method(*method) {
+                MethodInfo::ProcBlock { proc } => alloc.is_alive(proc.as_object()),
+                _ => false,
+            };
+            if !alive {
+                method_table.remove_method(*method);
+            }
+            alive
+        });
+    }
+
+    /// Get the block which calls the closure given to `VM::eval_with_native_block` at `depth`.
+    pub fn get_native_block(&mut self, depth: usize) -> MethodRef {
+        while self.native_blocks.len() <= depth {
+            let depth = self.native_blocks.len();
+            let method = self.add_method(MethodInfo::NativeBlock { depth });
+            self.native_blocks.push(method);
+        }
+        self.native_blocks[depth]
+    }
+
+    /// Return true if `method` is a marker of an undefined method.
+    pub fn is_void_method(&self, method: MethodRef) -> bool {
+        match self.get_method_info(method) {
+            MethodInfo::Void => true,
+            _ => false,
+        }
+    }
+
     pub fn get_mut_method_info(&mut self, method: MethodRef) -> &mut MethodInfo {
         self.method_table.get_mut_method(method)
     }
@@ -235,10 +386,13 @@ impl Globals {
                 ObjKind::Proc(_) => "Proc".to_string(),
                 ObjKind::Method(_) => "Method".to_string(),
                 ObjKind::Ordinary => oref.class_name(self).to_string(),
-                ObjKind::Integer(_) => "Integer".to_string(),
+                ObjKind::Integer(_) | ObjKind::BigNum(_) => "Integer".to_string(),
                 ObjKind::Float(_) => "Float".to_string(),
                 ObjKind::Fiber(_) => "Fiber".to_string(),
                 ObjKind::Enumerator(_) => "Enumerator".to_string(),
+                ObjKind::Exception(_) | ObjKind::Generator(_) | ObjKind::Yielder(_) => {
+                    oref.class_name(self).to_string()
+                }
             },
         }
     }
@@ -318,6 +472,14 @@ pub struct MethodCacheEntry {
     pub version: usize,
 }
 
+impl GC for MethodCache {
+    fn mark(&self, alloc: &mut Allocator) {
+        for (class, _) in self.0.keys() {
+            class.mark(alloc);
+        }
+    }
+}
+
 impl MethodCache {
     fn new() -> Self {
         MethodCache(HashMap::new())
@@ -354,6 +516,14 @@ pub struct InlineCacheEntry {
     method: MethodRef,
 }
 
+impl GC for InlineCache {
+    fn mark(&self, alloc: &mut Allocator) {
+        for entry in self.table.iter().flatten() {
+            entry.class.mark(alloc);
+        }
+    }
+}
+
 impl InlineCache {
     fn new() -> Self {
         InlineCache {
@@ -385,6 +555,16 @@ pub struct CaseDispatchMap {
     id: u32,
 }
 
+impl GC for CaseDispatchMap {
+    fn mark(&self, alloc: &mut Allocator) {
+        for map in &self.table {
+            for key in map.keys() {
+                key.mark(alloc);
+            }
+        }
+    }
+}
+
 impl CaseDispatchMap {
     fn new() -> Self {
         CaseDispatchMap {
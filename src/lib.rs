@@ -2,7 +2,9 @@
 #![feature(box_patterns)]
 #![feature(cow_is_borrowed)]
 extern crate fancy_regex;
+pub mod alloc;
 pub mod builtin;
+pub mod coroutine;
 pub mod error;
 pub mod globals;
 pub mod loader;
@@ -11,7 +13,9 @@ pub mod test;
 pub mod util;
 pub mod value;
 pub mod vm;
+pub use crate::alloc::*;
 pub use crate::builtin::enumerator::*;
+pub use crate::builtin::errorobj::{ExceptionInfo, ExceptionRef};
 pub use crate::builtin::fiber::*;
 pub use crate::builtin::procobj::*;
 pub use crate::builtin::range::*;
@@ -62,9 +62,13 @@ fn exec_file(vm: &mut VMRef, file_name: impl Into<String>) {
     eprintln!("load file: {:?}", root_path);
     vm.root_path.push(root_path);
     let mut vm2 = vm.clone();
-    let res = thread::spawn(move || vm2.run(absolute_path, &program, None))
-        .join()
-        .unwrap();
+    let alloc = vm.globals.allocator;
+    let res = thread::spawn(move || {
+        Allocator::set_current(alloc);
+        vm2.run(absolute_path, &program, None)
+    })
+    .join()
+    .unwrap();
     match res {
         Ok(_) => {}
         Err(err) => {
@@ -2,6 +2,6 @@ mod lexer;
 pub mod node;
 pub mod parser;
 pub mod token;
-pub use lexer::Lexer;
+pub use lexer::{Lexer, PercentEnd, PercentLiteral};
 pub use node::*;
 pub use token::*;
@@ -15,6 +15,9 @@ pub struct Lexer {
     quote_state: Vec<QuoteState>,
     pub source_info: SourceInfoRef,
     state_save: Vec<(u32, u32)>, // (token_start_pos, pos)
+    /// (the end of the line, the end of heredoc bodies)
+    /// When the cursor passes the end of the line which has heredocs, it moves to the end of their bodies.
+    heredoc_skip: Option<(u32, u32)>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -22,9 +25,65 @@ enum QuoteState {
     DoubleQuote,
     RegEx,
     Brace,
+    Heredoc(Heredoc),
     //Expr,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+struct Heredoc {
+    terminator: String,
+    /// The terminator may be indented. (`<<-` or `<<~`)
+    indented_terminator: bool,
+    /// The width of the indentation removed from each line of the body. (`<<~`)
+    indent: usize,
+    /// `#{}` and escape sequences are processed. (not `<<'EOS'`)
+    interpolate: bool,
+    /// The end of the heredoc header, where the lexer resumes after reading the body.
+    resume: u32,
+    /// The end of the line which has the heredoc header.
+    line_end: u32,
+}
+
+/// A percent literal which is being read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PercentLiteral {
+    /// One of w, W, i, I, q, Q, r, s and x. `%()` is regarded as `%Q()`.
+    pub kind: char,
+    open: char,
+    close: char,
+    /// The nesting level of the delimiters.
+    depth: usize,
+}
+
+impl PercentLiteral {
+    /// `#{}` and escape sequences are processed.
+    pub fn interpolate(&self) -> bool {
+        match self.kind {
+            'W' | 'I' | 'Q' | 'r' | 'x' => true,
+            _ => false,
+        }
+    }
+
+    /// The body is a list of words separated by whitespaces.
+    pub fn is_array(&self) -> bool {
+        match self.kind {
+            'w' | 'W' | 'i' | 'I' => true,
+            _ => false,
+        }
+    }
+}
+
+/// How a part of the body of a percent literal ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PercentEnd {
+    /// `#{`
+    Interpolation,
+    /// Whitespaces between words.
+    Separator,
+    /// The closing delimiter.
+    Close,
+}
+
 #[derive(Debug, Clone)]
 pub struct LexerResult {
     pub tokens: Vec<Token>,
@@ -75,9 +134,11 @@ impl Lexer {
             "nil" => Reserved::Nil,
             "return" => Reserved::Return,
             "rescue" => Reserved::Rescue,
+            "retry" => Reserved::Retry,
             "self" => Reserved::Self_,
             "then" => Reserved::Then,
             "true" => Reserved::True,
+            "undef" => Reserved::Undef,
             "until" => Reserved::Until,
             "unless" => Reserved::Unless,
             "when" => Reserved::When,
@@ -95,6 +156,7 @@ impl Lexer {
             quote_state: vec![],
             source_info: SourceInfoRef::new(SourceInfo::new(std::path::PathBuf::default())),
             state_save: vec![],
+            heredoc_skip: None,
         }
     }
 
@@ -313,6 +375,10 @@ impl Lexer {
                     '}' => match self.quote_state.last() {
                         Some(QuoteState::DoubleQuote) => return self.lex_interpolate_string(),
                         Some(QuoteState::RegEx) => return self.lex_interpolate_regexp(),
+                        Some(QuoteState::Heredoc(heredoc)) => {
+                            let heredoc = heredoc.clone();
+                            return self.lex_interpolate_heredoc(heredoc);
+                        }
                         Some(QuoteState::Brace) => return Ok(self.new_punct(Punct::RBrace)),
                         _ => return Err(self.error_unexpected(pos)),
                     },
@@ -404,7 +470,11 @@ impl Lexer {
                         }
                     }
                     '@' => {
-                        return self.lex_identifier(None, VarKind::InstanceVar);
+                        if self.consume('@') {
+                            return self.lex_identifier(None, VarKind::ClassVar);
+                        } else {
+                            return self.lex_identifier(None, VarKind::InstanceVar);
+                        }
                     }
                     '$' => {
                         return self.lex_identifier(None, VarKind::GlobalVar);
@@ -439,6 +509,10 @@ impl Lexer {
                     Ok(ch) => {
                         if ch.is_alphanumeric() || ch == '_' || ch == '&' || ch == '\'' {
                             tok.push(ch);
+                        } else if ch == '!' && var_kind == VarKind::GlobalVar {
+                            // `$!` consists of only one character.
+                            tok.push(ch);
+                            return Ok(self.new_global_var(tok));
                         } else {
                             return Err(self.error_unexpected(self.pos));
                         }
@@ -467,6 +541,9 @@ impl Lexer {
             VarKind::InstanceVar => {
                 return Ok(self.new_instance_var(tok));
             }
+            VarKind::ClassVar => {
+                return Ok(self.new_class_var(tok));
+            }
             VarKind::GlobalVar => {
                 return Ok(self.new_global_var(tok));
             }
@@ -490,6 +567,10 @@ impl Lexer {
                         }
                         _ => {}
                     };
+                    // A reserved word with a suffix. (e.g. `defined?`)
+                    if let Some(reserved) = self.reserved.get(&tok) {
+                        return Ok(self.new_reserved(*reserved));
+                    }
                     let (has_suffix, trailing_space) = match self.peek() {
                         Ok(ch) if ch == ':' || ch == '=' || ch == '(' => (true, false),
                         Ok(ch) if ch.is_ascii_whitespace() => (false, true),
@@ -715,20 +796,283 @@ impl Lexer {
         }
     }
 
-    pub fn lex_percent_notation(&mut self) -> Result<Token, RubyError> {
-        if self.consume('w') {
-            let mut s = "".to_string();
-            if !self.consume('(') {
-                return Err(self.error_unexpected(self.pos));
+    /// Check whether a heredoc header follows `pos`, which is just after `<<`.
+    pub fn is_heredoc_start(&self, pos: u32) -> bool {
+        let code = &self.source_info.code;
+        let mut pos = pos as usize;
+        if let Some('~') | Some('-') = code.get(pos) {
+            pos += 1;
+        }
+        match code.get(pos) {
+            Some(ch) => *ch == '\'' || *ch == '"' || ch.is_ascii_alphabetic() || *ch == '_',
+            None => false,
+        }
+    }
+
+    /// Read a heredoc. The cursor must be just after `<<`.
+    /// Returns StringLit, or OpenString if the body has interpolations.
+    /// The cursor is moved back to the end of the header after reading the body.
+    pub fn lex_heredoc(&mut self) -> Result<Token, RubyError> {
+        let indented_terminator = self.consume('-');
+        let squiggly = !indented_terminator && self.consume('~');
+        let (terminator, interpolate) = match self.peek()? {
+            quote @ '\'' | quote @ '"' => {
+                self.get()?;
+                let mut terminator = "".to_string();
+                loop {
+                    match self.get()? {
+                        '\n' => {
+                            return Err(
+                                self.error_parse("unterminated here document identifier", self.pos)
+                            )
+                        }
+                        ch if ch == quote => break,
+                        ch => terminator.push(ch),
+                    }
+                }
+                (terminator, quote == '"')
             }
-            loop {
-                match self.get()? {
-                    ')' => return Ok(self.new_percent('w', s)),
-                    ch => s.push(ch),
+            _ => {
+                let mut terminator = "".to_string();
+                while let Ok(ch) = self.peek() {
+                    if ch.is_ascii_alphanumeric() || ch == '_' {
+                        terminator.push(ch);
+                        self.get()?;
+                    } else {
+                        break;
+                    }
                 }
+                (terminator, true)
             }
+        };
+        let resume = self.pos;
+        let code = &self.source_info.code;
+        let line_end = (resume as usize..self.len)
+            .find(|i| code[*i] == '\n')
+            .unwrap_or(self.len) as u32;
+        // The body starts after the bodies of preceding heredocs on the same line.
+        let body_start = match self.heredoc_skip {
+            Some((end, body_end)) if end == line_end => body_end,
+            _ => line_end + 1,
+        };
+        let mut heredoc = Heredoc {
+            terminator,
+            indented_terminator: indented_terminator || squiggly,
+            indent: 0,
+            interpolate,
+            resume,
+            line_end,
+        };
+        if squiggly {
+            heredoc.indent = self.heredoc_indent(&heredoc, body_start);
+        }
+        self.pos = body_start;
+        let (s, closed) = self.lex_heredoc_body(&heredoc)?;
+        let loc = Loc(self.token_start_pos, resume - 1);
+        if closed {
+            Ok(Annot::new(TokenKind::StringLit(s), loc))
         } else {
-            return Err(self.error_unexpected(self.pos));
+            self.quote_state.push(QuoteState::Heredoc(heredoc));
+            Ok(Token::new_open_dq(s, loc))
+        }
+    }
+
+    fn lex_interpolate_heredoc(&mut self, heredoc: Heredoc) -> Result<Token, RubyError> {
+        let (s, closed) = self.lex_heredoc_body(&heredoc)?;
+        if closed {
+            Ok(self.new_close_dq(s))
+        } else {
+            Ok(self.new_inter_dq(s))
+        }
+    }
+
+    /// Read the body of a heredoc until `#{` or the terminator line.
+    /// Returns the string and true if the terminator was reached.
+    fn lex_heredoc_body(&mut self, heredoc: &Heredoc) -> Result<(String, bool), RubyError> {
+        let mut s = "".to_string();
+        loop {
+            if self.pos as usize >= self.len || self.source_info.code[self.pos as usize - 1] == '\n'
+            {
+                if let Some(end) = self.heredoc_terminator_end(heredoc, self.pos) {
+                    self.heredoc_skip = Some((heredoc.line_end, end));
+                    self.pos = heredoc.resume;
+                    return Ok((s, true));
+                }
+                for _ in 0..heredoc.indent {
+                    if !self.consume(' ') && !self.consume('\t') {
+                        break;
+                    }
+                }
+            }
+            match self.get() {
+                Ok('\\') if heredoc.interpolate => s.push(self.read_escaped_char()?),
+                Ok('#') if heredoc.interpolate && self.consume('{') => return Ok((s, false)),
+                Ok(ch) => s.push(ch),
+                Err(_) => {
+                    let msg = format!(
+                        "can't find string \"{}\" anywhere before EOF",
+                        heredoc.terminator
+                    );
+                    return Err(self.error_parse(&msg, self.pos));
+                }
+            }
+        }
+    }
+
+    /// If the line which begins at `pos` is the terminator of the heredoc,
+    /// returns the position of the next line.
+    fn heredoc_terminator_end(&self, heredoc: &Heredoc, pos: u32) -> Option<u32> {
+        let code = &self.source_info.code;
+        let mut pos = pos as usize;
+        if heredoc.indented_terminator {
+            while pos < self.len && (code[pos] == ' ' || code[pos] == '\t') {
+                pos += 1;
+            }
+        }
+        for ch in heredoc.terminator.chars() {
+            if pos < self.len && code[pos] == ch {
+                pos += 1;
+            } else {
+                return None;
+            }
+        }
+        if pos < self.len && code[pos] == '\r' {
+            pos += 1;
+        }
+        if pos >= self.len {
+            Some(self.len as u32)
+        } else if code[pos] == '\n' {
+            Some(pos as u32 + 1)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the width of the least indented line of the body of a squiggly heredoc.
+    /// Lines which consist of whitespaces only are ignored.
+    fn heredoc_indent(&self, heredoc: &Heredoc, body_start: u32) -> usize {
+        let code = &self.source_info.code;
+        let mut pos = body_start;
+        let mut indent = None;
+        while (pos as usize) < self.len && self.heredoc_terminator_end(heredoc, pos).is_none() {
+            let mut width = 0;
+            let mut i = pos as usize;
+            while i < self.len && (code[i] == ' ' || code[i] == '\t') {
+                width += 1;
+                i += 1;
+            }
+            if i < self.len && code[i] != '\n' {
+                indent = Some(std::cmp::min(width, indent.unwrap_or(width)));
+            }
+            while i < self.len && code[i] != '\n' {
+                i += 1;
+            }
+            pos = i as u32 + 1;
+        }
+        indent.unwrap_or(0)
+    }
+
+    /// Check whether a percent literal follows `pos`, which is just after `%`.
+    pub fn is_percent_start(&self, pos: u32) -> bool {
+        let code = &self.source_info.code;
+        let pos = pos as usize;
+        let is_delimiter = |pos: usize| match code.get(pos) {
+            Some(ch) => ch.is_ascii_punctuation() && *ch != '=',
+            None => false,
+        };
+        match code.get(pos) {
+            Some('w') | Some('W') | Some('i') | Some('I') | Some('q') | Some('Q') | Some('r')
+            | Some('s') | Some('x') => is_delimiter(pos + 1),
+            _ => is_delimiter(pos),
+        }
+    }
+
+    /// Read the header of a percent literal. The cursor must be just after `%`.
+    pub fn lex_percent_notation(&mut self) -> Result<PercentLiteral, RubyError> {
+        let kind = match self.peek()? {
+            ch @ 'w'
+            | ch @ 'W'
+            | ch @ 'i'
+            | ch @ 'I'
+            | ch @ 'q'
+            | ch @ 'Q'
+            | ch @ 'r'
+            | ch @ 's'
+            | ch @ 'x' => {
+                self.get()?;
+                ch
+            }
+            _ => 'Q',
+        };
+        let open = self.get()?;
+        let close = match open {
+            '(' => ')',
+            '[' => ']',
+            '{' => '}',
+            '<' => '>',
+            ch if ch.is_ascii_punctuation() => ch,
+            _ => return Err(self.error_unexpected(self.pos - 1)),
+        };
+        Ok(PercentLiteral {
+            kind,
+            open,
+            close,
+            depth: 0,
+        })
+    }
+
+    /// Read the body of a percent literal until `#{`, a whitespace between words, or the closing delimiter.
+    /// When `#{` is read, the lexer is in the state of a brace, and the parser must read the closing `}`.
+    pub fn lex_percent_body(
+        &mut self,
+        lit: &mut PercentLiteral,
+    ) -> Result<(String, PercentEnd), RubyError> {
+        let mut s = "".to_string();
+        loop {
+            let ch = match self.get() {
+                Ok(ch) => ch,
+                Err(_) => {
+                    return Err(self.error_parse("unterminated percent literal", self.pos));
+                }
+            };
+            match ch {
+                ch if ch == lit.close && lit.depth == 0 => {
+                    if lit.kind == 'r' {
+                        self.check_postfix(&mut s);
+                    }
+                    return Ok((s, PercentEnd::Close));
+                }
+                ch if ch == lit.close => {
+                    lit.depth -= 1;
+                    s.push(ch);
+                }
+                ch if ch == lit.open => {
+                    lit.depth += 1;
+                    s.push(ch);
+                }
+                ch if lit.is_array() && ch.is_ascii_whitespace() => {
+                    while self.consume_whitespace() {}
+                    return Ok((s, PercentEnd::Separator));
+                }
+                '\\' if lit.kind == 'r' => {
+                    s.push('\\');
+                    s.push(self.get()?);
+                }
+                '\\' if lit.interpolate() => s.push(self.read_escaped_char()?),
+                '\\' => match self.get()? {
+                    ch if ch == '\\' || ch == lit.open || ch == lit.close => s.push(ch),
+                    ch if lit.is_array() && ch.is_ascii_whitespace() => s.push(ch),
+                    ch => {
+                        s.push('\\');
+                        s.push(ch);
+                    }
+                },
+                '#' if lit.interpolate() && self.consume('{') => {
+                    self.quote_state.push(QuoteState::Brace);
+                    return Ok((s, PercentEnd::Interpolation));
+                }
+                ch => s.push(ch),
+            }
         }
     }
 
@@ -843,6 +1187,11 @@ impl Lexer {
         let mut res = None;
         loop {
             if self.consume('\n') {
+                if let Some((line_end, body_end)) = self.heredoc_skip {
+                    if self.pos == line_end + 1 {
+                        self.pos = body_end;
+                    }
+                }
                 res = Some(self.new_line_term());
                 self.token_start_pos = self.pos;
             } else if self.consume_whitespace() {
@@ -880,6 +1229,10 @@ impl Lexer {
         Annot::new(TokenKind::InstanceVar(ident.into()), self.cur_loc())
     }
 
+    fn new_class_var(&self, ident: impl Into<String>) -> Token {
+        Annot::new(TokenKind::ClassVar(ident.into()), self.cur_loc())
+    }
+
     fn new_global_var(&self, ident: impl Into<String>) -> Token {
         Annot::new(TokenKind::GlobalVar(ident.into()), self.cur_loc())
     }
@@ -927,10 +1280,6 @@ impl Lexer {
         Token::new_open_reg(s, self.cur_loc())
     }
 
-    fn new_percent(&self, kind: char, content: String) -> Token {
-        Token::new_percent(kind, content, self.cur_loc())
-    }
-
     fn new_space(&self) -> Token {
         Annot::new(TokenKind::Space, self.cur_loc())
     }
@@ -991,6 +1340,9 @@ mod test {
         (InstanceVar($item:expr), $loc_0:expr, $loc_1:expr) => {
             Token::new_instance_var($item, Loc($loc_0, $loc_1))
         };
+        (ClassVar($item:expr), $loc_0:expr, $loc_1:expr) => {
+            Token::new_class_var($item, Loc($loc_0, $loc_1))
+        };
         (GlobalVar($item:expr), $loc_0:expr, $loc_1:expr) => {
             Token::new_global_var($item, Loc($loc_0, $loc_1))
         };
@@ -1068,6 +1420,13 @@ mod test {
         assert_tokens(program, ans);
     }
 
+    #[test]
+    fn class_var() {
+        let program = "@@amber";
+        let ans = vec![Token![ClassVar("@@amber"), 0, 6], Token![EOF, 7]];
+        assert_tokens(program, ans);
+    }
+
     #[test]
     fn global_var() {
         let program = "$amber";
@@ -23,6 +23,7 @@ pub enum NodeKind {
     LocalVar(IdentId),
     Ident(IdentId),
     InstanceVar(IdentId),
+    ClassVar(IdentId),
     GlobalVar(IdentId),
     Const {
         toplevel: bool,
@@ -63,7 +64,7 @@ pub enum NodeKind {
     },
     Begin {
         body: Box<Node>,
-        rescue: Vec<(Node, Node)>, // (ex_class_list, ex_param)
+        rescue: Vec<RescueEntry>,
         else_: Box<Node>,
         ensure: Box<Node>,
     },
@@ -75,16 +76,27 @@ pub enum NodeKind {
     Break(Box<Node>),
     Next(Box<Node>),
     Return(Box<Node>),
+    Retry,
     Yield(SendArgs),
+    /// `super`. `zsuper` is true when no argument list is given. (e.g. `super`, `super { }`)
+    Super {
+        send_args: SendArgs,
+        zsuper: bool,
+    },
+    Defined(Box<Node>),
 
     Param(IdentId),
     PostParam(IdentId),
     OptionalParam(IdentId, Box<Node>),
     RestParam(IdentId),
     KeywordParam(IdentId, Box<Option<Node>>),
+    KWRestParam(IdentId),
     BlockParam(IdentId),
 
     MethodDef(IdentId, NodeVec, Box<Node>, LvarCollector), // id, params, body
+    AliasMethod(IdentId, IdentId),                         // new, old
+    AliasGlobalVar(IdentId, IdentId),                      // new, old
+    Undef(Vec<IdentId>),
     SingletonMethodDef(Box<Node>, IdentId, NodeVec, Box<Node>, LvarCollector), // singleton_class, id, params, body
     ClassDef {
         id: IdentId,
@@ -93,6 +105,11 @@ pub enum NodeKind {
         lvar: LvarCollector,
         is_module: bool,
     },
+    SingletonClassDef {
+        singleton: Box<Node>,
+        body: Box<Node>,
+        lvar: LvarCollector,
+    },
     Send {
         receiver: Box<Node>,
         method: IdentId,
@@ -105,6 +122,7 @@ pub enum NodeKind {
 pub struct SendArgs {
     pub args: NodeVec,
     pub kw_args: Vec<(IdentId, Node)>,
+    pub kw_splat: NodeVec,
     pub block: Option<Box<Node>>,
 }
 
@@ -113,6 +131,7 @@ impl SendArgs {
         SendArgs {
             args: vec![],
             kw_args: vec![],
+            kw_splat: vec![],
             block: None,
         }
     }
@@ -133,6 +152,23 @@ impl CaseBranch {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct RescueEntry {
+    pub exception_list: Vec<Node>,
+    pub assign: Option<Box<Node>>,
+    pub body: Box<Node>,
+}
+
+impl RescueEntry {
+    pub fn new(exception_list: Vec<Node>, assign: Option<Node>, body: Node) -> Self {
+        RescueEntry {
+            exception_list,
+            assign: assign.map(|node| Box::new(node)),
+            body: Box::new(body),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BinOp {
     Add,
@@ -159,6 +195,36 @@ pub enum BinOp {
     Match,
 }
 
+impl BinOp {
+    /// The name of the method which the operator calls, or None for logical operators.
+    pub fn method_name(&self) -> Option<&'static str> {
+        let name = match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::Exp => "**",
+            BinOp::Shr => ">>",
+            BinOp::Shl => "<<",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::TEq => "===",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Cmp => "<=>",
+            BinOp::Match => "=~",
+            BinOp::LAnd | BinOp::LOr => return None,
+        };
+        Some(name)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum UnOp {
     BitNot,
@@ -287,6 +353,10 @@ impl Node {
         Node::new(NodeKind::KeywordParam(id, Box::new(default)), loc)
     }
 
+    pub fn new_kwrest_param(id: IdentId, loc: Loc) -> Self {
+        Node::new(NodeKind::KWRestParam(id), loc)
+    }
+
     pub fn new_block_param(id: IdentId, loc: Loc) -> Self {
         Node::new(NodeKind::BlockParam(id), loc)
     }
@@ -303,6 +373,10 @@ impl Node {
         Node::new(NodeKind::InstanceVar(id), loc)
     }
 
+    pub fn new_class_var(id: IdentId, loc: Loc) -> Self {
+        Node::new(NodeKind::ClassVar(id), loc)
+    }
+
     pub fn new_global_var(id: IdentId, loc: Loc) -> Self {
         Node::new(NodeKind::GlobalVar(id), loc)
     }
@@ -364,6 +438,22 @@ impl Node {
         )
     }
 
+    pub fn new_singleton_class_decl(
+        singleton: Node,
+        body: Node,
+        lvar: LvarCollector,
+        loc: Loc,
+    ) -> Self {
+        Node::new(
+            NodeKind::SingletonClassDef {
+                singleton: Box::new(singleton),
+                body: Box::new(body),
+                lvar,
+            },
+            loc,
+        )
+    }
+
     pub fn new_send(
         receiver: Node,
         method: IdentId,
@@ -391,6 +481,7 @@ impl Node {
         let send_args = SendArgs {
             args: vec![],
             kw_args: vec![],
+            kw_splat: vec![],
             block: None,
         };
         Node::new(
@@ -441,7 +532,7 @@ impl Node {
 
     pub fn new_begin(
         body: Node,
-        rescue: Vec<(Node, Node)>,
+        rescue: Vec<RescueEntry>,
         else_: Node,
         ensure: Node,
         loc: Loc,
@@ -465,15 +556,40 @@ impl Node {
         Node::new(NodeKind::Next(Box::new(val)), loc)
     }
 
+    pub fn new_alias_method(new: IdentId, old: IdentId, loc: Loc) -> Self {
+        Node::new(NodeKind::AliasMethod(new, old), loc)
+    }
+
+    pub fn new_alias_global_var(new: IdentId, old: IdentId, loc: Loc) -> Self {
+        Node::new(NodeKind::AliasGlobalVar(new, old), loc)
+    }
+
+    pub fn new_undef(ids: Vec<IdentId>, loc: Loc) -> Self {
+        Node::new(NodeKind::Undef(ids), loc)
+    }
+
+    pub fn new_retry(loc: Loc) -> Self {
+        Node::new(NodeKind::Retry, loc)
+    }
+
     pub fn new_return(val: Node, loc: Loc) -> Self {
         Node::new(NodeKind::Return(Box::new(val)), loc)
     }
 
+    pub fn new_defined(content: Node, loc: Loc) -> Self {
+        Node::new(NodeKind::Defined(Box::new(content)), loc)
+    }
+
     pub fn new_yield(mut args: SendArgs, loc: Loc) -> Self {
         args.args.reverse();
         Node::new(NodeKind::Yield(args), loc)
     }
 
+    pub fn new_super(mut send_args: SendArgs, zsuper: bool, loc: Loc) -> Self {
+        send_args.args.reverse();
+        Node::new(NodeKind::Super { send_args, zsuper }, loc)
+    }
+
     pub fn new_proc(params: NodeVec, body: Node, lvar: LvarCollector, loc: Loc) -> Self {
         let loc = loc.merge(body.loc());
         Node::new(
@@ -486,6 +602,13 @@ impl Node {
         )
     }
 
+    pub fn is_nop(&self) -> bool {
+        match &self.kind {
+            NodeKind::CompStmt(nodes) => nodes.is_empty(),
+            _ => false,
+        }
+    }
+
     pub fn is_operation(&self) -> bool {
         match self.kind {
             NodeKind::Ident(_) => true,
@@ -10,6 +10,8 @@ pub struct Parser {
     prev_loc: Loc,
     context_stack: Vec<Context>,
     pub ident_table: IdentifierTable,
+    /// Bodies of `BEGIN` blocks, which are hoisted before the main program.
+    begin_blocks: Vec<Node>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -180,6 +182,7 @@ enum ContextKind {
 struct ArgList {
     args: Vec<Node>,
     kw_args: Vec<(IdentId, Node)>,
+    kw_splat: Vec<Node>,
     block: Option<Box<Node>>,
 }
 
@@ -191,6 +194,7 @@ impl Parser {
             prev_loc: Loc(0, 0),
             context_stack: vec![],
             ident_table: IdentifierTable::new(),
+            begin_blocks: vec![],
         }
     }
 
@@ -344,16 +348,6 @@ impl Parser {
         }
     }
 
-    fn consume_const(&mut self) -> Result<bool, RubyError> {
-        match self.peek()?.kind {
-            TokenKind::Const(_, _, _) => {
-                self.get()?;
-                Ok(true)
-            }
-            _ => Ok(false),
-        }
-    }
-
     fn consume_reserved_no_skip_line_term(&mut self, expect: Reserved) -> Result<bool, RubyError> {
         if TokenKind::Reserved(expect) == self.peek_no_term()?.kind {
             self.get()?;
@@ -426,6 +420,7 @@ impl Parser {
             TokenKind::Ident(ident, _, _) => ident,
             TokenKind::Const(ident, _, _) => ident,
             TokenKind::InstanceVar(ident) => ident,
+            TokenKind::ClassVar(ident) => ident,
             TokenKind::StringLit(ident) => ident,
             TokenKind::Reserved(reserved) => {
                 self.lexer.get_string_from_reserved(reserved).to_string()
@@ -498,8 +493,14 @@ impl Parser {
     ) -> Result<(Node, LvarCollector), RubyError> {
         self.lexer.init(path, program);
         self.context_stack.push(Context::new_class(lvar));
-        let node = self.parse_comp_stmt()?;
+        let mut node = self.parse_comp_stmt()?;
         let lvar = self.context_stack.pop().unwrap().lvar;
+        if !self.begin_blocks.is_empty() {
+            let mut nodes = std::mem::replace(&mut self.begin_blocks, vec![]);
+            let loc = node.loc();
+            nodes.push(node);
+            node = Node::new_comp_stmt(nodes, loc);
+        }
         Ok((node, lvar))
     }
 
@@ -571,6 +572,12 @@ impl Parser {
                 let cond = Node::new_unop(UnOp::Not, self.parse_expr()?, loc);
                 let loc = loc.merge(self.prev_loc());
                 node = Node::new_while(cond, node, loc);
+            } else if self.consume_reserved_no_skip_line_term(Reserved::Rescue)? {
+                // STMT : STMT rescue STMT
+                let loc = self.prev_loc();
+                let rescue = self.parse_stmt()?;
+                let rescue = vec![RescueEntry::new(vec![], None, rescue)];
+                node = Node::new_begin(node, rescue, Node::new_nop(loc), Node::new_nop(loc), loc);
             } else {
                 break;
             }
@@ -715,22 +722,24 @@ impl Parser {
     fn parse_arglist(&mut self) -> Result<SendArgs, RubyError> {
         let first_arg = self.parse_arg()?;
         if self.is_line_term()? {
-            return Ok(SendArgs{args:vec![first_arg], kw_args:vec![], block:None});
+            return Ok(SendArgs{args:vec![first_arg], kw_args:vec![], kw_splat:vec![], block:None});
         }
 
         if first_arg.is_operation() && self.is_command()? {
             let args =
                 vec![self.parse_command(first_arg.as_method_name().unwrap(), first_arg.loc())?];
-            return Ok(SendArgs{args, kw_args:vec![], block:None});
+            return Ok(SendArgs{args, kw_args:vec![], kw_splat:vec![], block:None});
         }
 
         let mut args = vec![first_arg];
         let mut kw_args = vec![];
+        let mut kw_splat = vec![];
         let mut block = None;
         if self.consume_punct_no_term(Punct::Comma)? {
             let res = self.parse_argument_list(None)?;
             let mut new_args = res.args;
             kw_args = res.kw_args;
+            kw_splat = res.kw_splat;
             block = res.block;
             args.append(&mut new_args);
         }
@@ -741,7 +750,7 @@ impl Parser {
             }
             None => {}
         };
-        Ok(SendArgs{args, kw_args, block})
+        Ok(SendArgs{args, kw_args, kw_splat, block})
     }
 
     fn is_command(&mut self) -> Result<bool, RubyError> {
@@ -749,6 +758,7 @@ impl Parser {
         match tok.kind {
             TokenKind::Ident(_, _, _)
             | TokenKind::InstanceVar(_)
+            | TokenKind::ClassVar(_)
             | TokenKind::GlobalVar(_)
             | TokenKind::Const(_, _, _)
             | TokenKind::NumLit(_)
@@ -764,10 +774,12 @@ impl Parser {
                 | Punct::Plus
                 | Punct::Minus
                 | Punct::Arrow => Ok(true),
+                Punct::Shl => Ok(self.lexer.is_heredoc_start(tok.loc.1 + 1)),
+                Punct::Rem => Ok(self.lexer.is_percent_start(tok.loc.1 + 1)),
                 _ => Ok(false),
             },
             TokenKind::Reserved(r) => match r {
-                Reserved::False | Reserved::Nil | Reserved::True => Ok(true),
+                Reserved::False | Reserved::Nil | Reserved::True | Reserved::Defined => Ok(true),
                 _ => Ok(false),
             },
             _ => Ok(false),
@@ -785,7 +797,11 @@ impl Parser {
             return Ok(lhs);
         }
         if self.consume_punct_no_term(Punct::Assign)? {
-            let mrhs = self.parse_arg_list(None)?;
+            let mut mrhs = self.parse_arg_list(None)?;
+            if mrhs.len() == 1 {
+                let rhs = mrhs.pop().unwrap();
+                mrhs.push(self.parse_arg_rhs(rhs)?);
+            }
             self.check_lhs(&lhs)?;
             Ok(Node::new_mul_assign(vec![lhs], mrhs))
         } else if let TokenKind::Punct(Punct::AssignOp(op)) = self.peek_no_term()?.kind {
@@ -793,6 +809,7 @@ impl Parser {
                 BinOp::LOr => {
                     self.get()?;
                     let rhs = self.parse_arg()?;
+                    let rhs = self.parse_arg_rhs(rhs)?;
                     self.check_lhs(&lhs)?;
                     if let NodeKind::Ident(id) = lhs.kind {
                         lhs = Node::new_lvar(id, lhs.loc());
@@ -807,6 +824,7 @@ impl Parser {
                 _ => {
                     self.get()?;
                     let rhs = self.parse_arg()?;
+                    let rhs = self.parse_arg_rhs(rhs)?;
                     self.check_lhs(&lhs)?;
                     Ok(Node::new_mul_assign(
                         vec![lhs.clone()],
@@ -819,6 +837,20 @@ impl Parser {
         }
     }
 
+    fn parse_arg_rhs(&mut self, rhs: Node) -> Result<Node, RubyError> {
+        // ARG-RHS : ARG [rescue ARG]
+        // The rescue modifier of an assignment covers only the right-hand side.
+        if self.consume_reserved_no_skip_line_term(Reserved::Rescue)? {
+            let loc = self.prev_loc();
+            let rescue = self.parse_arg()?;
+            let rescue = vec![RescueEntry::new(vec![], None, rescue)];
+            let rhs_loc = rhs.loc();
+            Ok(Node::new_begin(rhs, rescue, Node::new_nop(loc), Node::new_nop(loc), rhs_loc))
+        } else {
+            Ok(rhs)
+        }
+    }
+
     fn check_lhs(&mut self, lhs: &Node) -> Result<(), RubyError> {
         if let NodeKind::Ident(id) = lhs.kind {
             self.add_local_var_if_new(id);
@@ -1069,7 +1101,7 @@ impl Parser {
         let loc = node.loc();
         if self.consume_punct_no_term(Punct::LParen)? {
             // PRIMARY-METHOD : FNAME ( ARGS ) BLOCK?
-            let ArgList{args, kw_args, mut block} = self.parse_argument_list(Punct::RParen)?;
+            let ArgList{args, kw_args, kw_splat, mut block} = self.parse_argument_list(Punct::RParen)?;
             match self.parse_block()? {
                 Some(actual_block) => {
                     if block.is_some() {return Err(self.error_unexpected(actual_block.loc(), "Both block arg and actual block given."))}
@@ -1077,7 +1109,7 @@ impl Parser {
                 }
                 None => {}
             };
-            let send_args = SendArgs {args, kw_args, block};
+            let send_args = SendArgs {args, kw_args, kw_splat, block};
 
             Ok(Node::new_send(
                 Node::new_self(loc),
@@ -1088,7 +1120,7 @@ impl Parser {
             ))
         } else if let Some(block) = self.parse_block()? {
             // PRIMARY-METHOD : FNAME BLOCK
-            let send_args = SendArgs {args:vec![], kw_args:vec![], block: Some(block)};
+            let send_args = SendArgs {args:vec![], kw_args:vec![], kw_splat:vec![], block: Some(block)};
             Ok(Node::new_send(
                 Node::new_self(loc),
                 node.as_method_name().unwrap(),
@@ -1101,6 +1133,40 @@ impl Parser {
         }
     }
 
+    fn parse_super(&mut self, has_suffix: bool, trailing_space: bool, loc: Loc) -> Result<Node, RubyError> {
+        // super
+        // super BLOCK
+        // super ( ARGS ) BLOCK?
+        // super ARGS
+        if has_suffix && self.consume_punct_no_term(Punct::LParen)? {
+            let ArgList{args, kw_args, kw_splat, mut block} = self.parse_argument_list(Punct::RParen)?;
+            match self.parse_block()? {
+                Some(actual_block) => {
+                    if block.is_some() {return Err(self.error_unexpected(actual_block.loc(), "Both block arg and actual block given."))}
+                    block = Some(actual_block);
+                }
+                None => {}
+            };
+            let loc = loc.merge(self.prev_loc());
+            let send_args = SendArgs {args, kw_args, kw_splat, block};
+            return Ok(Node::new_super(send_args, false, loc));
+        }
+        match self.peek_no_term()?.kind {
+            TokenKind::Punct(Punct::LBrace) | TokenKind::Reserved(Reserved::Do) => {}
+            _ => {
+                if trailing_space && self.is_command_()? {
+                    let send_args = self.parse_arglist()?;
+                    let loc = loc.merge(self.prev_loc());
+                    return Ok(Node::new_super(send_args, false, loc));
+                }
+            }
+        }
+        let block = self.parse_block()?;
+        let loc = loc.merge(self.prev_loc());
+        let send_args = SendArgs {args:vec![], kw_args:vec![], kw_splat:vec![], block};
+        Ok(Node::new_super(send_args, true, loc))
+    }
+
     fn parse_function(&mut self) -> Result<Node, RubyError> {
         if self.consume_reserved(Reserved::Yield)?  {
             let loc = self.prev_loc();
@@ -1164,12 +1230,14 @@ impl Parser {
                 };
                 let mut args = vec![];
                 let mut kw_args = vec![];
+                let mut kw_splat = vec![];
                 let mut block = None;
                 let mut completed = false;
                 if self.consume_punct_no_term(Punct::LParen)? {
                     let res = self.parse_argument_list(Punct::RParen)?;
                     args = res.args;
                     kw_args = res.kw_args;
+                    kw_splat = res.kw_splat;
                     block = res.block;
                     completed = true;
                 }
@@ -1189,7 +1257,7 @@ impl Parser {
                     }
                     _ => node,
                 };
-                let send_args = SendArgs {args, kw_args, block};
+                let send_args = SendArgs {args, kw_args, kw_splat, block};
                 Node::new_send(
                     node,
                     id,
@@ -1215,7 +1283,7 @@ impl Parser {
     }
 
     /// Parse argument list.
-    /// arg, *splat_arg, kw: kw_arg, &block <punct>
+    /// arg, *splat_arg, kw: kw_arg, **double_splat_arg, &block <punct>
     /// punct: punctuator for terminating arg list. Set None for unparenthesized argument list.
     fn parse_argument_list(
         &mut self,
@@ -1227,16 +1295,20 @@ impl Parser {
         };
         let mut args = vec![];
         let mut kw_args = vec![];
+        let mut kw_splat = vec![];
         let mut block = None;
         loop {
             if flag && self.consume_punct(punct)? {
-                return Ok(ArgList {args, kw_args, block});
+                return Ok(ArgList {args, kw_args, kw_splat, block});
             }
             if self.consume_punct(Punct::Mul)? {
                 // splat argument
                 let loc = self.prev_loc();
                 let array = self.parse_arg()?;
                 args.push(Node::new_splat(array, loc));
+            } else if self.consume_punct(Punct::DMul)? {
+                // double splat argument
+                kw_splat.push(self.parse_arg()?);
             } else if self.consume_punct(Punct::BitAnd)? {
                 // block argument
                 let arg = self.parse_arg()?;
@@ -1266,7 +1338,7 @@ impl Parser {
         if flag {
             self.expect_punct(punct)?
         };
-        Ok(ArgList {args, kw_args, block})
+        Ok(ArgList {args, kw_args, kw_splat, block})
     }
 
     fn parse_block(&mut self) -> Result<Option<Box<Node>>, RubyError> {
@@ -1296,11 +1368,12 @@ impl Parser {
             vec![]
         };
 
-        let body = self.parse_comp_stmt()?;
-        if do_flag {
-            self.expect_reserved(Reserved::End)?;
+        let body = if do_flag {
+            self.parse_begin()?
         } else {
+            let body = self.parse_comp_stmt()?;
             self.expect_punct(Punct::RBrace)?;
+            body
         };
         let lvar = self.context_stack.pop().unwrap().lvar;
         let loc = loc.merge(self.prev_loc());
@@ -1313,6 +1386,9 @@ impl Parser {
         let loc = tok.loc();
         match &tok.kind {
             TokenKind::Ident(name, has_suffix, trailing_space) => {
+                if name == "super" {
+                    return Ok(self.parse_super(*has_suffix, *trailing_space, loc)?);
+                }
                 let id = self.get_ident_id(name);
                 if *has_suffix {
                     if self.peek_no_term()?.kind == TokenKind::Punct(Punct::LParen) {
@@ -1346,6 +1422,10 @@ impl Parser {
                 let id = self.get_ident_id(name);
                 return Ok(Node::new_instance_var(id, loc));
             }
+            TokenKind::ClassVar(name) => {
+                let id = self.get_ident_id(name);
+                return Ok(Node::new_class_var(id, loc));
+            }
             TokenKind::GlobalVar(name) => {
                 let id = self.get_ident_id(name);
                 return Ok(Node::new_global_var(id, loc));
@@ -1452,6 +1532,10 @@ impl Parser {
                     let node = self.parse_percent_notation()?;
                     Ok(node)
                 }
+                Punct::Shl => {
+                    let node = self.parse_heredoc()?;
+                    Ok(node)
+                }
                 _ => {
                     return Err(
                         self.error_unexpected(loc, format!("Unexpected token: {:?}", tok.kind))
@@ -1528,6 +1612,9 @@ impl Parser {
             }
             TokenKind::Reserved(Reserved::Def) => Ok(self.parse_def()?),
             TokenKind::Reserved(Reserved::Class) => {
+                if self.consume_punct_no_term(Punct::Shl)? {
+                    return Ok(self.parse_singleton_class(loc)?);
+                }
                 if self.context_stack.last().unwrap().kind == ContextKind::Method {
                     return Err(
                         self.error_unexpected(loc, "SyntaxError: class definition in method body.")
@@ -1618,6 +1705,70 @@ impl Parser {
                     Ok(Node::new_next(val, loc))
                 }
             }
+            TokenKind::Reserved(Reserved::Alias) => {
+                // alias NEW OLD
+                // alias $NEW $OLD
+                if let TokenKind::GlobalVar(new) = self.peek_no_term()?.kind {
+                    self.get()?;
+                    let old = match self.get()?.kind {
+                        TokenKind::GlobalVar(old) => old,
+                        _ => {
+                            let loc = self.prev_loc();
+                            return Err(self.error_unexpected(loc, "Expected global variable."));
+                        }
+                    };
+                    let new = self.get_ident_id(new);
+                    let old = self.get_ident_id(old);
+                    return Ok(Node::new_alias_global_var(new, old, loc));
+                }
+                let new = self.parse_method_name()?;
+                let old = self.parse_method_name()?;
+                Ok(Node::new_alias_method(new, old, loc))
+            }
+            TokenKind::Reserved(Reserved::Undef) => {
+                // undef NAME [, NAME]*
+                let mut ids = vec![self.parse_method_name()?];
+                while self.consume_punct_no_term(Punct::Comma)? {
+                    ids.push(self.parse_method_name()?);
+                }
+                Ok(Node::new_undef(ids, loc))
+            }
+            TokenKind::Reserved(Reserved::BEGIN) => {
+                // BEGIN { COMPSTMT }
+                if self.context_stack.len() != 1 {
+                    return Err(self.error_unexpected(loc, "BEGIN is permitted only at toplevel."));
+                }
+                self.expect_punct(Punct::LBrace)?;
+                let body = self.parse_comp_stmt()?;
+                self.expect_punct(Punct::RBrace)?;
+                self.begin_blocks.push(body);
+                Ok(Node::new_nop(loc))
+            }
+            TokenKind::Reserved(Reserved::END) => {
+                // END { COMPSTMT }
+                // Registered as a block of `at_exit`.
+                if self.peek_no_term()?.kind != TokenKind::Punct(Punct::LBrace) {
+                    let loc = self.loc();
+                    return Err(self.error_unexpected(loc, "Expected `{'."));
+                }
+                let block = self.parse_block()?;
+                let send_args = SendArgs {args: vec![], kw_args: vec![], kw_splat: vec![], block};
+                let method = self.get_ident_id("at_exit");
+                Ok(Node::new_send(Node::new_self(loc), method, send_args, true, loc))
+            }
+            TokenKind::Reserved(Reserved::Defined) => {
+                // defined?(EXPR)
+                // defined? ARG
+                let content = if self.consume_punct_no_term(Punct::LParen)? {
+                    let node = self.parse_expr()?;
+                    self.expect_punct(Punct::RParen)?;
+                    node
+                } else {
+                    self.parse_arg()?
+                };
+                Ok(Node::new_defined(content, loc))
+            }
+            TokenKind::Reserved(Reserved::Retry) => Ok(Node::new_retry(loc)),
             TokenKind::Reserved(Reserved::True) => Ok(Node::new_bool(true, loc)),
             TokenKind::Reserved(Reserved::False) => Ok(Node::new_bool(false, loc)),
             TokenKind::Reserved(Reserved::Nil) => Ok(Node::new_nil(loc)),
@@ -1635,6 +1786,7 @@ impl Parser {
         match tok.kind {
             TokenKind::Ident(_, _, _)
             | TokenKind::InstanceVar(_)
+            | TokenKind::ClassVar(_)
             | TokenKind::Const(_, _, _)
             | TokenKind::NumLit(_)
             | TokenKind::FloatLit(_)
@@ -1646,10 +1798,17 @@ impl Parser {
                 | Punct::Colon
                 | Punct::Scope
                 | Punct::Arrow => Ok(true),
+                Punct::Shl => Ok(self.lexer.is_heredoc_start(tok.loc.1 + 1)),
+                Punct::Rem => Ok(self.lexer.is_percent_start(tok.loc.1 + 1)),
                 _ => Ok(false),
             },
             TokenKind::Reserved(r) => match r {
-                Reserved::False | Reserved::Nil | Reserved::True | Reserved::Self_=> Ok(true),
+                Reserved::False
+                | Reserved::Nil
+                | Reserved::True
+                | Reserved::Self_
+                | Reserved::Def
+                | Reserved::Defined => Ok(true),
                 _ => Ok(false),
             },
             _ => Ok(false),
@@ -1735,18 +1894,117 @@ impl Parser {
         }
     }
 
+    fn parse_heredoc(&mut self) -> Result<Node, RubyError> {
+        if !self.lexer.is_heredoc_start(self.prev_loc().1 + 1) {
+            let loc = self.prev_loc();
+            return Err(self.error_unexpected(loc, "Unexpected token: Punct(Shl)"));
+        }
+        let tok = self.lexer.lex_heredoc()?;
+        match tok.kind {
+            TokenKind::StringLit(s) => Ok(Node::new_string(s, tok.loc)),
+            TokenKind::OpenString(s) => self.parse_interporated_string_literal(&s),
+            _ => panic!(),
+        }
+    }
+
     fn parse_percent_notation(&mut self) -> Result<Node, RubyError> {
-        let tok = self.lexer.lex_percent_notation()?;
-        let loc = tok.loc;
-        if let TokenKind::PercentNotation(_kind, content) = tok.kind {
-            let ary = content
-                .split(' ')
-                .map(|x| Node::new_string(x.to_string(), loc))
-                .rev()
-                .collect();
-            Ok(Node::new_array(ary, tok.loc))
-        } else {
-            panic!();
+        let loc = self.prev_loc();
+        let mut lit = self.lexer.lex_percent_notation()?;
+        // Each element is a list of String and interpolated CompStmt nodes.
+        let mut elements = vec![];
+        let mut parts = vec![];
+        loop {
+            let (s, end) = self.lexer.lex_percent_body(&mut lit)?;
+            if !s.is_empty() {
+                parts.push(Node::new_string(s, loc));
+            }
+            match end {
+                PercentEnd::Interpolation => {
+                    parts.push(self.parse_comp_stmt()?);
+                    self.expect_punct(Punct::RBrace)?;
+                }
+                PercentEnd::Separator => {
+                    if !parts.is_empty() {
+                        elements.push(std::mem::replace(&mut parts, vec![]));
+                    }
+                }
+                PercentEnd::Close => {
+                    if !parts.is_empty() || !lit.is_array() {
+                        elements.push(parts);
+                    }
+                    break;
+                }
+            }
+        }
+        let loc = loc.merge(self.prev_loc());
+        match lit.kind {
+            'w' | 'W' => {
+                let mut ary: Vec<Node> = elements
+                    .into_iter()
+                    .map(|parts| Parser::percent_string(parts, loc))
+                    .collect();
+                ary.reverse();
+                Ok(Node::new_array(ary, loc))
+            }
+            'i' | 'I' => {
+                let mut ary = vec![];
+                for parts in elements {
+                    let symbol = match Parser::percent_string(parts, loc) {
+                        Annot {
+                            kind: NodeKind::String(s),
+                            ..
+                        } => Node::new_symbol(self.get_ident_id(s), loc),
+                        node => {
+                            let to_sym = self.get_ident_id("to_sym");
+                            Node::new_send_noarg(node, to_sym, true, loc)
+                        }
+                    };
+                    ary.push(symbol);
+                }
+                ary.reverse();
+                Ok(Node::new_array(ary, loc))
+            }
+            's' => {
+                let s = match elements.pop().unwrap().pop() {
+                    Some(Annot {
+                        kind: NodeKind::String(s),
+                        ..
+                    }) => s,
+                    _ => "".to_string(),
+                };
+                Ok(Node::new_symbol(self.get_ident_id(s), loc))
+            }
+            'r' => Ok(Node::new_regexp(elements.pop().unwrap(), loc)),
+            'x' => {
+                let command = Parser::percent_string(elements.pop().unwrap(), loc);
+                let send_args = SendArgs {
+                    args: vec![command],
+                    kw_args: vec![],
+                    kw_splat: vec![],
+                    block: None,
+                };
+                let method = self.get_ident_id("`");
+                Ok(Node::new_send(
+                    Node::new_self(loc),
+                    method,
+                    send_args,
+                    true,
+                    loc,
+                ))
+            }
+            _ => Ok(Parser::percent_string(elements.pop().unwrap(), loc)),
+        }
+    }
+
+    /// Make a String node, or an InterporatedString node if `parts` has interpolations.
+    fn percent_string(mut parts: Vec<Node>, loc: Loc) -> Node {
+        match parts.len() {
+            0 => Node::new_string("".to_string(), loc),
+            1 => match parts[0].kind {
+                NodeKind::String(_) => parts.pop().unwrap(),
+                _ => Node::new_interporated_string(parts, loc),
+            },
+            _ => Node::new_interporated_string(parts, loc),
         }
     }
 
@@ -1874,21 +2132,7 @@ impl Parser {
                     self.get_ident_id(name)
                 }
             }
-            TokenKind::Punct(Punct::Plus) => self.get_ident_id("+"),
-            TokenKind::Punct(Punct::Minus) => self.get_ident_id("-"),
-            TokenKind::Punct(Punct::Mul) => self.get_ident_id("*"),
-            TokenKind::Punct(Punct::LBracket) => {
-                if self.consume_punct_no_term(Punct::RBracket)? {
-                    if self.consume_punct_no_term(Punct::Assign)? {
-                        self.get_ident_id("[]=")
-                    } else {
-                        self.get_ident_id("[]")
-                    }
-                } else {
-                    let loc = self.loc();
-                    return Err(self.error_unexpected(loc, "Expected `]'"));
-                }
-            }
+            TokenKind::Punct(punct) => self.parse_op_definable(&punct)?,
             _ => {
                 let loc = self.loc();
                 return Err(self.error_unexpected(loc, "Expected identifier or operator."));
@@ -1928,6 +2172,19 @@ impl Parser {
                 args.push(Node::new_block_param(id, loc));
                 self.new_block_param(id, loc)?;
                 break;
+            } else if self.consume_punct(Punct::DMul)? {
+                // Keyword rest param
+                let id = self.expect_ident()?;
+                loc = loc.merge(self.prev_loc());
+                if state >= Kind::KWRest {
+                    return Err(self.error_unexpected(
+                        loc,
+                        "Keyword rest parameter is not allowed in ths position.",
+                    ));
+                }
+                state = Kind::KWRest;
+                args.push(Node::new_kwrest_param(id, loc));
+                self.new_param(id, loc)?;
             } else if self.consume_punct(Punct::Mul)? {
                 // Splat(Rest) param
                 let id = self.expect_ident()?;
@@ -2070,46 +2327,130 @@ impl Parser {
         ))
     }
 
+    fn parse_singleton_class(&mut self, loc: Loc) -> Result<Node, RubyError> {
+        //  class `<<' EXPR
+        //      COMPSTMT
+        //  end
+        let singleton = self.parse_expr()?;
+        let loc = loc.merge(self.prev_loc());
+        self.consume_term()?;
+        self.context_stack.push(Context::new_class(None));
+        let body = self.parse_begin()?;
+        let lvar = self.context_stack.pop().unwrap().lvar;
+        Ok(Node::new_singleton_class_decl(singleton, body, lvar, loc))
+    }
+
+    /// Parse an operator which can be defined as a method.
     fn parse_op_definable(&mut self, punct: &Punct) -> Result<IdentId, RubyError> {
-        match punct {
+        let name = match punct {
             Punct::LBracket => {
                 if self.consume_punct_no_term(Punct::RBracket)? {
                     if self.consume_punct_no_term(Punct::Assign)? {
-                        Ok(self.get_ident_id("[]="))
+                        "[]="
                     } else {
-                        Ok(self.get_ident_id("[]"))
+                        "[]"
                     }
                 } else {
                     let loc = self.loc();
-                    Err(self.error_unexpected(loc, "Invalid symbol literal."))
+                    return Err(self.error_unexpected(loc, "Expected `]'"));
                 }
             }
-            _ => Err(self.error_unexpected(self.prev_loc(), "Invalid symbol literal.")),
+            Punct::Plus => "+",
+            Punct::Minus => "-",
+            Punct::Mul => "*",
+            Punct::Div => "/",
+            Punct::Rem => "%",
+            Punct::DMul => "**",
+            Punct::Shl => "<<",
+            Punct::Shr => ">>",
+            Punct::BitOr => "|",
+            Punct::BitAnd => "&",
+            Punct::BitXor => "^",
+            Punct::BitNot => "~",
+            Punct::Not => "!",
+            Punct::Eq => "==",
+            Punct::Ne => "!=",
+            Punct::TEq => "===",
+            Punct::Gt => ">",
+            Punct::Ge => ">=",
+            Punct::Lt => "<",
+            Punct::Le => "<=",
+            Punct::Cmp => "<=>",
+            Punct::Match => "=~",
+            _ => return Err(self.error_unexpected(self.prev_loc(), "Invalid operator.")),
+        };
+        Ok(self.get_ident_id(name))
+    }
+
+    /// Parse a method name of `alias` and `undef`. A symbol literal is also accepted.
+    fn parse_method_name(&mut self) -> Result<IdentId, RubyError> {
+        self.consume_punct_no_term(Punct::Colon)?;
+        let tok = self.get()?;
+        match &tok.kind {
+            TokenKind::Punct(punct) => self.parse_op_definable(punct),
+            TokenKind::Ident(name, true, _) if self.consume_punct_no_term(Punct::Assign)? => {
+                Ok(self.get_ident_id(format!("{}=", name)))
+            }
+            _ if tok.can_be_symbol() => {
+                let name = self.token_as_symbol(&tok);
+                Ok(self.get_ident_id(name))
+            }
+            _ => Err(self.error_unexpected(tok.loc(), "Expected identifier or operator.")),
         }
     }
 
     fn parse_begin(&mut self) -> Result<Node, RubyError> {
+        //  begin
+        //      COMPSTMT
+        //      [rescue [ARGS] [`=>' LHS] THEN COMPSTMT]*
+        //      [else COMPSTMT]
+        //      [ensure COMPSTMT]
+        //  end
         let body = self.parse_comp_stmt()?;
-        if self.consume_reserved(Reserved::Rescue)? {
-            if self.consume_const()? {}
-            if self.consume_punct_no_term(Punct::FatArrow)? {
-                self.expect_ident()?;
+        let loc = body.loc();
+        let mut rescue = vec![];
+        while self.consume_reserved(Reserved::Rescue)? {
+            // rescue [ARGS] [`=>' LHS] THEN COMPSTMT
+            let mut exception_list = vec![];
+            let mut assign = None;
+            if !self.peek_no_term()?.is_term()
+                && self.peek_no_term()?.kind != TokenKind::Reserved(Reserved::Then)
+            {
+                if !self.consume_punct_no_term(Punct::FatArrow)? {
+                    loop {
+                        exception_list.push(self.parse_arg()?);
+                        if !self.consume_punct_no_term(Punct::Comma)? {
+                            break;
+                        }
+                    }
+                    if self.consume_punct_no_term(Punct::FatArrow)? {
+                        assign = Some(self.parse_rescue_lhs()?);
+                    }
+                } else {
+                    assign = Some(self.parse_rescue_lhs()?);
+                }
             }
-            self.parse_comp_stmt()?;
+            self.parse_then()?;
+            let rescue_body = self.parse_comp_stmt()?;
+            rescue.push(RescueEntry::new(exception_list, assign, rescue_body));
         }
+        let else_ = if self.consume_reserved(Reserved::Else)? {
+            self.parse_comp_stmt()?
+        } else {
+            Node::new_nop(loc)
+        };
         let ensure = if self.consume_reserved(Reserved::Ensure)? {
             self.parse_comp_stmt()?
         } else {
-            Node::new_nop(body.loc())
+            Node::new_nop(loc)
         };
         self.expect_reserved(Reserved::End)?;
-        let loc = body.loc();
-        Ok(Node::new_begin(
-            body,
-            vec![],
-            Node::new_nop(loc),
-            ensure,
-            loc,
-        ))
+        Ok(Node::new_begin(body, rescue, else_, ensure, loc))
+    }
+
+    fn parse_rescue_lhs(&mut self) -> Result<Node, RubyError> {
+        let lhs = self.parse_primary()?;
+        self.check_lhs(&lhs)?;
+        Ok(lhs)
     }
 }
@@ -38,6 +38,7 @@ pub enum TokenKind {
     EOF,
     Ident(String, bool, bool),
     InstanceVar(String),
+    ClassVar(String),
     GlobalVar(String),
     Const(String, bool, bool),
     NumLit(i64),
@@ -49,7 +50,6 @@ pub enum TokenKind {
     InterString(String),
     CloseString(String),
     OpenRegex(String),
-    PercentNotation(char, String),
     Space,
     LineTerm,
 }
@@ -79,9 +79,11 @@ pub enum Reserved {
     Nil,
     Return,
     Rescue,
+    Retry,
     Self_,
     Then,
     True,
+    Undef,
     Until,
     Unless,
     When,
@@ -156,6 +158,10 @@ impl Token {
         Annot::new(TokenKind::InstanceVar(ident.into()), loc)
     }
 
+    pub fn new_class_var(ident: impl Into<String>, loc: Loc) -> Self {
+        Annot::new(TokenKind::ClassVar(ident.into()), loc)
+    }
+
     pub fn new_const(
         ident: impl Into<String>,
         has_suffix: bool,
@@ -204,10 +210,6 @@ impl Token {
         Annot::new(TokenKind::OpenRegex(s.into()), loc)
     }
 
-    pub fn new_percent(kind: char, content: String, loc: Loc) -> Self {
-        Annot::new(TokenKind::PercentNotation(kind, content), loc)
-    }
-
     pub fn new_punct(punct: Punct, loc: Loc) -> Self {
         Annot::new(TokenKind::Punct(punct), loc)
     }
@@ -253,6 +255,7 @@ impl Token {
             TokenKind::Const(_, _, _)
             | TokenKind::Ident(_, _, _)
             | TokenKind::InstanceVar(_)
+            | TokenKind::ClassVar(_)
             | TokenKind::Reserved(_)
             | TokenKind::StringLit(_) => true,
             _ => false,
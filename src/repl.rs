@@ -30,7 +30,13 @@ pub fn repl_vm() {
             rl.readline(&format!("{}{:1}{} ", Red.bold().paint("irb:"), level, prompt).to_string());
         let mut line = match readline {
             Ok(line) => line,
-            Err(_) => return,
+            Err(_) => {
+                // Execute `at_exit` handlers at the end of the session.
+                if let Err(err) = vm.exec_at_exit() {
+                    err.show_err();
+                }
+                return;
+            }
         };
         rl.add_history_entry(line.clone());
         line.push('\n');
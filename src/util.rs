@@ -63,6 +63,12 @@ impl<T> Ref<T> {
     pub fn id(&self) -> u64 {
         self.0.as_ptr() as u64
     }
+
+    /// Free the heap memory which `self` points to.
+    /// `self` and its copies must not be used after calling this.
+    pub fn free(self) {
+        unsafe { drop(Box::from_raw(self.0.as_ptr())) };
+    }
 }
 
 impl<T: Clone> Ref<T> {
@@ -136,6 +142,12 @@ impl SourceInfo {
         eprintln!("{}", self.path.to_string_lossy());
     }
 
+    /// Get the line number of the Loc in the source code.
+    pub fn get_line(&self, loc: &Loc) -> usize {
+        let pos = std::cmp::min(loc.0 as usize, self.code.len());
+        self.code[..pos].iter().filter(|ch| **ch == '\n').count() + 1
+    }
+
     /// Show the location of the Loc in the source code using '^^^'.
     pub fn show_loc(&self, loc: &Loc) {
         let mut line: u32 = 1;
@@ -289,6 +301,9 @@ impl IdentId {
     pub const _NEQ: IdentId = id!(12);
     pub const _GT: IdentId = id!(13);
     pub const _GE: IdentId = id!(14);
+    pub const _LT: IdentId = id!(15);
+    pub const _LE: IdentId = id!(16);
+    pub const _CMP: IdentId = id!(17);
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -320,6 +335,9 @@ impl IdentifierTable {
         table.set_ident_id("!=", IdentId::_NEQ);
         table.set_ident_id(">", IdentId::_GT);
         table.set_ident_id(">=", IdentId::_GE);
+        table.set_ident_id("<", IdentId::_LT);
+        table.set_ident_id("<=", IdentId::_LE);
+        table.set_ident_id("<=>", IdentId::_CMP);
         table
     }
 
@@ -91,6 +91,14 @@ impl ArrayInfo {
     }
 }
 
+impl GC for ArrayInfo {
+    fn mark(&self, alloc: &mut Allocator) {
+        for elem in &self.elements {
+            elem.mark(alloc);
+        }
+    }
+}
+
 pub type ArrayRef = Ref<ArrayInfo>;
 
 impl ArrayRef {
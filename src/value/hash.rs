@@ -1,12 +1,177 @@
 use crate::*;
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::iter::FromIterator;
 use std::ops::Deref;
 
+/// The entries of Hash are kept in insertion order, and deletion does not disturb the order.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HashInfo {
-    Map(HashMap<HashKey, Value>),
-    IdentMap(HashMap<IdentKey, Value>),
+    Map(OrderedMap<HashKey>),
+    IdentMap(OrderedMap<IdentKey>),
+}
+
+/// An insertion-ordered map from `K` to Value.
+///
+/// Removing an entry leaves a tombstone in `entries`, so that removal is O(1)
+/// and keeps the order of the other entries. Tombstones are swept out
+/// when they outnumber the live entries.
+#[derive(Debug, Clone)]
+pub struct OrderedMap<K: Hash + Eq + Copy> {
+    index: HashMap<K, usize>,
+    entries: Vec<Option<(K, Value)>>,
+}
+
+impl<K: Hash + Eq + Copy> OrderedMap<K> {
+    pub fn new() -> Self {
+        OrderedMap {
+            index: HashMap::new(),
+            entries: vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn get(&self, k: &K) -> Option<&Value> {
+        match self.index.get(k) {
+            Some(i) => self.entries[*i].as_ref().map(|(_, v)| v),
+            None => None,
+        }
+    }
+
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.index.contains_key(k)
+    }
+
+    /// Insert `v` for `k`. An existing entry keeps its key and its position.
+    pub fn insert(&mut self, k: K, v: Value) -> Option<Value> {
+        match self.index.get(&k) {
+            Some(i) => match &mut self.entries[*i] {
+                Some((_, old)) => Some(std::mem::replace(old, v)),
+                None => unreachable!("An index refers to a removed entry."),
+            },
+            None => {
+                self.index.insert(k, self.entries.len());
+                self.entries.push(Some((k, v)));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, k: &K) -> Option<Value> {
+        let i = self.index.remove(k)?;
+        let res = self.entries[i].take().map(|(_, v)| v);
+        if self.entries.len() > self.index.len() * 2 {
+            self.compact();
+        }
+        res
+    }
+
+    /// Remove the entry inserted last.
+    fn remove_last(&mut self) {
+        self.entries.pop();
+        self.compact();
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &mut Value) -> bool) {
+        for entry in self.entries.iter_mut() {
+            if let Some((k, v)) = entry {
+                if !f(k, v) {
+                    *entry = None;
+                }
+            }
+        }
+        self.compact();
+    }
+
+    pub fn clear(&mut self) {
+        self.index.clear();
+        self.entries.clear();
+    }
+
+    pub fn iter(&self) -> MapIter<K> {
+        MapIter(self.entries.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> MapIterMut<K> {
+        MapIterMut(self.entries.iter_mut())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// Sweep out tombstones and rebuild the index.
+    fn compact(&mut self) {
+        self.entries.retain(|entry| entry.is_some());
+        self.index = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.unwrap().0, i))
+            .collect();
+    }
+}
+
+impl<K: Hash + Eq + Copy> Default for OrderedMap<K> {
+    fn default() -> Self {
+        OrderedMap::new()
+    }
+}
+
+impl<K: Hash + Eq + Copy> PartialEq for OrderedMap<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K: Hash + Eq + Copy> Eq for OrderedMap<K> {}
+
+impl<K: Hash + Eq + Copy> FromIterator<(K, Value)> for OrderedMap<K> {
+    fn from_iter<T: IntoIterator<Item = (K, Value)>>(iter: T) -> Self {
+        let mut map = OrderedMap::new();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
+    }
+}
+
+impl<K: Hash + Eq + Copy> IntoIterator for OrderedMap<K> {
+    type Item = (K, Value);
+    type IntoIter = std::iter::Flatten<std::vec::IntoIter<Option<(K, Value)>>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter().flatten()
+    }
+}
+
+pub struct MapIter<'a, K>(std::slice::Iter<'a, Option<(K, Value)>>);
+
+impl<'a, K> Iterator for MapIter<'a, K> {
+    type Item = (&'a K, &'a Value);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.find_map(|entry| entry.as_ref().map(|(k, v)| (k, v)))
+    }
+}
+
+pub struct MapIterMut<'a, K>(std::slice::IterMut<'a, Option<(K, Value)>>);
+
+impl<'a, K> Iterator for MapIterMut<'a, K> {
+    type Item = (&'a K, &'a mut Value);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .find_map(|entry| entry.as_mut().map(|(k, v)| (&*k, v)))
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -25,9 +190,14 @@ impl Hash for HashKey {
             None => self.0.hash(state),
             Some(lhs) => match &lhs.kind {
                 ObjKind::Integer(lhs) => lhs.hash(state),
+                ObjKind::BigNum(lhs) => lhs.hash(state),
                 ObjKind::Float(lhs) => (*lhs as u64).hash(state),
                 ObjKind::String(lhs) => lhs.hash(state),
-                ObjKind::Array(lhs) => lhs.elements.hash(state),
+                ObjKind::Array(lhs) => {
+                    for elem in &lhs.elements {
+                        HashKey(*elem).hash(state);
+                    }
+                }
                 ObjKind::Range(lhs) => lhs.hash(state),
                 ObjKind::Hash(lhs) => {
                     for (key, val) in lhs.iter() {
@@ -36,12 +206,56 @@ impl Hash for HashKey {
                     }
                 }
                 ObjKind::Method(lhs) => lhs.inner().hash(state),
+                ObjKind::Ordinary => match call_hash(self.0) {
+                    Some(hash) => HashKey(hash).hash(state),
+                    None => self.0.hash(state),
+                },
                 _ => self.0.hash(state),
             },
         }
     }
 }
 
+/// Call `hash` of the ordinary object `val`.
+/// Return None if no VM is running, or `hash` failed or did not return an Integer.
+/// The error raised in `hash` is reported by `HashInfo::catch_key_error`.
+fn call_hash(val: Value) -> Option<Value> {
+    let mut vm = VM::current()?;
+    let id = vm.globals.get_ident_id("hash");
+    match vm.send0(val, id) {
+        Ok(hash) if hash.as_fixnum().is_some() || hash.as_bignum().is_some() => Some(hash),
+        Ok(_) => None,
+        Err(err) => {
+            set_key_error(&mut vm, err);
+            None
+        }
+    }
+}
+
+/// Call `eql?` of the ordinary object `lhs`.
+/// Return false if no VM is running or `eql?` failed.
+/// The error raised in `eql?` is reported by `HashInfo::catch_key_error`.
+fn call_eql(lhs: Value, rhs: Value) -> bool {
+    let mut vm = match VM::current() {
+        Some(vm) => vm,
+        None => return false,
+    };
+    let id = vm.globals.get_ident_id("eql?");
+    match vm.send1(lhs, id, rhs) {
+        Ok(res) => vm.val_to_bool(res),
+        Err(err) => {
+            set_key_error(&mut vm, err);
+            false
+        }
+    }
+}
+
+fn set_key_error(vm: &mut VM, err: RubyError) {
+    if vm.hash_key_error.is_none() {
+        vm.hash_key_error = Some(err);
+    }
+}
+
 impl PartialEq for HashKey {
     // Object#eql?()
     // This type of equality is used for comparison for keys of Hash.
@@ -53,12 +267,21 @@ impl PartialEq for HashKey {
             (None, None) => self.0 == other.0,
             (Some(lhs), Some(rhs)) => match (&lhs.kind, &rhs.kind) {
                 (ObjKind::Integer(lhs), ObjKind::Integer(rhs)) => *lhs == *rhs,
+                (ObjKind::BigNum(lhs), ObjKind::BigNum(rhs)) => *lhs == *rhs,
                 (ObjKind::Float(lhs), ObjKind::Float(rhs)) => *lhs == *rhs,
                 (ObjKind::String(lhs), ObjKind::String(rhs)) => *lhs == *rhs,
-                (ObjKind::Array(lhs), ObjKind::Array(rhs)) => lhs.elements == rhs.elements,
+                (ObjKind::Array(lhs), ObjKind::Array(rhs)) => {
+                    lhs.elements.len() == rhs.elements.len()
+                        && lhs
+                            .elements
+                            .iter()
+                            .zip(rhs.elements.iter())
+                            .all(|(l, r)| HashKey(*l) == HashKey(*r))
+                }
                 (ObjKind::Range(lhs), ObjKind::Range(rhs)) => *lhs == *rhs,
                 (ObjKind::Hash(lhs), ObjKind::Hash(rhs)) => lhs.inner() == rhs.inner(),
                 (ObjKind::Method(lhs), ObjKind::Method(rhs)) => *lhs.inner() == *rhs.inner(),
+                (ObjKind::Ordinary, ObjKind::Ordinary) => call_eql(self.0, other.0),
                 _ => lhs.kind == rhs.kind,
             },
             _ => false,
@@ -93,11 +316,9 @@ impl PartialEq for IdentKey {
 }
 impl Eq for IdentKey {}
 
-use std::collections::hash_map;
-
 pub enum IntoIter {
-    Map(hash_map::IntoIter<HashKey, Value>),
-    IdentMap(hash_map::IntoIter<IdentKey, Value>),
+    Map(<OrderedMap<HashKey> as IntoIterator>::IntoIter),
+    IdentMap(<OrderedMap<IdentKey> as IntoIterator>::IntoIter),
 }
 
 impl IntoIter {
@@ -126,16 +347,16 @@ impl Iterator for IntoIter {
 }
 
 macro_rules! define_iter {
-    ($trait:ident) => {
+    ($trait:ident, $inner:ident) => {
         pub enum $trait<'a> {
-            Map(hash_map::$trait<'a, HashKey, Value>),
-            IdentMap(hash_map::$trait<'a, IdentKey, Value>),
+            Map($inner<'a, HashKey>),
+            IdentMap($inner<'a, IdentKey>),
         }
     };
 }
 
-define_iter!(Iter);
-define_iter!(IterMut);
+define_iter!(Iter, MapIter);
+define_iter!(IterMut, MapIterMut);
 
 macro_rules! define_iter_new {
     ($ty1: ident, $ty2: ty, $method: ident) => {
@@ -201,7 +422,7 @@ impl IntoIterator for HashInfo {
 }
 
 impl HashInfo {
-    pub fn new(map: HashMap<HashKey, Value>) -> Self {
+    pub fn new(map: OrderedMap<HashKey>) -> Self {
         HashInfo::Map(map)
     }
 
@@ -213,13 +434,27 @@ impl HashInfo {
         IterMut::new(self)
     }
 
-    pub fn get(&self, v: &Value) -> Option<&Value> {
-        match self {
-            HashInfo::Map(map) => map.get(&HashKey(*v)),
-            HashInfo::IdentMap(map) => map.get(&IdentKey(*v)),
+    /// Run `f`, and return the first error raised in `hash` or `eql?` of the keys during `f`.
+    pub fn catch_key_error<T>(f: impl FnOnce() -> T) -> Result<T, RubyError> {
+        let mut vm = match VM::current() {
+            Some(vm) => vm,
+            None => return Ok(f()),
+        };
+        let prev = vm.hash_key_error.take();
+        let res = f();
+        match std::mem::replace(&mut vm.hash_key_error, prev) {
+            Some(err) => Err(err),
+            None => Ok(res),
         }
     }
 
+    pub fn get(&self, v: &Value) -> Result<Option<Value>, RubyError> {
+        HashInfo::catch_key_error(|| match self {
+            HashInfo::Map(map) => map.get(&HashKey(*v)).cloned(),
+            HashInfo::IdentMap(map) => map.get(&IdentKey(*v)).cloned(),
+        })
+    }
+
     pub fn len(&self) -> usize {
         match self {
             HashInfo::Map(map) => map.len(),
@@ -234,25 +469,36 @@ impl HashInfo {
         }
     }
 
-    pub fn insert(&mut self, k: Value, v: Value) {
-        match self {
-            HashInfo::Map(map) => map.insert(HashKey(k), v),
-            HashInfo::IdentMap(map) => map.insert(IdentKey(k), v),
-        };
+    /// Insert `v` for `k`. If `hash` or `eql?` of the keys raised an error, a new entry is not added.
+    pub fn insert(&mut self, k: Value, v: Value) -> Result<(), RubyError> {
+        let len = self.len();
+        let res = HashInfo::catch_key_error(|| {
+            match self {
+                HashInfo::Map(map) => map.insert(HashKey(k), v),
+                HashInfo::IdentMap(map) => map.insert(IdentKey(k), v),
+            };
+        });
+        if res.is_err() && self.len() > len {
+            match self {
+                HashInfo::Map(map) => map.remove_last(),
+                HashInfo::IdentMap(map) => map.remove_last(),
+            }
+        }
+        res
     }
 
-    pub fn remove(&mut self, k: Value) -> Option<Value> {
-        match self {
+    pub fn remove(&mut self, k: Value) -> Result<Option<Value>, RubyError> {
+        HashInfo::catch_key_error(|| match self {
             HashInfo::Map(map) => map.remove(&HashKey(k)),
             HashInfo::IdentMap(map) => map.remove(&IdentKey(k)),
-        }
+        })
     }
 
-    pub fn contains_key(&self, k: Value) -> bool {
-        match self {
+    pub fn contains_key(&self, k: Value) -> Result<bool, RubyError> {
+        HashInfo::catch_key_error(|| match self {
             HashInfo::Map(map) => map.contains_key(&HashKey(k)),
             HashInfo::IdentMap(map) => map.contains_key(&IdentKey(k)),
-        }
+        })
     }
 
     pub fn keys(&self) -> Vec<Value> {
@@ -291,10 +537,19 @@ impl HashInfo {
     }
 }
 
+impl GC for HashInfo {
+    fn mark(&self, alloc: &mut Allocator) {
+        for (k, v) in self.iter() {
+            k.mark(alloc);
+            v.mark(alloc);
+        }
+    }
+}
+
 pub type HashRef = Ref<HashInfo>;
 
 impl HashRef {
-    pub fn from(map: HashMap<HashKey, Value>) -> Self {
+    pub fn from(map: OrderedMap<HashKey>) -> Self {
         HashRef::new(HashInfo::new(map))
     }
 }
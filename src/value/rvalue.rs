@@ -1,3 +1,4 @@
+use num::BigInt;
 use std::collections::HashMap;
 //#[macro_use]
 use crate::*;
@@ -14,6 +15,7 @@ pub struct RValue {
 pub enum ObjKind {
     Ordinary,
     Integer(i64),
+    BigNum(BigInt),
     Float(f64),
     Class(ClassRef),
     Module(ClassRef),
@@ -27,6 +29,11 @@ pub enum ObjKind {
     Method(MethodObjRef),
     Fiber(FiberRef),
     Enumerator(EnumRef),
+    Exception(ExceptionRef),
+    /// Enumerator::Generator, which holds the block of Enumerator.new.
+    Generator(Value),
+    /// Enumerator::Yielder, which holds the target of the yielded values.
+    Yielder(Value),
 }
 
 impl RValue {
@@ -46,9 +53,12 @@ impl RValue {
                 ObjKind::Array(aref) => ObjKind::Array(aref.dup()),
                 ObjKind::Class(cref) => ObjKind::Class(cref.dup()),
                 ObjKind::Enumerator(eref) => ObjKind::Enumerator(eref.dup()),
+                ObjKind::Exception(eref) => ObjKind::Exception(eref.dup()),
                 ObjKind::Fiber(_fref) => ObjKind::Ordinary,
                 ObjKind::Integer(num) => ObjKind::Integer(*num),
+                ObjKind::BigNum(num) => ObjKind::BigNum(num.clone()),
                 ObjKind::Float(num) => ObjKind::Float(*num),
+                ObjKind::Generator(v) => ObjKind::Generator(*v),
                 ObjKind::Hash(href) => ObjKind::Hash(href.dup()),
                 ObjKind::Method(mref) => ObjKind::Method(mref.dup()),
                 ObjKind::Module(cref) => ObjKind::Module(cref.dup()),
@@ -58,6 +68,7 @@ impl RValue {
                 ObjKind::Regexp(rref) => ObjKind::Regexp(*rref),
                 ObjKind::Splat(v) => ObjKind::Splat(*v),
                 ObjKind::String(rstr) => ObjKind::String(rstr.clone()),
+                ObjKind::Yielder(v) => ObjKind::Yielder(*v),
             },
         }
     }
@@ -96,6 +107,14 @@ impl RValue {
         }
     }
 
+    pub fn new_bignum(n: BigInt) -> Self {
+        RValue {
+            class: Value::nil(),
+            var_table: Box::new(HashMap::new()),
+            kind: ObjKind::BigNum(n),
+        }
+    }
+
     pub fn new_flonum(f: f64) -> Self {
         RValue {
             class: Value::nil(),
@@ -200,14 +219,16 @@ impl RValue {
         }
     }
 
-    pub fn new_fiber(
-        globals: &Globals,
-        vm: VMRef,
-        context: ContextRef,
-        rec: std::sync::mpsc::Receiver<VMResult>,
-        tx: std::sync::mpsc::SyncSender<usize>,
-    ) -> Self {
-        let fiber = FiberInfo::new(vm, context, rec, tx);
+    pub fn new_exception(class: Value, exceptionref: ExceptionRef) -> Self {
+        RValue {
+            class,
+            var_table: Box::new(HashMap::new()),
+            kind: ObjKind::Exception(exceptionref),
+        }
+    }
+
+    pub fn new_fiber(globals: &Globals, vm: VMRef, kind: FiberKind) -> Self {
+        let fiber = FiberInfo::new(vm, kind);
         RValue {
             class: globals.builtins.fiber,
             var_table: Box::new(HashMap::new()),
@@ -220,25 +241,90 @@ impl RValue {
         method: IdentId,
         receiver: Value,
         mut args: Args,
+        size: EnumSize,
     ) -> Self {
         args.block = Some(MethodRef::from(0));
-        let enum_info = EnumRef::from(method, receiver, args);
+        let enum_info = EnumRef::from(method, receiver, args, size);
         RValue {
             class: globals.builtins.enumerator,
             var_table: Box::new(HashMap::new()),
             kind: ObjKind::Enumerator(enum_info),
         }
     }
+
+    pub fn new_generator(class: Value, procobj: Value) -> Self {
+        RValue {
+            class,
+            var_table: Box::new(HashMap::new()),
+            kind: ObjKind::Generator(procobj),
+        }
+    }
+
+    pub fn new_yielder(class: Value, target: Value) -> Self {
+        RValue {
+            class,
+            var_table: Box::new(HashMap::new()),
+            kind: ObjKind::Yielder(target),
+        }
+    }
 }
 
 pub type ObjectRef = Ref<RValue>;
 
+impl GC for RValue {
+    fn mark(&self, alloc: &mut Allocator) {
+        self.class.mark(alloc);
+        for val in self.var_table.values() {
+            val.mark(alloc);
+        }
+        match &self.kind {
+            ObjKind::Class(cref) | ObjKind::Module(cref) => cref.mark(alloc),
+            ObjKind::Array(aref) => aref.mark(alloc),
+            ObjKind::Hash(href) => href.mark(alloc),
+            ObjKind::Range(info) => {
+                info.start.mark(alloc);
+                info.end.mark(alloc);
+            }
+            ObjKind::Splat(val) => val.mark(alloc),
+            ObjKind::Proc(pref) => pref.mark(alloc),
+            ObjKind::Method(mref) => mref.receiver.mark(alloc),
+            ObjKind::Fiber(fref) => fref.mark(alloc),
+            ObjKind::Enumerator(eref) => eref.mark(alloc),
+            ObjKind::Exception(eref) => eref.mark(alloc),
+            ObjKind::Generator(val) | ObjKind::Yielder(val) => val.mark(alloc),
+            _ => {}
+        }
+    }
+}
+
 impl RValue {
     /// Pack `self` into `Value`(64-bit data representation).
-    /// This method consumes `self` and allocates it on the heap, returning `Value`,
+    /// This method consumes `self` and allocates it on the GC heap, returning `Value`,
     /// a wrapped raw pointer.  
     pub fn pack(self) -> Value {
-        Value::from(Box::into_raw(Box::new(self)) as u64)
+        Value::from(Allocator::alloc_current(self) as u64)
+    }
+
+    /// Free the memory owned by `self` which is not freed by drop.
+    /// This method is called by GC before `self` is dropped.
+    pub fn free(&mut self) {
+        match self.kind {
+            ObjKind::Class(cref) | ObjKind::Module(cref) => cref.free(),
+            ObjKind::Proc(pref) => {
+                // The block context of a proc is owned by the proc.
+                if let ProcKind::Block(context) = pref.kind {
+                    context.free();
+                }
+                pref.free();
+            }
+            ObjKind::Method(mref) => mref.free(),
+            ObjKind::Enumerator(eref) => eref.free(),
+            ObjKind::Array(aref) => aref.free(),
+            ObjKind::Hash(href) => href.free(),
+            ObjKind::Fiber(fref) => fref.free(),
+            ObjKind::Exception(eref) => eref.free(),
+            _ => {}
+        }
     }
 
     /// Return a class of the object. If the objetct has a sigleton class, return the singleton class.
@@ -1,4 +1,5 @@
 use crate::*;
+use num::{BigInt, ToPrimitive};
 
 const FALSE_VALUE: u64 = 0x00;
 const UNINITIALIZED: u64 = 0x04;
@@ -85,6 +86,7 @@ impl std::hash::Hash for Value {
             None => self.0.hash(state),
             Some(lhs) => match &lhs.kind {
                 ObjKind::Integer(lhs) => lhs.hash(state),
+                ObjKind::BigNum(lhs) => lhs.hash(state),
                 ObjKind::Float(lhs) => lhs.to_bits().hash(state),
                 ObjKind::String(lhs) => lhs.hash(state),
                 ObjKind::Array(lhs) => lhs.elements.hash(state),
@@ -123,6 +125,7 @@ impl PartialEq for Value {
         };
         match (&self.rvalue().kind, &other.rvalue().kind) {
             (ObjKind::Integer(lhs), ObjKind::Integer(rhs)) => *lhs == *rhs,
+            (ObjKind::BigNum(lhs), ObjKind::BigNum(rhs)) => *lhs == *rhs,
             (ObjKind::Float(lhs), ObjKind::Float(rhs)) => *lhs == *rhs,
             (ObjKind::Integer(lhs), ObjKind::Float(rhs)) => *lhs as f64 == *rhs,
             (ObjKind::Float(lhs), ObjKind::Integer(rhs)) => *lhs == *rhs as f64,
@@ -142,6 +145,14 @@ impl PartialEq for Value {
 }
 impl Eq for Value {}
 
+impl GC for Value {
+    fn mark(&self, alloc: &mut Allocator) {
+        if let Some(oref) = self.is_object() {
+            alloc.mark(oref);
+        }
+    }
+}
+
 impl Default for Value {
     fn default() -> Self {
         Value::nil()
@@ -224,13 +235,13 @@ impl Value {
                 } else if self.is_packed_num() {
                     globals.builtins.float
                 } else if self.is_packed_symbol() {
-                    globals.builtins.object
+                    globals.builtins.symbol
                 } else {
                     globals.builtins.object
                 }
             }
             Some(info) => match &info.kind {
-                ObjKind::Integer(_) => globals.builtins.integer,
+                ObjKind::Integer(_) | ObjKind::BigNum(_) => globals.builtins.integer,
                 ObjKind::Float(_) => globals.builtins.float,
                 _ => info.class(),
             },
@@ -245,13 +256,13 @@ impl Value {
                 } else if self.is_packed_num() {
                     globals.builtins.float
                 } else if self.is_packed_symbol() {
-                    globals.builtins.object
+                    globals.builtins.symbol
                 } else {
                     globals.builtins.object
                 }
             }
             Some(info) => match &info.kind {
-                ObjKind::Integer(_) => globals.builtins.integer,
+                ObjKind::Integer(_) | ObjKind::BigNum(_) => globals.builtins.integer,
                 ObjKind::Float(_) => globals.builtins.float,
                 _ => info.search_class(),
             },
@@ -289,22 +300,6 @@ impl Value {
             None => false,
         }
     }
-
-    pub fn get_instance_method(&self, id: IdentId) -> Option<MethodRef> {
-        let cref = self.as_module().unwrap();
-        match cref.method_table.get(&id) {
-            Some(method) => Some(*method),
-            None => {
-                for v in &cref.include {
-                    match v.get_instance_method(id) {
-                        Some(method) => return Some(method),
-                        None => {}
-                    }
-                }
-                None
-            }
-        }
-    }
 }
 
 impl Value {
@@ -358,6 +353,16 @@ impl Value {
         }
     }
 
+    pub fn as_bignum(&self) -> Option<&BigInt> {
+        match self.as_rvalue() {
+            Some(info) => match &info.kind {
+                ObjKind::BigNum(n) => Some(n),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
     pub fn expect_integer(&self, vm: &VM, msg: impl Into<String>) -> Result<i64, RubyError> {
         match self.as_fixnum() {
             Some(i) => Ok(i),
@@ -555,6 +560,36 @@ impl Value {
         }
     }
 
+    pub fn as_generator(&self) -> Option<Value> {
+        match self.is_object() {
+            Some(oref) => match oref.kind {
+                ObjKind::Generator(procobj) => Some(procobj),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
+    pub fn as_yielder(&self) -> Option<Value> {
+        match self.is_object() {
+            Some(oref) => match oref.kind {
+                ObjKind::Yielder(target) => Some(target),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
+    pub fn as_exception(&self) -> Option<ExceptionRef> {
+        match self.is_object() {
+            Some(oref) => match oref.kind {
+                ObjKind::Exception(eref) => Some(eref),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
     pub fn as_symbol(&self) -> Option<IdentId> {
         if self.is_packed_symbol() {
             Some(self.as_packed_symbol())
@@ -618,6 +653,15 @@ impl Value {
         }
     }
 
+    /// Generate Integer from `num`.
+    /// Fixnum is returned if `num` fits in i64.
+    pub fn bignum(num: BigInt) -> Self {
+        match num.to_i64() {
+            Some(i) => Value::fixnum(i),
+            None => RValue::new_bignum(num).pack(),
+        }
+    }
+
     pub fn flonum(num: f64) -> Self {
         if num == 0.0 {
             return Value(ZERO);
@@ -693,7 +737,7 @@ impl Value {
         Value::object(RValue::new_hash(globals, hash_ref))
     }
 
-    pub fn hash_from(globals: &Globals, hash: std::collections::HashMap<HashKey, Value>) -> Self {
+    pub fn hash_from(globals: &Globals, hash: OrderedMap<HashKey>) -> Self {
         Value::object(RValue::new_hash(globals, HashRef::from(hash)))
     }
 
@@ -705,6 +749,14 @@ impl Value {
         Value::object(RValue::new_proc(globals, ProcRef::from(context)))
     }
 
+    pub fn procobj_from(globals: &Globals, procref: ProcRef) -> Self {
+        Value::object(RValue::new_proc(globals, procref))
+    }
+
+    pub fn lambda(globals: &Globals, context: ContextRef) -> Self {
+        Value::object(RValue::new_proc(globals, ProcRef::lambda(context)))
+    }
+
     pub fn method(globals: &Globals, name: IdentId, receiver: Value, method: MethodRef) -> Self {
         Value::object(RValue::new_method(
             globals,
@@ -712,18 +764,34 @@ impl Value {
         ))
     }
 
-    pub fn fiber(
+    pub fn fiber(globals: &Globals, vm: VMRef, kind: FiberKind) -> Self {
+        Value::object(RValue::new_fiber(globals, vm, kind))
+    }
+
+    pub fn enumerator(globals: &Globals, method: IdentId, receiver: Value, args: Args) -> Self {
+        Value::enumerator_with_size(globals, method, receiver, args, EnumSize::Unknown)
+    }
+
+    pub fn enumerator_with_size(
         globals: &Globals,
-        vm: VMRef,
-        context: ContextRef,
-        rec: std::sync::mpsc::Receiver<VMResult>,
-        tx: std::sync::mpsc::SyncSender<usize>,
+        method: IdentId,
+        receiver: Value,
+        args: Args,
+        size: EnumSize,
     ) -> Self {
-        Value::object(RValue::new_fiber(globals, vm, context, rec, tx))
+        Value::object(RValue::new_enumerator(globals, method, receiver, args, size))
     }
 
-    pub fn enumerator(globals: &Globals, method: IdentId, receiver: Value, args: Args) -> Self {
-        Value::object(RValue::new_enumerator(globals, method, receiver, args))
+    pub fn generator(class: Value, procobj: Value) -> Self {
+        Value::object(RValue::new_generator(class, procobj))
+    }
+
+    pub fn yielder(class: Value, target: Value) -> Self {
+        Value::object(RValue::new_yielder(class, target))
+    }
+
+    pub fn exception(class: Value, message: Value) -> Self {
+        Value::object(RValue::new_exception(class, ExceptionRef::from(message)))
     }
 }
 
@@ -733,6 +801,11 @@ impl Value {
         if self.id() == other.id() {
             return true;
         };
+        match (self.as_bignum(), other.as_bignum()) {
+            (Some(lhs), None) => return Value::bignum_eq_float(lhs, other),
+            (None, Some(rhs)) => return Value::bignum_eq_float(rhs, self),
+            _ => {}
+        };
         if self.is_packed_value() || other.is_packed_value() {
             if self.is_packed_num() && other.is_packed_num() {
                 match (self.is_packed_fixnum(), other.is_packed_fixnum()) {
@@ -749,6 +822,7 @@ impl Value {
         };
         match (&self.rvalue().kind, &other.rvalue().kind) {
             (ObjKind::Integer(lhs), ObjKind::Integer(rhs)) => *lhs == *rhs,
+            (ObjKind::BigNum(lhs), ObjKind::BigNum(rhs)) => *lhs == *rhs,
             (ObjKind::Float(lhs), ObjKind::Float(rhs)) => *lhs == *rhs,
             (ObjKind::Integer(lhs), ObjKind::Float(rhs)) => *lhs as f64 == *rhs,
             (ObjKind::Float(lhs), ObjKind::Integer(rhs)) => *lhs == *rhs as f64,
@@ -762,6 +836,15 @@ impl Value {
         }
     }
 
+    fn bignum_eq_float(lhs: &BigInt, rhs: Value) -> bool {
+        match rhs.as_flonum() {
+            Some(rhs) => {
+                builtin::integer::cmp_bigint_float(lhs, rhs) == Some(std::cmp::Ordering::Equal)
+            }
+            None => false,
+        }
+    }
+
     pub fn to_ordering(&self) -> std::cmp::Ordering {
         use std::cmp::Ordering;
         match self.as_fixnum() {
@@ -102,6 +102,17 @@ impl Deref for Args {
     }
 }
 
+impl GC for Args {
+    fn mark(&self, alloc: &mut Allocator) {
+        for arg in self.iter() {
+            arg.mark(alloc);
+        }
+        if let Some(kw_arg) = self.kw_arg {
+            kw_arg.mark(alloc);
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 enum ArgsArray {
     Array {
@@ -1,13 +1,21 @@
 use crate::*;
-use std::collections::HashMap;
+use indexmap::IndexMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone)]
 pub struct ClassInfo {
     pub name: Option<IdentId>,
     pub method_table: MethodTable,
     pub superclass: Value,
+    /// Modules included in this class. The module included later comes first.
     pub include: Vec<Value>,
+    /// Modules prepended to this class. The module prepended later comes first.
+    pub prepend: Vec<Value>,
     pub is_singleton: bool,
+    /// Constants which can not be referred with an explicit scope. (e.g. `Foo::BAR`)
+    pub private_constants: HashSet<IdentId>,
+    /// Class variables. (e.g. `@@foo`)
+    pub class_vars: IndexMap<IdentId, Value>,
 }
 
 impl ClassInfo {
@@ -17,7 +25,22 @@ impl ClassInfo {
             method_table: HashMap::new(),
             superclass,
             include: vec![],
+            prepend: vec![],
             is_singleton: false,
+            private_constants: HashSet::new(),
+            class_vars: IndexMap::new(),
+        }
+    }
+}
+
+impl GC for ClassInfo {
+    fn mark(&self, alloc: &mut Allocator) {
+        self.superclass.mark(alloc);
+        for module in self.include.iter().chain(self.prepend.iter()) {
+            module.mark(alloc);
+        }
+        for val in self.class_vars.values() {
+            val.mark(alloc);
         }
     }
 }
@@ -40,4 +63,22 @@ impl ClassRef {
             Some(self.superclass.as_class())
         }
     }
+
+    /// Search the class variable `id` in the inheritance chain including modules,
+    /// and return the class or module which holds it.
+    pub fn class_var_owner(&self, id: IdentId) -> Option<ClassRef> {
+        let mut class = *self;
+        loop {
+            if class.class_vars.contains_key(&id) {
+                return Some(class);
+            }
+            for module in class.prepend.iter().chain(class.include.iter()) {
+                let module = module.as_module().unwrap();
+                if module.class_vars.contains_key(&id) {
+                    return Some(module);
+                }
+            }
+            class = class.superclass()?;
+        }
+    }
 }
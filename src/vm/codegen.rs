@@ -1,6 +1,6 @@
 use super::vm_inst::*;
 use crate::error::{ParseErrKind, RubyError, RuntimeErrKind};
-use crate::parse::node::{BinOp, Node, NodeKind, UnOp};
+use crate::parse::node::{BinOp, Node, NodeKind, RescueEntry, SendArgs, UnOp};
 use crate::*;
 use std::collections::HashMap;
 
@@ -65,18 +65,31 @@ enum EscapeKind {
 pub struct Context {
     lvar_info: HashMap<IdentId, LvarId>,
     pub iseq_sourcemap: Vec<(ISeqPos, Loc)>,
+    exception_table: Vec<ExceptionEntry>,
     exceptions: Vec<Exceptions>,
+    /// Start positions of the `begin` expressions whose rescue clauses are now being generated.
+    retry_dest: Vec<ISeqPos>,
+    /// Loop depths of the ensure clauses for errors which are now being generated.
+    /// The error caught by each of the clauses is pending on the exception stack of VM.
+    error_ensures: Vec<usize>,
+    /// Loop depths of the rescue clauses which are now being generated.
+    /// `$!` saved on entering each of the clauses is restored when the clause is left.
+    rescue_clauses: Vec<usize>,
     kind: ContextKind,
 }
 
+/// Ensure clause of the `begin` expression which is now being generated.
+/// The clause is expanded in place when `break`, `next` or `return` escapes from the protected region.
 #[derive(Debug, Clone, PartialEq)]
 struct Exceptions {
-    entry: Vec<ISeqPos>,
+    ensure: Node,
+    /// The depth of loop_stack where the `begin` expression is placed.
+    loop_depth: usize,
 }
 
 impl Exceptions {
-    fn new() -> Self {
-        Exceptions { entry: vec![] }
+    fn new(ensure: Node, loop_depth: usize) -> Self {
+        Exceptions { ensure, loop_depth }
     }
 }
 
@@ -92,7 +105,11 @@ impl Context {
         Context {
             lvar_info: HashMap::new(),
             iseq_sourcemap: vec![],
+            exception_table: vec![],
             exceptions: vec![],
+            retry_dest: vec![],
+            error_ensures: vec![],
+            rescue_clauses: vec![],
             kind: ContextKind::Eval,
         }
     }
@@ -101,7 +118,11 @@ impl Context {
         Context {
             lvar_info,
             iseq_sourcemap: vec![],
+            exception_table: vec![],
             exceptions: vec![],
+            retry_dest: vec![],
+            error_ensures: vec![],
+            rescue_clauses: vec![],
             kind,
         }
     }
@@ -310,6 +331,44 @@ impl Codegen {
         iseq.push(Inst::MRETURN);
     }
 
+    /// Generate Inst::TRY at the start of the protected region, and return its position.
+    fn gen_try(&mut self, iseq: &mut ISeq) -> ISeqPos {
+        let start = Codegen::current(iseq);
+        iseq.push(Inst::TRY);
+        start
+    }
+
+    fn gen_rescue_check(&mut self, iseq: &mut ISeq, len: usize) {
+        iseq.push(Inst::RESCUE);
+        Codegen::push32(iseq, len as u32);
+    }
+
+    fn gen_raise(&mut self, iseq: &mut ISeq) {
+        iseq.push(Inst::RAISE);
+    }
+
+    fn gen_ensure_end(&mut self, iseq: &mut ISeq) {
+        iseq.push(Inst::ENSURE_END);
+    }
+
+    /// Discard the pending errors of the ensure clauses, and restore `$!` of the rescue clauses
+    /// which `break` or `next` escapes from.
+    fn gen_discard_errors(&mut self, iseq: &mut ISeq) {
+        let loop_depth = self.loop_stack.len();
+        for depth in self.context().error_ensures.iter().rev() {
+            if *depth != loop_depth {
+                break;
+            }
+            iseq.push(Inst::DISCARD_ERR);
+        }
+        for depth in self.context().rescue_clauses.iter().rev() {
+            if *depth != loop_depth {
+                break;
+            }
+            iseq.push(Inst::RESTORE_ERRINFO);
+        }
+    }
+
     fn gen_yield(&mut self, iseq: &mut ISeq, args_num: usize) {
         self.save_cur_loc(iseq);
         iseq.push(Inst::YIELD);
@@ -390,6 +449,17 @@ impl Codegen {
         }
     }
 
+    fn gen_get_class_var(&mut self, iseq: &mut ISeq, id: IdentId, loc: Loc) {
+        self.save_loc(iseq, loc);
+        iseq.push(Inst::GET_CVAR);
+        Codegen::push32(iseq, id.into());
+    }
+
+    fn gen_set_class_var(&mut self, iseq: &mut ISeq, id: IdentId) {
+        iseq.push(Inst::SET_CVAR);
+        Codegen::push32(iseq, id.into());
+    }
+
     fn gen_get_global_var(&mut self, iseq: &mut ISeq, id: IdentId) {
         iseq.push(Inst::GET_GVAR);
         Codegen::push32(iseq, id.into());
@@ -473,6 +543,74 @@ impl Codegen {
         )
     }
 
+    /// Generate arguments, keyword arguments and a block argument of `send_args`.
+    /// Return the flag for argument info and the block given as a literal.
+    fn gen_send_args(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        send_args: &SendArgs,
+    ) -> Result<(usize, Option<MethodRef>), RubyError> {
+        for arg in &send_args.args {
+            self.gen(globals, iseq, arg, true)?;
+        }
+        let kw_flag = self.gen_kw_args(globals, iseq, send_args)?;
+        let mut block_flag = false;
+        let block_ref = match &send_args.block {
+            Some(block) => match &block.kind {
+                NodeKind::Proc { params, body, lvar } => {
+                    self.loop_stack.push(LoopInfo::new_top());
+                    let methodref =
+                        self.gen_iseq(globals, params, body, lvar, true, ContextKind::Block, None)?;
+                    self.loop_stack.pop().unwrap();
+                    Some(methodref)
+                }
+                _ => {
+                    self.gen(globals, iseq, block, true)?;
+                    block_flag = true;
+                    None
+                }
+            },
+            None => None,
+        };
+        return Ok((create_flag(kw_flag, block_flag), block_ref));
+
+        /// Create flag for argument info.
+        /// 0b0011
+        ///     ||
+        ///     |+- 1: keyword args exists. 0: no keyword args,
+        ///     +-- 1: a block arg exists. 0: no block arg.
+        fn create_flag(kw_flag: bool, block_flag: bool) -> usize {
+            (if kw_flag { 1usize } else { 0usize }) + (if block_flag { 2usize } else { 0usize })
+        }
+    }
+
+    /// Generate SUPER.
+    /// The flag for argument info has an additional bit 0b0100 for zsuper,
+    /// which passes the arguments of the current method.
+    fn gen_super(
+        &mut self,
+        iseq: &mut ISeq,
+        args_num: usize,
+        flag: usize,
+        zsuper: bool,
+        block: Option<MethodRef>,
+    ) {
+        let flag = flag + if zsuper { 4 } else { 0 };
+        self.save_cur_loc(iseq);
+        iseq.push(Inst::SUPER);
+        Codegen::push16(iseq, args_num as u32 as u16);
+        Codegen::push16(iseq, flag as u32 as u16);
+        Codegen::push32(
+            iseq,
+            match block {
+                Some(block) => block,
+                None => MethodRef::from(0),
+            }
+            .into(),
+        )
+    }
+
     fn gen_assign(
         &mut self,
         globals: &mut Globals,
@@ -486,6 +624,7 @@ impl Codegen {
                 self.gen_set_const(iseq, *id);
             }
             NodeKind::InstanceVar(id) => self.gen_set_instance_var(iseq, *id),
+            NodeKind::ClassVar(id) => self.gen_set_class_var(iseq, *id),
             NodeKind::GlobalVar(id) => self.gen_set_global_var(iseq, *id),
             NodeKind::Scope(parent, id) => {
                 self.gen(globals, iseq, parent, true)?;
@@ -496,9 +635,14 @@ impl Codegen {
             } => {
                 let name = globals.get_ident_name(*method).to_string() + "=";
                 let assign_id = globals.get_ident_id(name);
-                self.gen(globals, iseq, &receiver, true)?;
-                self.loc = lhs.loc();
-                self.gen_send(globals, iseq, assign_id, 1, 0, None);
+                if NodeKind::SelfValue == receiver.kind {
+                    self.loc = lhs.loc();
+                    self.gen_send_self(globals, iseq, assign_id, 1, 0, None);
+                } else {
+                    self.gen(globals, iseq, &receiver, true)?;
+                    self.loc = lhs.loc();
+                    self.gen_send(globals, iseq, assign_id, 1, 0, None);
+                }
                 self.gen_pop(iseq);
             }
             NodeKind::ArrayMember { array, index } => {
@@ -558,6 +702,106 @@ impl Codegen {
         Ok(())
     }
 
+    /// Generate `begin` body, `rescue` clauses and `else` clause.
+    /// `start` is the start position of the protected region.
+    fn gen_rescue(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        start: ISeqPos,
+        body: &Node,
+        rescue: &[RescueEntry],
+        else_: &Node,
+        use_value: bool,
+    ) -> Result<(), RubyError> {
+        self.gen(globals, iseq, body, use_value)?;
+        if rescue.is_empty() {
+            if !else_.is_nop() {
+                if use_value {
+                    self.gen_pop(iseq);
+                }
+                self.gen(globals, iseq, else_, use_value)?;
+            }
+            return Ok(());
+        }
+        let end = Codegen::current(iseq);
+        if !else_.is_nop() {
+            if use_value {
+                self.gen_pop(iseq);
+            }
+            self.gen(globals, iseq, else_, use_value)?;
+        }
+        let mut exit = vec![Codegen::gen_jmp(iseq)];
+        // When an exception occured, the exception object is pushed on the stack,
+        // and the execution starts from here.
+        let dest = Codegen::current(iseq);
+        for entry in rescue {
+            if entry.exception_list.is_empty() {
+                let id = globals.get_ident_id("StandardError");
+                self.gen_get_const_top(iseq, id);
+            } else {
+                for exception in &entry.exception_list {
+                    self.gen(globals, iseq, exception, true)?;
+                }
+            }
+            let len = std::cmp::max(1, entry.exception_list.len());
+            self.gen_rescue_check(iseq, len);
+            let next = self.gen_jmp_if_false(iseq);
+            match &entry.assign {
+                Some(lhs) => self.gen_assign(globals, iseq, lhs)?,
+                None => self.gen_pop(iseq),
+            }
+            let loop_depth = self.loop_stack.len();
+            self.context_mut().retry_dest.push(start);
+            self.context_mut().rescue_clauses.push(loop_depth);
+            self.gen(globals, iseq, &entry.body, use_value)?;
+            self.context_mut().rescue_clauses.pop().unwrap();
+            self.context_mut().retry_dest.pop().unwrap();
+            self.gen_restore_error_info(iseq);
+            exit.push(Codegen::gen_jmp(iseq));
+            Codegen::write_disp_from_cur(iseq, next);
+        }
+        // No rescue clause matched. Re-raise the exception.
+        self.gen_raise(iseq);
+        for src in exit {
+            Codegen::write_disp_from_cur(iseq, src);
+        }
+        self.context_mut().exception_table.push(ExceptionEntry::new(
+            ExceptionType::Rescue,
+            start,
+            end,
+            dest,
+        ));
+        Ok(())
+    }
+
+    /// Restore `$!` to the value before the rescue clause was entered.
+    fn gen_restore_error_info(&mut self, iseq: &mut ISeq) {
+        iseq.push(Inst::RESTORE_ERRINFO);
+    }
+
+    /// Expand ensure clauses for `break`, `next` and `return`.
+    /// If `loop_only` is true, only the clauses inside the innermost loop are expanded.
+    fn gen_ensure_clauses(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        loop_only: bool,
+    ) -> Result<(), RubyError> {
+        let loop_depth = self.loop_stack.len();
+        let exceptions = self.context().exceptions.clone();
+        for (i, ex) in exceptions.iter().enumerate().rev() {
+            if loop_only && ex.loop_depth != loop_depth {
+                break;
+            }
+            // `return` in the ensure clause must not expand the clause itself.
+            self.context_mut().exceptions.truncate(i);
+            self.gen(globals, iseq, &ex.ensure, false)?;
+        }
+        self.context_mut().exceptions = exceptions;
+        Ok(())
+    }
+
     /// Generate ISeq.
     pub fn gen_iseq(
         &mut self,
@@ -578,13 +822,7 @@ impl Codegen {
             self.method_stack.push(methodref)
         }
         let save_loc = self.loc;
-        let mut req_params = 0;
-        let mut opt_params = 0;
-        let mut rest_param = false;
-        let mut post_params = 0;
-        let mut block_param = false;
-        let mut param_ident = vec![];
-        let mut keyword_params = HashMap::new();
+        let mut params_info = ISeqParams::default();
         let mut iseq = ISeq::new();
 
         self.context_stack
@@ -592,16 +830,16 @@ impl Codegen {
         for (lvar_id, param) in params.iter().enumerate() {
             match &param.kind {
                 NodeKind::Param(id) => {
-                    param_ident.push(*id);
-                    req_params += 1;
+                    params_info.param_ident.push(*id);
+                    params_info.req_params += 1;
                 }
                 NodeKind::PostParam(id) => {
-                    param_ident.push(*id);
-                    post_params += 1;
+                    params_info.param_ident.push(*id);
+                    params_info.post_params += 1;
                 }
                 NodeKind::OptionalParam(id, default) => {
-                    param_ident.push(*id);
-                    opt_params += 1;
+                    params_info.param_ident.push(*id);
+                    params_info.opt_params += 1;
                     self.gen_check_local(&mut iseq, *id)?;
                     let src1 = self.gen_jmp_if_false(&mut iseq);
                     self.gen(globals, &mut iseq, default, true)?;
@@ -609,12 +847,14 @@ impl Codegen {
                     Codegen::write_disp_from_cur(&mut iseq, src1);
                 }
                 NodeKind::RestParam(id) => {
-                    param_ident.push(*id);
-                    rest_param = true;
+                    params_info.param_ident.push(*id);
+                    params_info.rest_param = true;
                 }
                 NodeKind::KeywordParam(id, default) => {
-                    param_ident.push(*id);
-                    keyword_params.insert(*id, LvarId::from_usize(lvar_id));
+                    params_info.param_ident.push(*id);
+                    params_info
+                        .keyword_params
+                        .insert(*id, LvarId::from_usize(lvar_id));
                     match &**default {
                         Some(default) => {
                             self.gen_check_local(&mut iseq, *id)?;
@@ -623,12 +863,16 @@ impl Codegen {
                             self.gen_set_local(&mut iseq, *id);
                             Codegen::write_disp_from_cur(&mut iseq, src1);
                         }
-                        None => {}
+                        None => params_info.required_keywords.push(*id),
                     }
                 }
+                NodeKind::KWRestParam(id) => {
+                    params_info.param_ident.push(*id);
+                    params_info.kwrest_param = Some(LvarId::from_usize(lvar_id));
+                }
                 NodeKind::BlockParam(id) => {
-                    param_ident.push(*id);
-                    block_param = true;
+                    params_info.param_ident.push(*id);
+                    params_info.block_param = true;
                 }
                 _ => return Err(self.error_syntax("Parameters should be identifier.", param.loc)),
             }
@@ -637,22 +881,18 @@ impl Codegen {
         self.gen(globals, &mut iseq, node, use_value)?;
         let context = self.context_stack.pop().unwrap();
         let iseq_sourcemap = context.iseq_sourcemap;
+        let exception_table = context.exception_table;
         self.gen_end(&mut iseq);
         self.loc = save_loc;
 
         let info = MethodInfo::RubyFunc {
             iseq: ISeqRef::new(ISeqInfo::new(
                 methodref,
-                req_params,
-                opt_params,
-                rest_param,
-                post_params,
-                block_param,
-                param_ident,
-                keyword_params,
+                params_info,
                 iseq,
                 lvar_collector.clone(),
                 iseq_sourcemap,
+                exception_table,
                 self.source_info,
                 match kind {
                     ContextKind::Block => ISeqKind::Block(*self.method_stack.last().unwrap()),
@@ -697,6 +937,148 @@ impl Codegen {
         Ok(methodref)
     }
 
+    /// Generate the code for `defined?(node)`, which pushes a description of `node` or nil.
+    /// `node` is not evaluated, except for receivers and scopes which are proved to be defined.
+    fn gen_defined(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        node: &Node,
+    ) -> Result<(), RubyError> {
+        match &node.kind {
+            NodeKind::Nil => self.gen_string(globals, iseq, "nil"),
+            NodeKind::SelfValue => self.gen_string(globals, iseq, "self"),
+            NodeKind::Bool(true) => self.gen_string(globals, iseq, "true"),
+            NodeKind::Bool(false) => self.gen_string(globals, iseq, "false"),
+            NodeKind::LocalVar(_) => self.gen_string(globals, iseq, "local-variable"),
+            NodeKind::Assign(..) | NodeKind::AssignOp(..) | NodeKind::MulAssign(..) => {
+                self.gen_string(globals, iseq, "assignment")
+            }
+            NodeKind::InstanceVar(id) => self.gen_defined_inst(iseq, Inst::DEFINED_IVAR, *id),
+            NodeKind::ClassVar(id) => self.gen_defined_inst(iseq, Inst::DEFINED_CVAR, *id),
+            NodeKind::GlobalVar(id) => self.gen_defined_inst(iseq, Inst::DEFINED_GVAR, *id),
+            NodeKind::Const {
+                id,
+                toplevel: false,
+            } => self.gen_defined_inst(iseq, Inst::DEFINED_CONST, *id),
+            NodeKind::Const { id, toplevel: true } => {
+                let object = globals.get_ident_id("Object");
+                self.gen_get_const_top(iseq, object);
+                self.gen_defined_inst(iseq, Inst::DEFINED_SCOPE, *id);
+            }
+            NodeKind::Scope(parent, id) => {
+                self.gen_defined_with_receiver(globals, iseq, parent, Inst::DEFINED_SCOPE, *id)?
+            }
+            NodeKind::Yield(_) => iseq.push(Inst::DEFINED_YIELD),
+            NodeKind::Super { .. } => iseq.push(Inst::DEFINED_SUPER),
+            NodeKind::Ident(id) => {
+                self.gen_push_self(iseq);
+                self.gen_defined_inst(iseq, Inst::DEFINED_METHOD, *id);
+            }
+            NodeKind::Send {
+                receiver, method, ..
+            } => match receiver.kind {
+                NodeKind::SelfValue => {
+                    self.gen_push_self(iseq);
+                    self.gen_defined_inst(iseq, Inst::DEFINED_METHOD, *method);
+                }
+                _ => self.gen_defined_with_receiver(
+                    globals,
+                    iseq,
+                    receiver,
+                    Inst::DEFINED_METHOD,
+                    *method,
+                )?,
+            },
+            NodeKind::BinOp(op, lhs, _) => match op.method_name() {
+                Some(name) => {
+                    let method = globals.get_ident_id(name);
+                    self.gen_defined_with_receiver(
+                        globals,
+                        iseq,
+                        lhs,
+                        Inst::DEFINED_METHOD,
+                        method,
+                    )?
+                }
+                None => self.gen_string(globals, iseq, "expression"),
+            },
+            _ => self.gen_string(globals, iseq, "expression"),
+        };
+        Ok(())
+    }
+
+    /// Generate `inst` which examines `id` of the value of `receiver`,
+    /// only when `receiver` itself is defined.
+    fn gen_defined_with_receiver(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        receiver: &Node,
+        inst: u8,
+        id: IdentId,
+    ) -> Result<(), RubyError> {
+        self.gen_defined(globals, iseq, receiver)?;
+        let src1 = self.gen_jmp_if_false(iseq);
+        // If evaluating the receiver raised a StandardError, the result is nil.
+        let start = self.gen_try(iseq);
+        self.gen(globals, iseq, receiver, true)?;
+        let end = Codegen::current(iseq);
+        self.gen_defined_inst(iseq, inst, id);
+        let src2 = Codegen::gen_jmp(iseq);
+        let dest = Codegen::current(iseq);
+        let error_id = globals.get_ident_id("StandardError");
+        self.gen_get_const_top(iseq, error_id);
+        self.gen_rescue_check(iseq, 1);
+        let next = self.gen_jmp_if_false(iseq);
+        self.gen_pop(iseq);
+        self.gen_restore_error_info(iseq);
+        let src3 = Codegen::gen_jmp(iseq);
+        Codegen::write_disp_from_cur(iseq, next);
+        self.gen_raise(iseq);
+        Codegen::write_disp_from_cur(iseq, src1);
+        Codegen::write_disp_from_cur(iseq, src3);
+        self.gen_push_nil(iseq);
+        Codegen::write_disp_from_cur(iseq, src2);
+        self.context_mut().exception_table.push(ExceptionEntry::new(
+            ExceptionType::Rescue,
+            start,
+            end,
+            dest,
+        ));
+        Ok(())
+    }
+
+    /// Generate keyword arguments as a Hash, and return true if any keyword argument exists.
+    fn gen_kw_args(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        send_args: &SendArgs,
+    ) -> Result<bool, RubyError> {
+        if send_args.kw_args.is_empty() && send_args.kw_splat.is_empty() {
+            return Ok(false);
+        }
+        for (id, default) in &send_args.kw_args {
+            self.gen_symbol(iseq, *id);
+            self.gen(globals, iseq, default, true)?;
+        }
+        self.gen_create_hash(iseq, send_args.kw_args.len());
+        // Merge double splat args as `splat.merge(hash)`.
+        // The latter splat and the literal keywords take precedence.
+        let merge = globals.get_ident_id("merge");
+        for splat in send_args.kw_splat.iter().rev() {
+            self.gen(globals, iseq, splat, true)?;
+            self.gen_send(globals, iseq, merge, 1, 0, None);
+        }
+        Ok(true)
+    }
+
+    fn gen_defined_inst(&mut self, iseq: &mut ISeq, inst: u8, id: IdentId) {
+        iseq.push(inst);
+        Codegen::push32(iseq, id.into());
+    }
+
     pub fn gen(
         &mut self,
         globals: &mut Globals,
@@ -864,6 +1246,12 @@ impl Codegen {
                     self.gen_pop(iseq)
                 };
             }
+            NodeKind::ClassVar(id) => {
+                self.gen_get_class_var(iseq, *id, node.loc);
+                if !use_value {
+                    self.gen_pop(iseq)
+                };
+            }
             NodeKind::BinOp(op, lhs, rhs) => {
                 let loc = self.loc;
                 match op {
@@ -987,16 +1375,16 @@ impl Codegen {
                         iseq.push(Inst::GT);
                     }
                     BinOp::Le => {
-                        self.gen(globals, iseq, rhs, true)?;
                         self.gen(globals, iseq, lhs, true)?;
+                        self.gen(globals, iseq, rhs, true)?;
                         self.save_loc(iseq, loc);
-                        iseq.push(Inst::GE);
+                        iseq.push(Inst::LE);
                     }
                     BinOp::Lt => {
-                        self.gen(globals, iseq, rhs, true)?;
                         self.gen(globals, iseq, lhs, true)?;
+                        self.gen(globals, iseq, rhs, true)?;
                         self.save_loc(iseq, loc);
-                        iseq.push(Inst::GT);
+                        iseq.push(Inst::LT);
                     }
                     BinOp::Cmp => {
                         self.gen(globals, iseq, rhs, true)?;
@@ -1154,18 +1542,47 @@ impl Codegen {
             }
             NodeKind::Begin {
                 body,
-                rescue: _,
-                else_: _,
+                rescue,
+                else_,
                 ensure,
             } => {
-                self.context_mut().exceptions.push(Exceptions::new());
-                self.gen(globals, iseq, body, use_value)?;
-                let exceptions = self.context_mut().exceptions.pop().unwrap();
-                for src in exceptions.entry {
+                if rescue.is_empty() && ensure.is_nop() {
+                    self.gen(globals, iseq, body, use_value)?;
+                    if !else_.is_nop() {
+                        if use_value {
+                            self.gen_pop(iseq);
+                        }
+                        self.gen(globals, iseq, else_, use_value)?;
+                    }
+                } else if ensure.is_nop() {
+                    let start = self.gen_try(iseq);
+                    self.gen_rescue(globals, iseq, start, body, rescue, else_, use_value)?;
+                } else {
+                    let start = self.gen_try(iseq);
+                    let loop_depth = self.loop_stack.len();
+                    self.context_mut()
+                        .exceptions
+                        .push(Exceptions::new((**ensure).clone(), loop_depth));
+                    self.gen_rescue(globals, iseq, start, body, rescue, else_, use_value)?;
+                    self.context_mut().exceptions.pop().unwrap();
+                    let end = Codegen::current(iseq);
+                    // Ensure clauses must not return value.
+                    self.gen(globals, iseq, ensure, false)?;
+                    let src = Codegen::gen_jmp(iseq);
+                    // When an error occured, the ensure clause is executed and the error is re-raised.
+                    let dest = Codegen::current(iseq);
+                    self.context_mut().error_ensures.push(loop_depth);
+                    self.gen(globals, iseq, ensure, false)?;
+                    self.context_mut().error_ensures.pop().unwrap();
+                    self.gen_ensure_end(iseq);
                     Codegen::write_disp_from_cur(iseq, src);
+                    self.context_mut().exception_table.push(ExceptionEntry::new(
+                        ExceptionType::Ensure,
+                        start,
+                        end,
+                        dest,
+                    ));
                 }
-                // Ensure clauses must not return value.
-                self.gen(globals, iseq, ensure, false)?;
             }
             NodeKind::Case { cond, when_, else_ } => {
                 let mut end = vec![];
@@ -1328,42 +1745,7 @@ impl Codegen {
                 ..
             } => {
                 let loc = self.loc;
-                for arg in &send_args.args {
-                    self.gen(globals, iseq, arg, true)?;
-                }
-                let kw_flag = send_args.kw_args.len() != 0;
-                if kw_flag {
-                    for (id, default) in &send_args.kw_args {
-                        self.gen_symbol(iseq, *id);
-                        self.gen(globals, iseq, default, true)?;
-                    }
-                    self.gen_create_hash(iseq, send_args.kw_args.len());
-                }
-                let mut block_flag = false;
-                let block_ref = match &send_args.block {
-                    Some(block) => match &block.kind {
-                        NodeKind::Proc { params, body, lvar } => {
-                            self.loop_stack.push(LoopInfo::new_top());
-                            let methodref = self.gen_iseq(
-                                globals,
-                                params,
-                                body,
-                                lvar,
-                                true,
-                                ContextKind::Block,
-                                None,
-                            )?;
-                            self.loop_stack.pop().unwrap();
-                            Some(methodref)
-                        }
-                        _ => {
-                            self.gen(globals, iseq, block, true)?;
-                            block_flag = true;
-                            None
-                        }
-                    },
-                    None => None,
-                };
+                let (flag, block_ref) = self.gen_send_args(globals, iseq, send_args)?;
                 if NodeKind::SelfValue == receiver.kind {
                     self.loc = loc;
                     self.gen_send_self(
@@ -1371,7 +1753,7 @@ impl Codegen {
                         iseq,
                         *method,
                         send_args.args.len(),
-                        create_flag(kw_flag, block_flag),
+                        flag,
                         block_ref,
                     );
                 } else {
@@ -1382,23 +1764,28 @@ impl Codegen {
                         iseq,
                         *method,
                         send_args.args.len(),
-                        create_flag(kw_flag, block_flag),
+                        flag,
                         block_ref,
                     );
                 };
                 if !use_value {
                     self.gen_pop(iseq)
                 };
-
-                /// Create flag for argument info.
-                /// 0b0011
-                ///     ||
-                ///     |+- 1: keyword args exists. 0: no keyword args,
-                ///     +-- 1: a block arg exists. 0: no block arg.
-                fn create_flag(kw_flag: bool, block_flag: bool) -> usize {
-                    (if kw_flag { 1usize } else { 0usize })
-                        + (if block_flag { 2usize } else { 0usize })
-                }
+            }
+            NodeKind::Super { send_args, zsuper } => {
+                let loc = self.loc;
+                let (flag, block_ref) = self.gen_send_args(globals, iseq, send_args)?;
+                self.loc = loc;
+                self.gen_super(iseq, send_args.args.len(), flag, *zsuper, block_ref);
+                if !use_value {
+                    self.gen_pop(iseq)
+                };
+            }
+            NodeKind::Defined(content) => {
+                self.gen_defined(globals, iseq, content)?;
+                if !use_value {
+                    self.gen_pop(iseq)
+                };
             }
             NodeKind::Yield(send_args) => {
                 //let loc = self.loc;
@@ -1436,6 +1823,31 @@ impl Codegen {
                     self.gen_symbol(iseq, *id);
                 };
             }
+            NodeKind::AliasMethod(new, old) => {
+                iseq.push(Inst::ALIAS_METHOD);
+                Codegen::push32(iseq, (*new).into());
+                Codegen::push32(iseq, (*old).into());
+                if use_value {
+                    iseq.push(Inst::PUSH_NIL);
+                };
+            }
+            NodeKind::AliasGlobalVar(new, old) => {
+                iseq.push(Inst::ALIAS_GVAR);
+                Codegen::push32(iseq, (*new).into());
+                Codegen::push32(iseq, (*old).into());
+                if use_value {
+                    iseq.push(Inst::PUSH_NIL);
+                };
+            }
+            NodeKind::Undef(ids) => {
+                for id in ids {
+                    iseq.push(Inst::UNDEF_METHOD);
+                    Codegen::push32(iseq, (*id).into());
+                }
+                if use_value {
+                    iseq.push(Inst::PUSH_NIL);
+                };
+            }
             NodeKind::SingletonMethodDef(singleton, id, params, body, lvar) => {
                 let methodref = self.gen_iseq(
                     globals,
@@ -1481,24 +1893,49 @@ impl Codegen {
                     self.gen_pop(iseq);
                 };
             }
+            NodeKind::SingletonClassDef {
+                singleton,
+                body,
+                lvar,
+            } => {
+                let loc = node.loc();
+                let methodref = self.gen_iseq(
+                    globals,
+                    &vec![],
+                    body,
+                    lvar,
+                    true,
+                    ContextKind::Method,
+                    None,
+                )?;
+                self.gen(globals, iseq, singleton, true)?;
+                self.save_loc(iseq, loc);
+                iseq.push(Inst::DEF_SCLASS);
+                Codegen::push32(iseq, methodref.into());
+                if !use_value {
+                    self.gen_pop(iseq);
+                };
+            }
             NodeKind::Return(val) => {
                 self.gen(globals, iseq, val, true)?;
                 // Call ensure clauses.
                 // Note ensure routine return no value.
-                match self.context_mut().exceptions.last_mut() {
-                    Some(ex) => {
-                        let src = Codegen::gen_jmp(iseq);
-                        ex.entry.push(src);
-                    }
-                    None => {
-                        if self.context().kind == ContextKind::Block {
-                            self.gen_method_return(iseq);
-                        } else {
-                            self.gen_return(iseq);
-                        }
-                    }
+                self.gen_ensure_clauses(globals, iseq, false)?;
+                if self.context().kind == ContextKind::Block {
+                    self.gen_method_return(iseq);
+                } else {
+                    self.gen_return(iseq);
                 }
             }
+            NodeKind::Retry => {
+                let loc = node.loc();
+                let dest = match self.context().retry_dest.last() {
+                    Some(dest) => *dest,
+                    None => return Err(self.error_syntax("Invalid retry.", loc.merge(self.loc))),
+                };
+                self.gen_restore_error_info(iseq);
+                self.gen_jmp_back(iseq, dest);
+            }
             NodeKind::Break(val) => {
                 let loc = node.loc();
                 if self.loop_stack.last().unwrap().state == LoopState::Top {
@@ -1506,6 +1943,7 @@ impl Codegen {
                     match self.context().kind {
                         ContextKind::Block => {
                             self.gen(globals, iseq, val, true)?;
+                            self.gen_ensure_clauses(globals, iseq, true)?;
                             self.gen_return(iseq);
                         }
                         ContextKind::Method => {
@@ -1521,6 +1959,8 @@ impl Codegen {
                 } else {
                     //In the case of inner of loops
                     self.gen(globals, iseq, val, true)?;
+                    self.gen_discard_errors(iseq);
+                    self.gen_ensure_clauses(globals, iseq, true)?;
                     let src = Codegen::gen_jmp(iseq);
                     let x = self.loop_stack.last_mut().unwrap();
                     x.escape.push(EscapeInfo::new(src, EscapeKind::Break));
@@ -1533,6 +1973,7 @@ impl Codegen {
                     match self.context_stack.last().unwrap().kind {
                         ContextKind::Block => {
                             self.gen(globals, iseq, val, true)?;
+                            self.gen_ensure_clauses(globals, iseq, true)?;
                             self.gen_end(iseq);
                         }
                         ContextKind::Method => {
@@ -1548,6 +1989,8 @@ impl Codegen {
                 } else {
                     //In the case of inner of loops
                     self.gen(globals, iseq, val, use_value)?;
+                    self.gen_discard_errors(iseq);
+                    self.gen_ensure_clauses(globals, iseq, true)?;
                     let src = Codegen::gen_jmp(iseq);
                     let x = self.loop_stack.last_mut().unwrap();
                     x.escape.push(EscapeInfo::new(src, EscapeKind::Next));
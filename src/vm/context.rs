@@ -8,6 +8,9 @@ pub struct Context {
     pub is_fiber: bool,
     pub self_value: Value,
     pub block: Option<MethodRef>,
+    /// The Proc object which `block` calls when the block was given by `&proc`.
+    /// The block does not keep the Proc alive, so the context does.
+    block_proc: Option<Value>,
     lvar_ary: [Value; LVAR_ARRAY_SIZE],
     lvar_vec: Vec<Value>,
     pub iseq_ref: ISeqRef,
@@ -16,6 +19,8 @@ pub struct Context {
     pub on_stack: bool,
     pub stack_len: usize,
     pub kind: ISeqKind,
+    /// The id of the lambda call, which is the target of `return` in the lambda and its blocks.
+    pub lambda_id: Option<usize>,
 }
 
 pub type ContextRef = Ref<Context>;
@@ -83,6 +88,7 @@ impl Context {
             is_fiber: false,
             self_value,
             block,
+            block_proc: None,
             lvar_ary: [Value::uninitialized(); LVAR_ARRAY_SIZE],
             lvar_vec,
             iseq_ref,
@@ -91,6 +97,7 @@ impl Context {
             on_stack: true,
             stack_len: 0,
             kind: iseq_ref.kind.clone(),
+            lambda_id: None,
         }
     }
 
@@ -100,15 +107,48 @@ impl Context {
         iseq: ISeqRef,
         args: &Args,
         outer: Option<ContextRef>,
+    ) -> Result<Self, RubyError> {
+        Context::from_args_core(vm, self_value, iseq, args, outer, !iseq.is_block())
+    }
+
+    /// Create a context for a lambda, which checks the number of arguments as a method does.
+    pub fn from_lambda_args(
+        vm: &mut VM,
+        self_value: Value,
+        iseq: ISeqRef,
+        args: &Args,
+        outer: Option<ContextRef>,
+    ) -> Result<Self, RubyError> {
+        Context::from_args_core(vm, self_value, iseq, args, outer, true)
+    }
+
+    fn from_args_core(
+        vm: &mut VM,
+        self_value: Value,
+        iseq: ISeqRef,
+        args: &Args,
+        outer: Option<ContextRef>,
+        check_arity: bool,
     ) -> Result<Self, RubyError> {
         let mut context = Context::new(self_value, args.block, iseq, outer);
+        if let Some(block) = args.block {
+            if let MethodInfo::ProcBlock { proc } = vm.globals.get_method_info(block) {
+                context.block_proc = Some(*proc);
+            }
+        }
         let params = &iseq.params;
-        let kw = if params.keyword_params.is_empty() {
-            args.kw_arg
-        } else {
+        // An empty keyword hash (e.g. `f(**{})`) is not passed.
+        let kw_arg = match args.kw_arg {
+            Some(kw_arg) if kw_arg.as_hash().map_or(false, |h| h.len() == 0) => None,
+            kw_arg => kw_arg,
+        };
+        // Keyword args are passed as a positional Hash when no keyword param exists.
+        let kw = if params.has_keyword_params() {
             None
+        } else {
+            kw_arg
         };
-        if !iseq.is_block() {
+        if check_arity {
             let len = args.len() + if kw.is_some() { 1 } else { 0 };
             let min = params.req_params + params.post_params;
             if params.rest_param {
@@ -117,51 +157,85 @@ impl Context {
                 vm.check_args_range(len, min, min + params.opt_params)?;
             }
         }
-        context.set_arguments(&vm.globals, args, kw);
-        match args.kw_arg {
-            Some(kw_arg) if kw.is_none() => {
-                let keyword = kw_arg.as_hash().unwrap();
-                for (k, v) in keyword.iter() {
-                    let id = k.as_symbol().unwrap();
-                    match params.keyword_params.get(&id) {
-                        Some(lvar) => {
-                            context[*lvar] = v;
-                        }
-                        None => return Err(vm.error_argument("Undefined keyword.")),
-                    };
-                }
-            }
-            _ => {}
-        };
+        context.set_arguments(&vm.globals, args, kw, !check_arity);
+        if params.has_keyword_params() {
+            context.set_keyword_arguments(vm, kw_arg)?;
+        }
         if let Some(id) = iseq.lvar.block_param() {
             context[id] = match args.block {
-                Some(block) => {
-                    let proc_context = vm.create_block_context(block)?;
-                    Value::procobj(&vm.globals, proc_context)
-                }
+                Some(block) => vm.create_proc(block)?,
                 None => Value::nil(),
             }
         }
         Ok(context)
     }
 
-    fn set_arguments(&mut self, globals: &Globals, args: &Args, kw_arg: Option<Value>) {
+    /// Bind keyword arguments to keyword params and a keyword rest param.
+    /// Return ArgumentError if required keywords are missing or unknown keywords are given.
+    fn set_keyword_arguments(
+        &mut self,
+        vm: &mut VM,
+        kw_arg: Option<Value>,
+    ) -> Result<(), RubyError> {
+        let iseq = self.iseq_ref;
+        let params = &iseq.params;
+        let mut kwrest = OrderedMap::new();
+        let mut unknown = vec![];
+        if let Some(kw_arg) = kw_arg {
+            let keyword = kw_arg.as_hash().unwrap();
+            for (k, v) in keyword.iter() {
+                match k.as_symbol().and_then(|id| params.keyword_params.get(&id)) {
+                    Some(lvar) => self[*lvar] = v,
+                    None if params.kwrest_param.is_some() => {
+                        kwrest.insert(HashKey(k), v);
+                    }
+                    None => unknown.push(k),
+                }
+            }
+        }
+        if !unknown.is_empty() {
+            return Err(keyword_error(vm, "unknown", unknown));
+        }
+        let missing: Vec<Value> = params
+            .required_keywords
+            .iter()
+            .filter(|id| self[params.keyword_params[id]].is_uninitialized())
+            .map(|id| Value::symbol(*id))
+            .collect();
+        if !missing.is_empty() {
+            return Err(keyword_error(vm, "missing", missing));
+        }
+        if let Some(lvar) = params.kwrest_param {
+            self[lvar] = Value::hash_from(&vm.globals, kwrest);
+        }
+        return Ok(());
+
+        fn keyword_error(vm: &mut VM, kind: &str, keys: Vec<Value>) -> RubyError {
+            let keys: Vec<String> = keys.iter().map(|k| vm.val_inspect(*k)).collect();
+            let plural = if keys.len() == 1 { "" } else { "s" };
+            vm.error_argument(format!("{} keyword{}: {}", kind, plural, keys.join(", ")))
+        }
+    }
+
+    /// Set `args` to the params.
+    /// If `expand_array` is true, a single Array argument is expanded to multiple params.
+    fn set_arguments(
+        &mut self,
+        globals: &Globals,
+        args: &Args,
+        kw_arg: Option<Value>,
+        expand_array: bool,
+    ) {
         let iseq = self.iseq_ref;
         let req_len = iseq.params.req_params;
         let post_len = iseq.params.post_params;
 
-        match self.kind {
-            ISeqKind::Block(_) if args.len() == 1 && req_len + post_len > 1 => {
-                match args[0].as_array() {
-                    Some(ary) => {
-                        let args = &ary.elements;
-                        self.fill_arguments(globals, args, args.len(), iseq, kw_arg);
-                        return;
-                    }
-                    _ => {}
-                }
+        if expand_array && args.len() == 1 && req_len + post_len > 1 {
+            if let Some(ary) = args[0].as_array() {
+                let args = &ary.elements;
+                self.fill_arguments(globals, args, args.len(), iseq, kw_arg);
+                return;
             }
-            _ => {}
         }
 
         self.fill_arguments(globals, args, args.len(), iseq, kw_arg);
@@ -228,6 +302,21 @@ impl Context {
     }
 }
 
+impl GC for Context {
+    fn mark(&self, alloc: &mut Allocator) {
+        self.self_value.mark(alloc);
+        if let Some(proc) = self.block_proc {
+            proc.mark(alloc);
+        }
+        for val in self.lvar_ary.iter().chain(self.lvar_vec.iter()) {
+            val.mark(alloc);
+        }
+        if let Some(outer) = self.outer {
+            outer.mark(alloc);
+        }
+    }
+}
+
 impl ContextRef {
     pub fn from(
         self_value: Value,
@@ -3,49 +3,184 @@ use crate::*;
 
 #[cfg(feature = "perf")]
 use super::perf::*;
+use num::BigInt;
+use std::cell::Cell;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::mpsc::{Receiver, SyncSender};
 use vm_inst::*;
 
 pub type ValueTable = HashMap<IdentId, Value>;
 pub type VMResult = Result<Value, RubyError>;
 
+thread_local!(
+    /// The VM which is running on the current thread, which is set by `CurrentVM`.
+    /// Hash calls `hash` and `eql?` of the keys with this VM.
+    static CURRENT_VM: Cell<Option<VMRef>> = Cell::new(None);
+);
+
+/// A guard which makes a VM the current VM of the thread while it is alive.
+/// The previous one is restored when the guard is dropped.
+pub struct CurrentVM {
+    prev: Option<VMRef>,
+}
+
+impl CurrentVM {
+    pub fn enter(vm: VMRef) -> Self {
+        let prev = CURRENT_VM.with(|current| current.replace(Some(vm)));
+        CurrentVM { prev }
+    }
+}
+
+impl Drop for CurrentVM {
+    fn drop(&mut self) {
+        CURRENT_VM.with(|current| current.set(self.prev));
+    }
+}
+
 #[derive(Debug)]
 pub struct VM {
     // Global info
     pub globals: GlobalsRef,
     pub root_path: Vec<PathBuf>,
     // VM state
-    fiber_state: FiberState,
     exec_context: Vec<ContextRef>,
     class_context: Vec<(Value, DefineMode)>,
     exec_stack: Vec<Value>,
-    exception: bool,
+    /// Errors caught by ensure clauses, which are re-raised at the end of the clauses.
+    exception_stack: Vec<RubyError>,
+    /// States saved when rescue clauses were entered, which are restored when the clauses are left.
+    errinfo_stack: Vec<RescueInfo>,
+    /// Values which are referred only from native code, and must be preserved from GC.
+    /// Values pushed in a builtin method are discarded when the method returns.
+    temp_stack: Vec<Value>,
+    /// Closures given to `eval_with_native_block`, which are called by the native block.
+    native_blocks: Vec<NativeBlockFrame>,
+    /// The first error raised in `hash` or `eql?` of a Hash key, which is reported by the Hash operation.
+    pub hash_key_error: Option<RubyError>,
     pc: usize,
-    pub channel: Option<(SyncSender<VMResult>, Receiver<usize>)>,
+    /// The Fiber object which runs on this VM. None for the main VM until Fiber.current is called.
+    pub fiber: Option<Value>,
+    /// The VM which resumed this VM as a fiber.
+    pub parent_vm: Option<VMRef>,
     #[cfg(feature = "perf")]
     perf: Perf,
 }
 
 pub type VMRef = Ref<VM>;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum FiberState {
-    Created,
-    Running,
-    Dead,
+/// A block implemented in Rust, which is called with each value yielded to the block,
+/// and returns the value of the block. The iteration is stopped when the block returns None.
+pub type NativeBlock<'a> = dyn FnMut(&mut VM, Value) -> Result<Option<Value>, RubyError> + 'a;
+
+#[derive(Debug)]
+struct NativeBlockFrame {
+    block: *mut NativeBlock<'static>,
+    /// The context which called `eval_with_native_block`, which is the current context of the block.
+    context: ContextRef,
+    /// Values pushed on the temporary stack by the block, which are preserved
+    /// until `eval_with_native_block` returns.
+    temp_stack: Vec<Value>,
+    /// True if `break` occured in a block called by the closure.
+    is_break: bool,
+}
+
+/// The state saved when a rescue clause is entered.
+#[derive(Debug)]
+struct RescueInfo {
+    /// The value of `$!` before the clause was entered.
+    errinfo: Value,
+    /// The exception object of the rescued error.
+    exception: Value,
+    /// The rescued error, which is re-raised as it is when no rescue clause matches.
+    err: RubyError,
+}
+
+/// The lengths of the stacks of VM at the start of a protected region,
+/// which are restored when the control is transferred to the handler of the region.
+#[derive(Debug, Clone, Copy)]
+struct StackMark {
+    /// The position of TRY at the start of the protected region.
+    pc: usize,
+    stack_len: usize,
+    exception_len: usize,
+    errinfo_len: usize,
+}
+
+impl StackMark {
+    fn new(pc: usize, vm: &VM) -> Self {
+        StackMark {
+            pc,
+            stack_len: vm.exec_stack.len(),
+            exception_len: vm.exception_stack.len(),
+            errinfo_len: vm.errinfo_stack.len(),
+        }
+    }
+}
+
+impl GC for VM {
+    fn mark(&self, alloc: &mut Allocator) {
+        for val in &self.exec_stack {
+            val.mark(alloc);
+        }
+        for context in &self.exec_context {
+            context.mark(alloc);
+        }
+        for (class, _) in &self.class_context {
+            class.mark(alloc);
+        }
+        for err in &self.exception_stack {
+            if let RubyErrorKind::Exception(exception, _) = err.kind {
+                exception.mark(alloc);
+            }
+        }
+        for info in &self.errinfo_stack {
+            info.errinfo.mark(alloc);
+            info.exception.mark(alloc);
+        }
+        for val in &self.temp_stack {
+            val.mark(alloc);
+        }
+        for frame in &self.native_blocks {
+            for val in &frame.temp_stack {
+                val.mark(alloc);
+            }
+        }
+        if let Some(fiber) = self.fiber {
+            fiber.mark(alloc);
+        }
+    }
+}
+
+/// Roots for GC: Globals, the state of the current VM, and the states of the VMs
+/// which resumed it as a fiber.
+/// The states of suspended fiber VMs are marked via Fiber objects.
+struct VMRoot<'a>(&'a VM);
+
+impl<'a> GC for VMRoot<'a> {
+    fn mark(&self, alloc: &mut Allocator) {
+        self.0.globals.mark(alloc);
+        self.0.mark(alloc);
+        let mut parent_vm = self.0.parent_vm;
+        while let Some(vm) = parent_vm {
+            vm.mark(alloc);
+            parent_vm = vm.parent_vm;
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct DefineMode {
     module_function: bool,
+    /// Visibility of methods defined without explicit visibility.
+    visibility: Visibility,
 }
 
 impl DefineMode {
     pub fn default() -> Self {
         DefineMode {
             module_function: false,
+            visibility: Visibility::Public,
         }
     }
 }
@@ -84,6 +219,7 @@ impl VM {
         set_builtin_class!("Proc", procobj);
         set_builtin_class!("Range", range);
         set_builtin_class!("String", string);
+        set_builtin_class!("Symbol", symbol);
         set_builtin_class!("Hash", hash);
         set_builtin_class!("Method", method);
         set_builtin_class!("Regexp", regexp);
@@ -94,19 +230,23 @@ impl VM {
         set_class!("File", file::init_file(&mut globals));
         set_class!("Process", process::init_process(&mut globals));
         set_class!("Struct", structobj::init_struct(&mut globals));
-        set_class!("StandardError", Value::class(&globals, globals.class_class));
-        set_class!("RuntimeError", errorobj::init_error(&mut globals));
+        set_class!("GC", gc::init_gc(&mut globals));
+        globals.builtins.exception = errorobj::init_error(&mut globals);
 
         let vm = VM {
             globals: GlobalsRef::new(globals),
             root_path: vec![],
-            fiber_state: FiberState::Created,
             class_context: vec![(Value::nil(), DefineMode::default())],
             exec_context: vec![],
             exec_stack: vec![],
-            exception: false,
+            exception_stack: vec![],
+            errinfo_stack: vec![],
+            temp_stack: vec![],
+            native_blocks: vec![],
+            hash_key_error: None,
             pc: 0,
-            channel: None,
+            fiber: None,
+            parent_vm: None,
             #[cfg(feature = "perf")]
             perf: Perf::new(),
         };
@@ -114,17 +254,21 @@ impl VM {
         vm
     }
 
-    pub fn dup_fiber(&self, tx: SyncSender<VMResult>, rx: Receiver<usize>) -> Self {
+    pub fn dup_fiber(&self) -> Self {
         VM {
             globals: self.globals.clone(),
             root_path: self.root_path.clone(),
-            fiber_state: FiberState::Created,
             exec_context: vec![],
             class_context: self.class_context.clone(),
             exec_stack: vec![],
-            exception: false,
+            exception_stack: vec![],
+            errinfo_stack: vec![],
+            temp_stack: vec![],
+            native_blocks: vec![],
+            hash_key_error: None,
             pc: 0,
-            channel: Some((tx, rx)),
+            fiber: None,
+            parent_vm: None,
             #[cfg(feature = "perf")]
             perf: self.perf.clone(),
         }
@@ -146,22 +290,6 @@ impl VM {
         self.context().iseq_ref.source_info
     }
 
-    pub fn fiberstate_created(&mut self) {
-        self.fiber_state = FiberState::Created;
-    }
-
-    pub fn fiberstate_running(&mut self) {
-        self.fiber_state = FiberState::Running;
-    }
-
-    pub fn fiberstate_dead(&mut self) {
-        self.fiber_state = FiberState::Dead;
-    }
-
-    pub fn fiberstate(&self) -> FiberState {
-        self.fiber_state
-    }
-
     pub fn stack_push(&mut self, val: Value) {
         self.exec_stack.push(val)
     }
@@ -222,6 +350,14 @@ impl VM {
         self.class_context.last_mut().unwrap().1.module_function = flag;
     }
 
+    pub fn default_visibility(&self) -> Visibility {
+        self.define_mode().visibility
+    }
+
+    pub fn set_default_visibility(&mut self, visibility: Visibility) {
+        self.class_context.last_mut().unwrap().1.visibility = visibility;
+    }
+
     pub fn get_pc(&mut self) -> usize {
         self.pc
     }
@@ -327,14 +463,36 @@ impl VM {
         Ok(method)
     }
 
+    /// Get the VM which is running on the current thread.
+    pub fn current() -> Option<VMRef> {
+        CURRENT_VM.with(|vm| vm.get())
+    }
+
     pub fn run(&mut self, path: PathBuf, program: &str, self_value: Option<Value>) -> VMResult {
+        let _current = CurrentVM::enter(VMRef::from_ref(self));
+        // `at_exit` handlers are executed only at the end of the main program, not of required files.
+        let is_main = self.exec_context.is_empty();
         let method = self.parse_program(path, program)?;
         let self_value = match self_value {
             Some(val) => val,
             None => self.globals.main_object,
         };
         let arg = Args::new0();
-        let val = self.eval_send(method, self_value, &arg)?;
+        let res = self.eval_send(method, self_value, &arg);
+        let val = if is_main {
+            // The result is preserved while the handlers run, and the error of the main program takes precedence.
+            let len = self.temp_stack.len();
+            if let Ok(val) = &res {
+                self.temp_push(*val);
+            }
+            let at_exit = self.exec_at_exit();
+            self.temp_stack.truncate(len);
+            let val = res?;
+            at_exit?;
+            val
+        } else {
+            res?
+        };
         #[cfg(feature = "perf")]
         {
             self.perf.get_perf(Perf::INVALID);
@@ -350,11 +508,31 @@ impl VM {
         Ok(val)
     }
 
+    /// Execute the procs registered by `at_exit` in the reverse order of registration.
+    /// Every proc is executed even if some of them raise an error, and the first error is returned.
+    pub fn exec_at_exit(&mut self) -> Result<(), RubyError> {
+        let _current = CurrentVM::enter(VMRef::from_ref(self));
+        let mut res = Ok(());
+        while let Some(proc_obj) = self.globals.at_exit_procs.pop() {
+            let len = self.temp_stack.len();
+            self.temp_push(proc_obj);
+            let val = self.eval_proc(proc_obj, &Args::new0());
+            self.temp_stack.truncate(len);
+            if let Err(err) = val {
+                if res.is_ok() {
+                    res = Err(err);
+                }
+            }
+        }
+        res
+    }
+
     pub fn run_repl(&mut self, result: &ParseResult, mut context: ContextRef) -> VMResult {
         #[cfg(feature = "perf")]
         {
             self.perf.set_prev_inst(Perf::CODEGEN);
         }
+        let _current = CurrentVM::enter(VMRef::from_ref(self));
         self.globals.ident_table = result.ident_table.clone();
         let methodref = Codegen::new(result.source_info).gen_iseq(
             &mut self.globals,
@@ -392,30 +570,7 @@ macro_rules! try_err {
         match $eval {
             Ok(val) => $self.stack_push(val),
             Err(err) if err.kind == RubyErrorKind::BlockReturn => {}
-            Err(mut err) => {
-                let m = $self.context().iseq_ref.method;
-                let res = if RubyErrorKind::MethodReturn(m) == err.kind {
-                    let result = $self.stack_pop();
-                    let prev_len = $self.context().stack_len;
-                    $self.exec_stack.truncate(prev_len);
-                    $self.unwind_context(&mut err);
-                    #[cfg(feature = "trace")]
-                    {
-                        println!("<--- METHOD_RETURN Ok({})", $self.val_inspect(result),);
-                    }
-                    Ok(result)
-                } else {
-                    $self.unwind_context(&mut err);
-                    #[cfg(feature = "trace")]
-                    {
-                        println!("<--- Err({:?})", err.kind);
-                    }
-                    Err(err)
-                };
-                $self.fiberstate_dead();
-                $self.fiber_send_to_parent(res.clone());
-                return res;
-            }
+            Err(err) => return Err(err),
         };
     };
 }
@@ -437,6 +592,93 @@ impl VM {
         };
         self.context_push(context);
         self.pc = context.pc;
+        self.gc();
+        let context_mark = StackMark::new(0, self);
+        // The stack lengths at the start of each protected region.
+        let mut stack_marks: Vec<StackMark> = vec![];
+        loop {
+            let mut err = match self.run_context_main(context, &mut stack_marks) {
+                Ok(res) => {
+                    // Discard the errors left by ensure clauses which were escaped by `return`,
+                    // and restore `$!` of rescue clauses escaped likewise.
+                    self.exception_stack.truncate(context_mark.exception_len);
+                    self.truncate_errinfo(context_mark.errinfo_len);
+                    return res;
+                }
+                Err(err) => err,
+            };
+            // An error occured in the current context.
+            let handler = self.get_exception_handler(context.iseq_ref, &err);
+            let mark = match &handler {
+                Some(entry) => match stack_marks
+                    .iter()
+                    .find(|mark| mark.pc == entry.start.to_usize())
+                {
+                    Some(mark) => *mark,
+                    None => context_mark,
+                },
+                None => context_mark,
+            };
+            // Discard values on the stack, except the return value of MethodReturn and LambdaReturn.
+            if let RubyErrorKind::MethodReturn(_) | RubyErrorKind::LambdaReturn(_) = err.kind {
+                let result = self.stack_pop();
+                self.exec_stack.truncate(mark.stack_len);
+                self.stack_push(result);
+            } else {
+                self.exec_stack.truncate(mark.stack_len);
+            }
+            // Discard the errors of ensure clauses in the protected region, which were left
+            // when the error occured in the clauses.
+            self.exception_stack.truncate(mark.exception_len);
+            self.truncate_errinfo(mark.errinfo_len);
+            if let Some(entry) = handler {
+                match entry.ty {
+                    ExceptionType::Rescue => {
+                        let exception = self.exception_from_error(&err);
+                        let id = self.globals.get_ident_id("$!");
+                        let errinfo = self.get_global_var(id);
+                        self.errinfo_stack.push(RescueInfo {
+                            errinfo,
+                            exception,
+                            err,
+                        });
+                        self.set_global_var(id, exception);
+                        self.stack_push(exception);
+                    }
+                    ExceptionType::Ensure => self.exception_stack.push(err),
+                }
+                self.pc = entry.dest.to_usize();
+                continue;
+            }
+            let res = if RubyErrorKind::MethodReturn(context.iseq_ref.method) == err.kind {
+                let result = self.stack_pop();
+                self.unwind_context(&mut err);
+                #[cfg(feature = "trace")]
+                {
+                    println!("<--- METHOD_RETURN Ok({})", self.val_inspect(result),);
+                }
+                Ok(result)
+            } else {
+                self.unwind_context(&mut err);
+                #[cfg(feature = "trace")]
+                {
+                    println!("<--- Err({:?})", err.kind);
+                }
+                Err(err)
+            };
+            return res;
+        }
+    }
+
+    /// Execute instructions of `context` until the context finishes or an error occurs.
+    ///
+    /// Return Ok(VMResult) when the context finished and was popped from the context stack.
+    /// Return Err(RubyError) when an error occured, leaving the context on the context stack.
+    fn run_context_main(
+        &mut self,
+        context: ContextRef,
+        stack_marks: &mut Vec<StackMark>,
+    ) -> Result<VMResult, RubyError> {
         let iseq = &context.iseq_ref.iseq;
         let mut self_oref = context.self_value.as_object();
         loop {
@@ -458,11 +700,6 @@ impl VM {
                     // reached the end of the method or block.
                     // - the end of the method or block.
                     // - `next` in block AND outer of loops.
-                    if self.exec_context.len() == 1 {
-                        // if in the final context, the fiber becomes DEAD.
-                        self.fiberstate_dead();
-                        self.fiber_send_to_parent(Err(self.error_fiber("Dead fiber called.")));
-                    };
                     let _context = self.context_pop().unwrap();
                     let val = self.stack_pop();
                     #[cfg(feature = "trace")]
@@ -476,7 +713,7 @@ impl VM {
                     if !self.exec_context.is_empty() {
                         self.pc = self.context().pc;
                     };
-                    return Ok(val);
+                    return Ok(Ok(val));
                 }
                 Inst::RETURN => {
                     // 'Inst::RETURN' is executed.
@@ -504,14 +741,17 @@ impl VM {
                     if !self.exec_context.is_empty() {
                         self.pc = self.context().pc;
                     }
-                    return res;
+                    return Ok(res);
                 }
                 Inst::MRETURN => {
                     // 'METHOD_RETURN' is executed.
                     // - `return` in block
-                    let res = if let ISeqKind::Block(method) = context.kind {
-                        // exit with Err(METHOD_RETURN).
-                        let err = self.error_method_return(method);
+                    let res = if let ISeqKind::Block(method) = context.iseq_ref.kind {
+                        // exit with Err(METHOD_RETURN), or Err(LAMBDA_RETURN) if the block is in a lambda.
+                        let err = match self.enclosing_lambda(self.context()) {
+                            Some(id) => self.error_lambda_return(id),
+                            None => self.error_method_return(method),
+                        };
                         #[cfg(feature = "trace")]
                         {
                             println!("<--- Err({:?})", err.kind);
@@ -524,7 +764,7 @@ impl VM {
                     if !self.exec_context.is_empty() {
                         self.pc = self.context().pc;
                     }
-                    return res;
+                    return Ok(res);
                 }
                 Inst::PUSH_NIL => {
                     self.stack_push(Value::nil());
@@ -659,15 +899,15 @@ impl VM {
                     self.pc += 1;
                 }
                 Inst::EQ => {
-                    let lhs = self.stack_pop();
                     let rhs = self.stack_pop();
+                    let lhs = self.stack_pop();
                     let val = Value::bool(self.eval_eq(rhs, lhs)?);
                     self.stack_push(val);
                     self.pc += 1;
                 }
                 Inst::NE => {
-                    let lhs = self.stack_pop();
                     let rhs = self.stack_pop();
+                    let lhs = self.stack_pop();
                     let val = Value::bool(!self.eval_eq(rhs, lhs)?);
                     self.stack_push(val);
                     self.pc += 1;
@@ -694,6 +934,20 @@ impl VM {
                     self.stack_push(val);
                     self.pc += 1;
                 }
+                Inst::LT => {
+                    let rhs = self.stack_pop();
+                    let lhs = self.stack_pop();
+                    let val = self.eval_lt(rhs, lhs)?;
+                    self.stack_push(val);
+                    self.pc += 1;
+                }
+                Inst::LE => {
+                    let rhs = self.stack_pop();
+                    let lhs = self.stack_pop();
+                    let val = self.eval_le(rhs, lhs)?;
+                    self.stack_push(val);
+                    self.pc += 1;
+                }
                 Inst::CMP => {
                     let lhs = self.stack_pop();
                     let rhs = self.stack_pop();
@@ -743,6 +997,57 @@ impl VM {
                     self.stack_push(Value::bool(val));
                     self.pc += 9;
                 }
+                Inst::DEFINED_IVAR => {
+                    let id = self.read_id(iseq, 1);
+                    let defined = self_oref.get_var(id).is_some();
+                    self.push_defined(defined, "instance-variable");
+                    self.pc += 5;
+                }
+                Inst::DEFINED_GVAR => {
+                    let id = self.resolve_global_alias(self.read_id(iseq, 1));
+                    let defined = self.globals.global_var.contains_key(&id);
+                    self.push_defined(defined, "global-variable");
+                    self.pc += 5;
+                }
+                Inst::DEFINED_CVAR => {
+                    let id = self.read_id(iseq, 1);
+                    let class = self.class_var_base().as_module().unwrap();
+                    let defined = class.class_var_owner(id).is_some();
+                    self.push_defined(defined, "class variable");
+                    self.pc += 5;
+                }
+                Inst::DEFINED_CONST => {
+                    let id = self.read_id(iseq, 1);
+                    let defined = self.get_env_const(id).is_some()
+                        || self.get_super_const(self.class(), id).is_ok();
+                    self.push_defined(defined, "constant");
+                    self.pc += 5;
+                }
+                Inst::DEFINED_SCOPE => {
+                    let parent = self.stack_pop();
+                    let id = self.read_id(iseq, 1);
+                    let defined =
+                        parent.as_module().is_some() && self.get_super_const(parent, id).is_ok();
+                    self.push_defined(defined, "constant");
+                    self.pc += 5;
+                }
+                Inst::DEFINED_METHOD => {
+                    let receiver = self.stack_pop();
+                    let id = self.read_id(iseq, 1);
+                    let defined = self.get_method(receiver, id).is_ok();
+                    self.push_defined(defined, "method");
+                    self.pc += 5;
+                }
+                Inst::DEFINED_YIELD => {
+                    let defined = self.get_method_context().block.is_some();
+                    self.push_defined(defined, "yield");
+                    self.pc += 1;
+                }
+                Inst::DEFINED_SUPER => {
+                    let defined = self.get_super_method().is_some();
+                    self.push_defined(defined, "super");
+                    self.pc += 1;
+                }
                 Inst::SET_CONST => {
                     let id = self.read_id(iseq, 1);
                     let mut parent = match self.stack_pop() {
@@ -759,6 +1064,7 @@ impl VM {
                         None => {}
                     }
                     parent.set_var(id, val);
+                    self.call_hook(parent, "const_added", Value::symbol(id))?;
                     self.pc += 5;
                 }
                 Inst::GET_CONST => {
@@ -780,6 +1086,16 @@ impl VM {
                 Inst::GET_SCOPE => {
                     let parent = self.stack_pop();
                     let id = self.read_id(iseq, 1);
+                    if let Some(class) = parent.as_module() {
+                        if class.private_constants.contains(&id) {
+                            let parent = self.val_inspect(parent);
+                            let name = self.globals.get_ident_name(id);
+                            return Err(self.error_name(format!(
+                                "private constant {}::{} referenced",
+                                parent, name
+                            )));
+                        }
+                    }
                     let val = self.get_super_const(parent, id)?;
                     self.stack_push(val);
                     self.pc += 5;
@@ -827,6 +1143,20 @@ impl VM {
                     self.stack_push(val);
                     self.pc += 5;
                 }
+                Inst::SET_CVAR => {
+                    let var_id = self.read_id(iseq, 1);
+                    let new_val = self.stack_pop();
+                    let class = self.class_var_base();
+                    self.set_class_var(class, var_id, new_val);
+                    self.pc += 5;
+                }
+                Inst::GET_CVAR => {
+                    let var_id = self.read_id(iseq, 1);
+                    let class = self.class_var_base();
+                    let val = self.get_class_var(class, var_id)?;
+                    self.stack_push(val);
+                    self.pc += 5;
+                }
                 Inst::SET_INDEX => {
                     let arg_num = self.read_usize(iseq, 1);
                     let mut args = self.pop_args_to_ary(arg_num);
@@ -839,11 +1169,17 @@ impl VM {
                                     args.push(val);
                                     aref.set_elem(self, &args)?;
                                 }
-                                ObjKind::Hash(mut href) => href.insert(args[0], val),
-                                _ => return Err(self.error_undefined_method("[]=", receiver)),
+                                ObjKind::Hash(mut href) => href.insert(args[0], val)?,
+                                _ => {
+                                    args.push(val);
+                                    self.fallback_to_index_method(receiver, "[]=", &args)?;
+                                }
                             };
                         }
-                        None => return Err(self.error_undefined_method("[]=", receiver)),
+                        None => {
+                            args.push(val);
+                            self.fallback_to_index_method(receiver, "[]=", &args)?;
+                        }
                     }
 
                     self.pc += 5;
@@ -858,23 +1194,15 @@ impl VM {
                             ObjKind::Array(aref) => aref.get_elem(self, &args)?,
                             ObjKind::Hash(href) => {
                                 self.check_args_range(arg_num, 1, 1)?;
-                                match href.get(&args[0]) {
-                                    Some(val) => *val,
+                                match href.get(&args[0])? {
+                                    Some(val) => val,
                                     None => Value::nil(),
                                 }
                             }
                             ObjKind::Method(mref) => {
                                 self.eval_send(mref.method, mref.receiver, &args)?
                             }
-                            _ => {
-                                let id = self.globals.get_ident_id("[]");
-                                match self.get_method(receiver, id) {
-                                    Ok(mref) => self.eval_send(mref, receiver, &args)?,
-                                    Err(_) => {
-                                        return Err(self.error_undefined_method("[]", receiver))
-                                    }
-                                }
-                            }
+                            _ => self.fallback_to_index_method(receiver, "[]", &args)?,
                         },
                         None if receiver.is_packed_fixnum() => {
                             let i = receiver.as_packed_fixnum();
@@ -887,7 +1215,7 @@ impl VM {
                             };
                             Value::fixnum(val)
                         }
-                        _ => return Err(self.error_undefined_method("[]", receiver)),
+                        _ => self.fallback_to_index_method(receiver, "[]", &args)?,
                     };
                     self.stack_push(val);
                     self.pc += 5;
@@ -918,14 +1246,15 @@ impl VM {
                     self.pc += 5;
                 }
                 Inst::CREATE_PROC => {
+                    // CREATE_PROC is emitted only for lambda literals.
                     let method = self.read_methodref(iseq, 1);
-                    let proc_obj = self.create_proc(method)?;
+                    let proc_obj = self.create_lambda(method)?;
                     self.stack_push(proc_obj);
                     self.pc += 5;
                 }
                 Inst::CREATE_HASH => {
                     let arg_num = self.read_usize(iseq, 1);
-                    let key_value = self.pop_key_value_pair(arg_num);
+                    let key_value = self.pop_key_value_pair(arg_num)?;
                     let hash = Value::hash(&self.globals, HashRef::from(key_value));
                     self.stack_push(hash);
                     self.pc += 5;
@@ -939,6 +1268,9 @@ impl VM {
                 Inst::JMP => {
                     let disp = self.read_disp(iseq, 1);
                     self.jump_pc(5, disp);
+                    if disp < 0 {
+                        self.gc();
+                    }
                 }
                 Inst::JMP_IF_FALSE => {
                     let val = self.stack_pop();
@@ -960,18 +1292,71 @@ impl VM {
                 }
                 Inst::SEND => {
                     let receiver = self.stack_pop();
-                    try_err!(self, self.vm_send(iseq, receiver));
+                    try_err!(self, self.vm_send(iseq, receiver, false));
                     self.pc += 17;
                 }
                 Inst::SEND_SELF => {
                     let receiver = context.self_value;
-                    try_err!(self, self.vm_send(iseq, receiver));
+                    try_err!(self, self.vm_send(iseq, receiver, true));
                     self.pc += 17;
                 }
+                Inst::SUPER => {
+                    try_err!(self, self.vm_super(iseq));
+                    self.pc += 9;
+                }
                 Inst::YIELD => {
                     try_err!(self, self.eval_yield(iseq));
                     self.pc += 5;
                 }
+                Inst::RESCUE => {
+                    let len = self.read32(iseq, 1) as usize;
+                    let stack_len = self.exec_stack.len();
+                    let exception = self.exec_stack[stack_len - len - 1];
+                    let mut res = false;
+                    for class in self.exec_stack.split_off(stack_len - len) {
+                        if class.as_module().is_none() {
+                            return Err(
+                                self.error_type("class or module required for rescue clause")
+                            );
+                        }
+                        if self.kind_of(exception, class) {
+                            res = true;
+                            break;
+                        }
+                    }
+                    self.stack_push(Value::bool(res));
+                    self.pc += 5;
+                }
+                Inst::RAISE => {
+                    // No rescue clause matched. Re-raise the rescued error with its original location.
+                    let exception = self.stack_pop();
+                    let err = match self.errinfo_stack.last() {
+                        Some(info) if info.exception.id() == exception.id() => info.err.clone(),
+                        _ => self.error_from_exception(exception),
+                    };
+                    return Err(err);
+                }
+                Inst::ENSURE_END => {
+                    let err = self.exception_stack.pop().unwrap();
+                    return Err(err);
+                }
+                Inst::TRY => {
+                    let mark = StackMark::new(self.pc, self);
+                    match stack_marks.iter_mut().find(|m| m.pc == mark.pc) {
+                        Some(m) => *m = mark,
+                        None => stack_marks.push(mark),
+                    }
+                    self.pc += 1;
+                }
+                Inst::DISCARD_ERR => {
+                    self.exception_stack.pop().unwrap();
+                    self.pc += 1;
+                }
+                Inst::RESTORE_ERRINFO => {
+                    let len = self.errinfo_stack.len();
+                    self.truncate_errinfo(len - 1);
+                    self.pc += 1;
+                }
                 Inst::DEF_CLASS => {
                     let is_module = self.read8(iseq, 1) == 1;
                     let id = self.read_id(iseq, 2);
@@ -1008,7 +1393,12 @@ impl VM {
                             } else {
                                 Value::class(&mut self.globals, classref)
                             };
-                            self.class().set_var(id, val);
+                            let mut parent = self.class();
+                            parent.set_var(id, val);
+                            self.call_hook(parent, "const_added", Value::symbol(id))?;
+                            if !is_module {
+                                self.call_hook(super_val, "inherited", val)?;
+                            }
                             val
                         }
                     };
@@ -1021,12 +1411,26 @@ impl VM {
                     self.pc += 10;
                     self.class_pop();
                 }
+                Inst::DEF_SCLASS => {
+                    let method = self.read_methodref(iseq, 1);
+                    let singleton = self.stack_pop();
+                    let val = self.get_singleton_class(singleton)?;
+                    self.class_push(val);
+                    let mut iseq = self.get_iseq(method)?;
+                    iseq.class_defined = self.gen_class_defined(val);
+                    let arg = Args::new0();
+                    try_err!(self, self.eval_send(method, val, &arg));
+                    self.pc += 5;
+                    self.class_pop();
+                }
                 Inst::DEF_METHOD => {
                     let id = self.read_id(iseq, 1);
                     let method = self.read_methodref(iseq, 5);
                     let mut iseq = self.get_iseq(method)?;
                     iseq.class_defined = self.gen_class_defined(None);
-                    self.define_method(id, method);
+                    let visibility = self.default_visibility();
+                    self.globals.set_method_visibility(method, visibility);
+                    self.define_method(id, method)?;
                     if self.define_mode().module_function {
                         self.define_singleton_method(self.class(), id, method)?;
                     };
@@ -1040,10 +1444,27 @@ impl VM {
                     let singleton = self.stack_pop();
                     self.define_singleton_method(singleton, id, method)?;
                     if self.define_mode().module_function {
-                        self.define_method(id, method);
+                        self.define_method(id, method)?;
                     };
                     self.pc += 9;
                 }
+                Inst::ALIAS_METHOD => {
+                    let new = self.read_id(iseq, 1);
+                    let old = self.read_id(iseq, 5);
+                    self.alias_method(self.class(), new, old)?;
+                    self.pc += 9;
+                }
+                Inst::ALIAS_GVAR => {
+                    let new = self.read_id(iseq, 1);
+                    let old = self.read_id(iseq, 5);
+                    self.alias_global_var(new, old);
+                    self.pc += 9;
+                }
+                Inst::UNDEF_METHOD => {
+                    let id = self.read_id(iseq, 1);
+                    self.undef_method(self.class(), id)?;
+                    self.pc += 5;
+                }
                 Inst::TO_S => {
                     let val = self.stack_pop();
                     let s = self.val_to_s(val);
@@ -1136,6 +1557,15 @@ impl VM {
         ))
     }
 
+    pub fn error_undefined_method_for_class(&mut self, method: IdentId, class: Value) -> RubyError {
+        let class_name = self.val_inspect(class);
+        let method_name = self.globals.get_ident_name(method);
+        self.error_name(format!(
+            "undefined method `{}' for class `{}'",
+            method_name, class_name
+        ))
+    }
+
     pub fn error_unimplemented(&self, msg: impl Into<String>) -> RubyError {
         let loc = self.get_loc();
         RubyError::new_runtime_err(
@@ -1164,6 +1594,16 @@ impl VM {
         RubyError::new_runtime_err(RuntimeErrKind::Type(msg.into()), self.source_info(), loc)
     }
 
+    /// TypeError raised when `val` can not be used as an operand of a numeric operation.
+    pub fn error_cant_coerce(&self, val: Value, class_name: &str) -> RubyError {
+        let name = match val.unpack() {
+            RV::Nil => "nil".to_string(),
+            RV::Bool(b) => format!("{}", b),
+            _ => self.globals.get_class_name(val),
+        };
+        self.error_type(format!("{} can't be coerced into {}", name, class_name))
+    }
+
     pub fn error_argument(&self, msg: impl Into<String>) -> RubyError {
         let loc = self.get_loc();
         RubyError::new_runtime_err(
@@ -1190,16 +1630,44 @@ impl VM {
         RubyError::new_runtime_err(RuntimeErrKind::Index(msg.into()), self.source_info(), loc)
     }
 
+    pub fn error_range(&self, msg: impl Into<String>) -> RubyError {
+        let loc = self.get_loc();
+        RubyError::new_runtime_err(RuntimeErrKind::Range(msg.into()), self.source_info(), loc)
+    }
+
     pub fn error_fiber(&self, msg: impl Into<String>) -> RubyError {
         let loc = self.get_loc();
         RubyError::new_runtime_err(RuntimeErrKind::Fiber(msg.into()), self.source_info(), loc)
     }
 
+    pub fn error_zero_divide(&self) -> RubyError {
+        let loc = self.get_loc();
+        RubyError::new_runtime_err(
+            RuntimeErrKind::ZeroDivision("divided by 0".to_string()),
+            self.source_info(),
+            loc,
+        )
+    }
+
+    pub fn error_stop_iteration(&self, msg: impl Into<String>) -> RubyError {
+        let loc = self.get_loc();
+        RubyError::new_runtime_err(
+            RuntimeErrKind::StopIteration(msg.into()),
+            self.source_info(),
+            loc,
+        )
+    }
+
     pub fn error_method_return(&self, method: MethodRef) -> RubyError {
         let loc = self.get_loc();
         RubyError::new_method_return(method, self.source_info(), loc)
     }
 
+    pub fn error_lambda_return(&self, id: usize) -> RubyError {
+        let loc = self.get_loc();
+        RubyError::new_lambda_return(id, self.source_info(), loc)
+    }
+
     pub fn error_block_return(&self) -> RubyError {
         let loc = self.get_loc();
         RubyError::new_block_return(self.source_info(), loc)
@@ -1406,7 +1874,45 @@ impl VM {
         }
     }
 
+    /// Return the class which is the base of class variable lookup in the current scope.
+    /// Singleton class definitions (`class << obj`) are skipped.
+    fn class_var_base(&self) -> Value {
+        let mut class_list = self.get_nearest_class_stack();
+        while let Some(list) = class_list {
+            if !list.class.as_module().unwrap().is_singleton {
+                return list.class;
+            }
+            class_list = list.outer;
+        }
+        self.globals.builtins.object
+    }
+
+    /// Search the inheritance chain of `class` for the class variable `id`.
+    pub fn get_class_var(&mut self, class: Value, id: IdentId) -> VMResult {
+        let cref = self.expect_module(class)?;
+        match cref.class_var_owner(id) {
+            Some(owner) => Ok(owner.class_vars[&id]),
+            None => {
+                let name = self.globals.get_ident_name(id).to_string();
+                let class = self.val_inspect(class);
+                Err(self.error_name(format!(
+                    "uninitialized class variable {} in {}",
+                    name, class
+                )))
+            }
+        }
+    }
+
+    /// Set the class variable `id` in the class which already holds it,
+    /// or in `class` if none of the inheritance chain has it.
+    pub fn set_class_var(&mut self, class: Value, id: IdentId, val: Value) {
+        let cref = class.as_module().unwrap();
+        let mut owner = cref.class_var_owner(id).unwrap_or(cref);
+        owner.class_vars.insert(id, val);
+    }
+
     pub fn get_global_var(&self, id: IdentId) -> Value {
+        let id = self.resolve_global_alias(id);
         match self.globals.global_var.get(&id) {
             Some(val) => val.clone(),
             None => Value::nil(),
@@ -1414,8 +1920,26 @@ impl VM {
     }
 
     pub fn set_global_var(&mut self, id: IdentId, val: Value) {
+        let id = self.resolve_global_alias(id);
         self.globals.global_var.insert(id, val);
     }
+
+    /// Make global variable `new` refer to the same storage as `old`.
+    pub fn alias_global_var(&mut self, new: IdentId, old: IdentId) {
+        let old = self.resolve_global_alias(old);
+        if new == old {
+            self.globals.global_alias.remove(&new);
+        } else {
+            self.globals.global_alias.insert(new, old);
+        }
+    }
+
+    fn resolve_global_alias(&self, id: IdentId) -> IdentId {
+        match self.globals.global_alias.get(&id) {
+            Some(id) => *id,
+            None => id,
+        }
+    }
 }
 
 // Utilities for method call
@@ -1456,11 +1980,22 @@ impl VM {
             }
             Err(_) => {
                 let name = self.globals.get_ident_name(method);
-                Err(self.error_undefined_op(name, rhs, lhs))
+                let err = self.error_undefined_op(name, rhs, lhs);
+                self.send_method_missing(lhs, method, &Args::new1(rhs), err)
             }
         }
     }
 
+    /// Call the method `[]` or `[]=` of `receiver`, which has no builtin implementation
+    /// in the VM, or `method_missing` if the method is not defined.
+    fn fallback_to_index_method(&mut self, receiver: Value, name: &str, args: &Args) -> VMResult {
+        let method = self.globals.get_ident_id(name);
+        match self.get_method(receiver, method) {
+            Ok(mref) => self.eval_send(mref, receiver, args),
+            Err(err) => self.send_method_missing(receiver, method, args, err),
+        }
+    }
+
     fn fallback_to_method_with_cache(
         &mut self,
         lhs: Value,
@@ -1475,9 +2010,12 @@ impl VM {
 }
 
 macro_rules! eval_op {
-    ($vm:ident, $iseq:ident, $rhs:expr, $lhs:expr, $op:ident, $id:expr) => {
+    ($vm:ident, $iseq:ident, $rhs:expr, $lhs:expr, $op:ident, $checked_op:ident, $id:expr) => {
         let val = match ($lhs.unpack(), $rhs.unpack()) {
-            (RV::Integer(lhs), RV::Integer(rhs)) => Value::fixnum(lhs.$op(rhs)),
+            (RV::Integer(lhs), RV::Integer(rhs)) => match lhs.$checked_op(rhs) {
+                Some(res) => Value::fixnum(res),
+                None => Value::bignum(BigInt::from(lhs).$op(BigInt::from(rhs))),
+            },
             (RV::Integer(lhs), RV::Float(rhs)) => Value::flonum((lhs as f64).$op(rhs)),
             (RV::Float(lhs), RV::Integer(rhs)) => Value::flonum(lhs.$op(rhs as f64)),
             (RV::Float(lhs), RV::Float(rhs)) => Value::flonum(lhs.$op(rhs)),
@@ -1493,23 +2031,26 @@ macro_rules! eval_op {
 impl VM {
     fn eval_add(&mut self, rhs: Value, lhs: Value, iseq: &ISeq) -> VMResult {
         use std::ops::Add;
-        eval_op!(self, iseq, rhs, lhs, add, IdentId::_ADD);
+        eval_op!(self, iseq, rhs, lhs, add, checked_add, IdentId::_ADD);
     }
 
     fn eval_sub(&mut self, rhs: Value, lhs: Value, iseq: &ISeq) -> VMResult {
         use std::ops::Sub;
-        eval_op!(self, iseq, rhs, lhs, sub, IdentId::_SUB);
+        eval_op!(self, iseq, rhs, lhs, sub, checked_sub, IdentId::_SUB);
     }
 
     fn eval_mul(&mut self, rhs: Value, lhs: Value, iseq: &ISeq) -> VMResult {
         use std::ops::Mul;
-        eval_op!(self, iseq, rhs, lhs, mul, IdentId::_MUL);
+        eval_op!(self, iseq, rhs, lhs, mul, checked_mul, IdentId::_MUL);
     }
 
     fn eval_addi(&mut self, lhs: Value, i: i32) -> VMResult {
         use std::ops::Add;
         let val = match lhs.unpack() {
-            RV::Integer(lhs) => Value::fixnum(lhs.add(i as i64)),
+            RV::Integer(lhs) => match lhs.checked_add(i as i64) {
+                Some(res) => Value::fixnum(res),
+                None => Value::bignum(BigInt::from(lhs).add(i)),
+            },
             RV::Float(lhs) => Value::flonum(lhs.add(i as f64)),
             _ => return self.fallback_to_method(IdentId::_ADD, lhs, Value::fixnum(i as i64)),
         };
@@ -1518,7 +2059,10 @@ impl VM {
 
     fn eval_subi(&mut self, lhs: Value, i: i32) -> VMResult {
         let val = match lhs.unpack() {
-            RV::Integer(lhs) => Value::fixnum(lhs - i as i64),
+            RV::Integer(lhs) => match lhs.checked_sub(i as i64) {
+                Some(res) => Value::fixnum(res),
+                None => Value::bignum(BigInt::from(lhs) - i),
+            },
             RV::Float(lhs) => Value::flonum(lhs - i as f64),
             _ => return self.fallback_to_method(IdentId::_SUB, lhs, Value::fixnum(i as i64)),
         };
@@ -1526,32 +2070,38 @@ impl VM {
     }
 
     fn eval_div(&mut self, rhs: Value, lhs: Value) -> VMResult {
+        use num::Integer;
         use std::ops::Div;
         match (lhs.unpack(), rhs.unpack()) {
-            (RV::Integer(lhs), RV::Integer(rhs)) => Ok(Value::fixnum(lhs.div(rhs))),
+            (RV::Integer(_), RV::Integer(0)) => Err(self.error_zero_divide()),
+            (RV::Integer(lhs), RV::Integer(rhs)) => match lhs.checked_div(rhs) {
+                Some(_) => Ok(Value::fixnum(Integer::div_floor(&lhs, &rhs))),
+                None => Ok(Value::bignum(
+                    BigInt::from(lhs).div_floor(&BigInt::from(rhs)),
+                )),
+            },
             (RV::Integer(lhs), RV::Float(rhs)) => Ok(Value::flonum((lhs as f64).div(rhs))),
             (RV::Float(lhs), RV::Integer(rhs)) => Ok(Value::flonum(lhs.div(rhs as f64))),
             (RV::Float(lhs), RV::Float(rhs)) => Ok(Value::flonum(lhs.div(rhs))),
-            (_, _) => return Err(self.error_undefined_op("/", rhs, lhs)),
+            (_, _) => {
+                let id = self.globals.get_ident_id("/");
+                self.fallback_to_method(id, lhs, rhs)
+            }
         }
     }
 
     fn eval_rem(&mut self, rhs: Value, lhs: Value) -> VMResult {
-        fn rem_floorf64(self_: f64, other: f64) -> f64 {
-            if self_ > 0.0 && other < 0.0 {
-                ((self_ - 1.0) % other) + other + 1.0
-            } else if self_ < 0.0 && other > 0.0 {
-                ((self_ + 1.0) % other) + other - 1.0
-            } else {
-                self_ % other
-            }
-        }
-        use divrem::*;
+        use builtin::float::rem_floor;
+        use num::Integer;
         let val = match (lhs.unpack(), rhs.unpack()) {
-            (RV::Integer(lhs), RV::Integer(rhs)) => Value::fixnum(lhs.rem_floor(rhs)),
-            (RV::Integer(lhs), RV::Float(rhs)) => Value::flonum(rem_floorf64(lhs as f64, rhs)),
-            (RV::Float(lhs), RV::Integer(rhs)) => Value::flonum(rem_floorf64(lhs, rhs as f64)),
-            (RV::Float(lhs), RV::Float(rhs)) => Value::flonum(rem_floorf64(lhs, rhs)),
+            (RV::Integer(_), RV::Integer(0)) => return Err(self.error_zero_divide()),
+            (RV::Integer(lhs), RV::Integer(rhs)) => match lhs.checked_rem(rhs) {
+                Some(_) => Value::fixnum(Integer::mod_floor(&lhs, &rhs)),
+                None => Value::fixnum(0),
+            },
+            (RV::Integer(lhs), RV::Float(rhs)) => Value::flonum(rem_floor(lhs as f64, rhs)),
+            (RV::Float(lhs), RV::Integer(rhs)) => Value::flonum(rem_floor(lhs, rhs as f64)),
+            (RV::Float(lhs), RV::Float(rhs)) => Value::flonum(rem_floor(lhs, rhs)),
             (_, _) => return self.fallback_to_method(IdentId::_REM, lhs, rhs),
         };
         Ok(val)
@@ -1561,7 +2111,10 @@ impl VM {
         let val = match (lhs.unpack(), rhs.unpack()) {
             (RV::Integer(lhs), RV::Integer(rhs)) => {
                 if 0 <= rhs && rhs <= std::u32::MAX as i64 {
-                    Value::fixnum(lhs.pow(rhs as u32))
+                    match lhs.checked_pow(rhs as u32) {
+                        Some(res) => Value::fixnum(res),
+                        None => Value::bignum(num::pow(BigInt::from(lhs), rhs as usize)),
+                    }
                 } else {
                     Value::flonum((lhs as f64).powf(rhs as f64))
                 }
@@ -1580,7 +2133,7 @@ impl VM {
         match lhs.unpack() {
             RV::Integer(lhs) => {
                 match rhs.as_fixnum() {
-                    Some(rhs) => return Ok(Value::fixnum(lhs << rhs)),
+                    Some(rhs) => return Ok(builtin::integer::shl(lhs, rhs)),
                     _ => {}
                 };
             }
@@ -1600,8 +2153,13 @@ impl VM {
 
     fn eval_shr(&mut self, rhs: Value, lhs: Value) -> VMResult {
         match (lhs.unpack(), rhs.unpack()) {
-            (RV::Integer(lhs), RV::Integer(rhs)) => Ok(Value::fixnum(lhs >> rhs)),
-            (_, _) => return Err(self.error_undefined_op(">>", rhs, lhs)),
+            (RV::Integer(lhs), RV::Integer(rhs)) if rhs != i64::MIN => {
+                Ok(builtin::integer::shl(lhs, -rhs))
+            }
+            (_, _) => {
+                let id = self.globals.get_ident_id(">>");
+                self.fallback_to_method(id, lhs, rhs)
+            }
         }
     }
 
@@ -1641,14 +2199,29 @@ macro_rules! eval_cmp {
             (RV::Float(lhs), RV::Integer(rhs)) => Ok(Value::bool(lhs.$op(&(rhs as f64)))),
             (RV::Integer(lhs), RV::Float(rhs)) => Ok(Value::bool((lhs as f64).$op(&rhs))),
             (RV::Float(lhs), RV::Float(rhs)) => Ok(Value::bool(lhs.$op(&rhs))),
+            (RV::Float(lhs), _) if $rhs.as_bignum().is_some() => {
+                let rhs = $rhs.as_bignum().unwrap();
+                match builtin::integer::cmp_bigint_float(rhs, lhs) {
+                    Some(ord) => Ok(Value::bool(ord.reverse().$op(&Ordering::Equal))),
+                    None => Ok(Value::false_val()),
+                }
+            }
             (_, _) => return $vm.fallback_to_method($id, $lhs, $rhs),
         }
     };
 }
 
 impl VM {
-    pub fn eval_eq(&self, rhs: Value, lhs: Value) -> Result<bool, RubyError> {
-        Ok(rhs.equal(lhs))
+    /// Examine whether `lhs` == `rhs`.
+    /// `==` of ordinary objects is dispatched to the method, which may be defined by users.
+    pub fn eval_eq(&mut self, rhs: Value, lhs: Value) -> Result<bool, RubyError> {
+        match lhs.is_object() {
+            Some(oref) if oref.kind == ObjKind::Ordinary => {
+                let val = self.fallback_to_method(IdentId::_EQ, lhs, rhs)?;
+                Ok(self.val_to_bool(val))
+            }
+            _ => Ok(rhs.equal(lhs)),
+        }
     }
 
     pub fn eval_teq(&mut self, rhs: Value, lhs: Value) -> Result<bool, RubyError> {
@@ -1685,22 +2258,33 @@ impl VM {
         eval_cmp!(self, rhs, lhs, gt, IdentId::_GT)
     }
 
+    fn eval_le(&mut self, rhs: Value, lhs: Value) -> VMResult {
+        eval_cmp!(self, rhs, lhs, le, IdentId::_LE)
+    }
+
+    fn eval_lt(&mut self, rhs: Value, lhs: Value) -> VMResult {
+        eval_cmp!(self, rhs, lhs, lt, IdentId::_LT)
+    }
+
     pub fn eval_cmp(&mut self, rhs: Value, lhs: Value) -> VMResult {
         let res = match lhs.unpack() {
             RV::Integer(lhs) => match rhs.unpack() {
                 RV::Integer(rhs) => lhs.partial_cmp(&rhs),
                 RV::Float(rhs) => (lhs as f64).partial_cmp(&rhs),
-                _ => return Ok(Value::nil()),
+                _ => match rhs.as_bignum() {
+                    Some(rhs) => BigInt::from(lhs).partial_cmp(rhs),
+                    None => return Ok(Value::nil()),
+                },
             },
             RV::Float(lhs) => match rhs.unpack() {
                 RV::Integer(rhs) => lhs.partial_cmp(&(rhs as f64)),
                 RV::Float(rhs) => lhs.partial_cmp(&rhs),
-                _ => return Ok(Value::nil()),
+                _ => match rhs.as_bignum() {
+                    Some(rhs) => builtin::integer::cmp_bigint_float(rhs, lhs).map(|o| o.reverse()),
+                    None => return Ok(Value::nil()),
+                },
             },
-            _ => {
-                let id = self.globals.get_ident_id("<=>");
-                return self.fallback_to_method(id, lhs, rhs);
-            }
+            _ => return self.fallback_to_method(IdentId::_CMP, lhs, rhs),
         };
         match res {
             Some(ord) => Ok(Value::fixnum(ord as i64)),
@@ -1708,26 +2292,47 @@ impl VM {
         }
     }
 
-    pub fn sort_array(&mut self, mut aref: ArrayRef) -> Result<(), RubyError> {
-        if aref.elements.len() > 0 {
-            let val = aref.elements[0];
-            for i in 1..aref.elements.len() {
-                match self.eval_cmp(aref.elements[i], val)? {
-                    v if v == Value::nil() => {
-                        let lhs = self.globals.get_class_name(val);
-                        let rhs = self.globals.get_class_name(aref.elements[i]);
-                        return Err(self.error_argument(format!(
-                            "Comparison of {} with {} failed.",
-                            lhs, rhs
-                        )));
-                    }
-                    _ => {}
+    /// Compare `lhs` and `rhs` with `<=>`.
+    /// Raise ArgumentError when they are not comparable.
+    pub fn compare_values(&mut self, lhs: Value, rhs: Value) -> Result<Ordering, RubyError> {
+        let res = self.eval_cmp(rhs, lhs)?;
+        match res.as_fixnum() {
+            Some(i) => Ok(i.cmp(&0)),
+            None => {
+                let lhs = self.globals.get_class_name(lhs);
+                let rhs = self.globals.get_class_name(rhs);
+                Err(self.error_argument(format!("Comparison of {} with {} failed.", lhs, rhs)))
+            }
+        }
+    }
+
+    /// Sort `v` with `cmp`. The error which occured in `cmp` is returned.
+    pub fn sort_values(
+        &mut self,
+        v: &mut Vec<Value>,
+        mut cmp: impl FnMut(&mut VM, Value, Value) -> Result<Ordering, RubyError>,
+    ) -> Result<(), RubyError> {
+        let mut err = None;
+        v.sort_by(|a, b| {
+            if err.is_some() {
+                return Ordering::Equal;
+            }
+            match cmp(self, *a, *b) {
+                Ok(ord) => ord,
+                Err(e) => {
+                    err = Some(e);
+                    Ordering::Equal
                 }
             }
-            aref.elements
-                .sort_by(|a, b| self.eval_cmp(*b, *a).unwrap().to_ordering());
+        });
+        match err {
+            Some(err) => Err(err),
+            None => Ok(()),
         }
-        Ok(())
+    }
+
+    pub fn sort_array(&mut self, mut aref: ArrayRef) -> Result<(), RubyError> {
+        self.sort_values(&mut aref.elements, |vm, a, b| vm.compare_values(a, b))
     }
 }
 
@@ -1775,6 +2380,7 @@ impl VM {
             RV::Symbol(i) => format!("{}", self.globals.get_ident_name(i)),
             RV::Object(oref) => match &oref.kind {
                 ObjKind::String(s) => s.to_s(),
+                ObjKind::BigNum(n) => n.to_string(),
                 ObjKind::Class(cref) => match cref.name {
                     Some(id) => format! {"{}", self.globals.get_ident_name(id)},
                     None => format! {"#<Class:0x{:x}>", cref.id()},
@@ -1808,6 +2414,7 @@ impl VM {
             RV::Symbol(sym) => format!(":{}", self.globals.get_ident_name(sym)),
             RV::Object(oref) => match &oref.kind {
                 ObjKind::String(s) => s.inspect(),
+                ObjKind::BigNum(n) => n.to_string(),
                 ObjKind::Range(rinfo) => rinfo.inspect(self),
                 ObjKind::Class(cref) => match cref.name {
                     Some(id) => format! {"{}", self.globals.get_ident_name(id)},
@@ -1834,23 +2441,105 @@ impl VM {
         }
     }
 
+    /// Call the method `method_id` of `receiver` with `args`.
+    /// If the method is not found, `method_missing` is called.
+    pub fn send(&mut self, receiver: Value, method_id: IdentId, args: &Args) -> VMResult {
+        match self.get_method(receiver, method_id) {
+            Ok(method) => self.eval_send(method, receiver, args),
+            Err(err) => self.send_method_missing(receiver, method_id, args, err),
+        }
+    }
+
     pub fn send0(&mut self, receiver: Value, method_id: IdentId) -> VMResult {
-        let method = self.get_method(receiver, method_id)?;
-        let args = Args::new0();
-        let val = self.eval_send(method, receiver, &args)?;
-        Ok(val)
+        self.send(receiver, method_id, &Args::new0())
+    }
+
+    pub fn send1(&mut self, receiver: Value, method_id: IdentId, arg: Value) -> VMResult {
+        self.send(receiver, method_id, &Args::new1(arg))
+    }
+
+    /// Call `method_missing` of `receiver` with the name of the missing method and `args`.
+    /// Return `err` if `method_missing` is not found.
+    pub fn send_method_missing(
+        &mut self,
+        receiver: Value,
+        method_id: IdentId,
+        args: &Args,
+        err: RubyError,
+    ) -> VMResult {
+        let id = self.globals.get_ident_id("method_missing");
+        if method_id == id {
+            return Err(err);
+        }
+        let method = match self.get_method(receiver, id) {
+            Ok(method) => method,
+            Err(_) => return Err(err),
+        };
+        let mut new_args = Args::new(args.len() + 1);
+        new_args[0] = Value::symbol(method_id);
+        for (i, arg) in args.iter().enumerate() {
+            new_args[i + 1] = *arg;
+        }
+        new_args.block = args.block;
+        new_args.kw_arg = args.kw_arg;
+        self.eval_send(method, receiver, &new_args)
     }
 }
 
 impl VM {
-    fn vm_send(&mut self, iseq: &ISeq, receiver: Value) -> VMResult {
+    /// Convert the block argument `&val` to a block.
+    /// `&:sym` is converted without allocating a Proc object.
+    /// Other objects than Proc are converted to Proc with `to_proc`.
+    fn block_from_value(&mut self, val: Value) -> Result<Option<MethodRef>, RubyError> {
+        if val.is_nil() {
+            return Ok(None);
+        }
+        if let Some(id) = val.as_symbol() {
+            return Ok(Some(self.globals.get_symbol_proc(id)));
+        }
+        let proc_obj = if val.as_proc().is_some() {
+            val
+        } else {
+            let to_proc = self.globals.get_ident_id("to_proc");
+            let proc_obj = match self.get_method(val, to_proc) {
+                Ok(method) => self.eval_send(method, val, &Args::new0())?,
+                Err(_) => Value::nil(),
+            };
+            if proc_obj.as_proc().is_none() {
+                let inspect = self.val_inspect(val);
+                return Err(
+                    self.error_type(format!("Wrong argument type {} (expected Proc).", inspect))
+                );
+            }
+            proc_obj
+        };
+        let pref = proc_obj.as_proc().unwrap();
+        let method = match pref.kind {
+            ProcKind::Block(context) if !pref.is_lambda => context.iseq_ref.method,
+            ProcKind::Symbol(id) => self.globals.get_symbol_proc(id),
+            _ => self.globals.get_proc_block(proc_obj),
+        };
+        Ok(Some(method))
+    }
+
+    /// Send a method according to SEND or SEND_SELF instruction.
+    /// Private methods can be called only if `is_self_call` is true, i.e. the receiver is self.
+    fn vm_send(&mut self, iseq: &ISeq, receiver: Value, is_self_call: bool) -> VMResult {
         let method_id = self.read_id(iseq, 1);
         let args_num = self.read16(iseq, 5);
         let flag = self.read16(iseq, 7);
         let cache_slot = self.read32(iseq, 9);
         let block = self.read32(iseq, 13);
-        let methodref = self.get_method_from_cache(cache_slot, receiver, method_id)?;
+        let methodref = self.get_method_from_cache(cache_slot, receiver, method_id);
 
+        let block = if block != 0 {
+            Some(MethodRef::from(block))
+        } else if flag & 0b10 == 2 {
+            let val = self.stack_pop();
+            self.block_from_value(val)?
+        } else {
+            None
+        };
         let keyword = if flag & 0b01 == 1 {
             let val = self.stack_pop();
             Some(val)
@@ -1858,24 +2547,136 @@ impl VM {
             None
         };
         let mut args = self.pop_args_to_ary(args_num as usize);
+        args.block = block;
+        args.kw_arg = keyword;
+        match methodref {
+            Ok(methodref) => {
+                self.check_visibility(methodref, method_id, receiver, is_self_call)?;
+                self.eval_send(methodref, receiver, &args)
+            }
+            Err(err) => self.send_method_missing(receiver, method_id, &args, err),
+        }
+    }
+
+    /// Call the method which `super` in the current method refers to.
+    /// Without an explicit block argument, the block of the current method is passed.
+    fn vm_super(&mut self, iseq: &ISeq) -> VMResult {
+        let args_num = self.read16(iseq, 1);
+        let flag = self.read16(iseq, 3);
+        let block = self.read32(iseq, 5);
+        let context = self.get_method_context();
         let block = if block != 0 {
             Some(MethodRef::from(block))
-        } else if flag & 0b10 == 2 {
+        } else if flag & 0b010 == 2 {
+            let val = self.stack_pop();
+            self.block_from_value(val)?
+        } else {
+            context.block
+        };
+        let keyword = if flag & 0b001 == 1 {
             let val = self.stack_pop();
-            let method = val
-                .as_proc()
-                .ok_or_else(|| self.error_argument("Block argument must be Proc."))?
-                .context
-                .iseq_ref
-                .method;
-            Some(method)
+            Some(val)
         } else {
             None
         };
+        let mut args = self.pop_args_to_ary(args_num as usize);
+        let id = match context.kind {
+            ISeqKind::Method(id) => id,
+            _ => return Err(self.error_nomethod("super called outside of method")),
+        };
+        if flag & 0b100 == 4 {
+            args = self.zsuper_args(context)?;
+        } else {
+            args.kw_arg = keyword;
+        }
         args.block = block;
-        args.kw_arg = keyword;
-        let val = self.eval_send(methodref, receiver, &args)?;
-        Ok(val)
+        let method = match self.get_super_method() {
+            Some(method) => method,
+            None => {
+                let inspect = self.val_inspect(context.self_value);
+                return Err(self.error_nomethod(format!(
+                    "no superclass method `{}' for {}.",
+                    self.globals.get_ident_name(id),
+                    inspect,
+                )));
+            }
+        };
+        self.eval_send(method, context.self_value, &args)
+    }
+
+    /// Collect the current values of the parameters of the method `context` for zsuper.
+    fn zsuper_args(&mut self, context: ContextRef) -> Result<Args, RubyError> {
+        let iseq = context.iseq_ref;
+        if iseq.is_block() {
+            return Err(self.error_internal(
+                "implicit argument passing of super from method defined by define_method() is not supported. Specify all arguments explicitly.",
+            ));
+        }
+        let params = &iseq.params;
+        let rest_pos = params.req_params + params.opt_params;
+        let post_end = rest_pos + if params.rest_param { 1 } else { 0 } + params.post_params;
+        let mut args = Args::new(0);
+        let mut kw = OrderedMap::new();
+        for (i, id) in params.param_ident.iter().enumerate() {
+            let val = context[i];
+            if params.rest_param && i == rest_pos {
+                match val.as_array() {
+                    Some(ary) => {
+                        for elem in &ary.elements {
+                            args.push(*elem);
+                        }
+                    }
+                    None => args.push(val),
+                }
+            } else if i < post_end {
+                args.push(val);
+            } else if params.keyword_params.contains_key(id) {
+                kw.insert(HashKey(Value::symbol(*id)), val);
+            } else if params.kwrest_param == Some(LvarId::from_usize(i)) {
+                if let Some(hash) = val.as_hash() {
+                    for (k, v) in hash.iter() {
+                        kw.insert(HashKey(k), v);
+                    }
+                }
+            }
+        }
+        if !kw.is_empty() {
+            args.kw_arg = Some(Value::hash_from(&self.globals, kw));
+        }
+        Ok(args)
+    }
+
+    /// Check whether the method `methodref` can be called for `receiver` in the current context.
+    fn check_visibility(
+        &mut self,
+        methodref: MethodRef,
+        method_id: IdentId,
+        receiver: Value,
+        is_self_call: bool,
+    ) -> Result<(), RubyError> {
+        let kind = match self.globals.get_method_visibility(methodref) {
+            Visibility::Public => return Ok(()),
+            Visibility::Private if is_self_call => return Ok(()),
+            Visibility::Private => "private",
+            Visibility::Protected => {
+                // A protected method can be called when self is a kind of the class which defines the method.
+                let owner = match self.globals.get_method_info(methodref) {
+                    MethodInfo::RubyFunc { iseq } => iseq.class_defined.map(|list| list.class),
+                    _ => None,
+                };
+                let owner = owner.unwrap_or_else(|| receiver.get_class_object(&self.globals));
+                if self.kind_of(self.context().self_value, owner) {
+                    return Ok(());
+                }
+                "protected"
+            }
+        };
+        let inspect = self.val_inspect(receiver);
+        let method_name = self.globals.get_ident_name(method_id);
+        Err(self.error_nomethod(format!(
+            "{} method `{}' called for {}",
+            kind, method_name, inspect
+        )))
     }
 }
 
@@ -1891,20 +2692,354 @@ impl VM {
         self.eval_method(methodref, context.self_value, Some(context), args)
     }
 
-    /// Evaluate method with self_val of current context, caller context as outer context, and given `args`.
-    fn eval_yield(&mut self, iseq: &ISeq) -> VMResult {
-        let args_num = self.read32(iseq, 1) as usize;
-        let args = self.pop_args_to_ary(args_num);
+    /// Call the method `id` of `receiver` with `args` and the block `f` implemented in Rust,
+    /// and return the value of the method.
+    /// When `f` returns None, the method is exited like `break` in a block with nil,
+    /// running ensure clauses in the method.
+    pub fn eval_with_native_block(
+        &mut self,
+        receiver: Value,
+        id: IdentId,
+        args: &Args,
+        f: &mut NativeBlock,
+    ) -> VMResult {
+        let method = self.get_method(receiver, id)?;
+        let block = self.globals.get_native_block(self.native_blocks.len());
+        let mut args = args.clone();
+        args.block = Some(block);
+        // The closure is removed from the stack before `f` goes out of scope.
+        let f: *mut NativeBlock = f;
+        self.native_blocks.push(NativeBlockFrame {
+            block: unsafe { std::mem::transmute::<*mut NativeBlock, *mut NativeBlock<'static>>(f) },
+            context: self.context(),
+            temp_stack: vec![],
+            is_break: false,
+        });
+        let res = self.eval_send(method, receiver, &args);
+        let frame = self.native_blocks.pop().unwrap();
+        self.temp_stack.extend_from_slice(&frame.temp_stack);
+        match res {
+            Ok(val) => Ok(val),
+            Err(err) if err.kind == RubyErrorKind::MethodReturn(block) => {
+                if frame.is_break {
+                    // The value of `break` is left on the stack.
+                    return Err(self.error_block_return());
+                }
+                Ok(self.stack_pop())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Call the closure at `depth` of the native block stack with `args`.
+    /// Multiple values are packed into an array.
+    fn eval_native_block(&mut self, depth: usize, args: &Args) -> VMResult {
+        let (f, mut context) = match self.native_blocks.get(depth) {
+            Some(frame) => (frame.block, frame.context),
+            None => return Err(self.error_internal("Native block was called out of its method.")),
+        };
+        let val = match args.len() {
+            0 => Value::nil(),
+            1 => args[0],
+            _ => Value::array_from(&self.globals, args.to_vec()),
+        };
+        let len = self.temp_stack.len();
+        // The closure runs in the context of its caller, so that blocks evaluated in the closure
+        // get the caller as their outer. The saved state of the context is restored afterwards.
+        let (pc, stack_len) = (context.pc, context.stack_len);
+        self.context_push(context);
+        let res = unsafe { (*f)(self, val) };
+        self.context_pop();
+        context.pc = pc;
+        context.stack_len = stack_len;
+        let pushed: Vec<Value> = self.temp_stack.drain(len..).collect();
+        self.native_blocks[depth].temp_stack.extend(pushed);
+        let block = self.globals.get_native_block(depth);
+        match res {
+            Ok(Some(val)) => Ok(val),
+            Ok(None) => {
+                self.stack_push(Value::nil());
+                Err(self.error_method_return(block))
+            }
+            // `break` in a block called by the closure exits the method called by
+            // `eval_with_native_block`, instead of the method which called the native block.
+            Err(err) if err.kind == RubyErrorKind::BlockReturn => {
+                self.native_blocks[depth].is_break = true;
+                Err(self.error_method_return(block))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Invoke the Proc object `proc_obj` with `args`.
+    pub fn eval_proc(&mut self, proc_obj: Value, args: &Args) -> VMResult {
+        let pref = match proc_obj.as_proc() {
+            Some(pref) => pref,
+            None => return Err(self.error_unimplemented("Expected Proc object.")),
+        };
+        let context = match &pref.kind {
+            ProcKind::Block(context) => *context,
+            ProcKind::Symbol(id) => return self.eval_symbol_proc(*id, args),
+            ProcKind::Method(method) => {
+                let method = method.as_method().unwrap();
+                return self.eval_send(method.method, method.receiver, args);
+            }
+            ProcKind::Curry {
+                proc,
+                arity,
+                args: curried,
+            } => {
+                let mut curried = curried.clone();
+                curried.extend(args.iter().cloned());
+                if curried.len() < *arity {
+                    let curry = ProcRef::new(ProcInfo {
+                        is_lambda: pref.is_lambda,
+                        kind: ProcKind::Curry {
+                            proc: *proc,
+                            arity: *arity,
+                            args: curried,
+                        },
+                    });
+                    return Ok(Value::procobj_from(&self.globals, curry));
+                }
+                let mut new_args = Args::new(0);
+                for arg in curried {
+                    new_args.push(arg);
+                }
+                new_args.block = args.block;
+                return self.eval_proc(*proc, &new_args);
+            }
+            ProcKind::Compose { first, second } => {
+                let val = self.eval_callable(*first, args)?;
+                let len = self.temp_stack.len();
+                self.temp_push(val);
+                let res = self.eval_callable(*second, &Args::new1(val));
+                self.temp_stack.truncate(len);
+                return res;
+            }
+        };
+        if !pref.is_lambda {
+            let context = Context::from_args(
+                self,
+                context.self_value,
+                context.iseq_ref,
+                args,
+                context.outer,
+            )?;
+            return self.run_context(ContextRef::from_local(&context));
+        }
+        let mut context = Context::from_lambda_args(
+            self,
+            context.self_value,
+            context.iseq_ref,
+            args,
+            context.outer,
+        )?;
+        self.run_lambda_context(&mut context)
+    }
+
+    /// Run `context` as a lambda call.
+    /// `return` in the lambda and its blocks, and `break` in the lambda exit the lambda itself.
+    fn run_lambda_context(&mut self, context: &mut Context) -> VMResult {
+        self.globals.lambda_count += 1;
+        let id = self.globals.lambda_count;
+        context.lambda_id = Some(id);
+        match self.run_context(ContextRef::from_local(context)) {
+            Err(err) => match err.kind {
+                RubyErrorKind::LambdaReturn(lambda_id) if lambda_id == id => Ok(self.stack_pop()),
+                RubyErrorKind::BlockReturn => Ok(self.stack_pop()),
+                _ => Err(err),
+            },
+            res => res,
+        }
+    }
+
+    /// Get the id of the innermost lambda call which encloses the block `context`.
+    /// Return None if the block is not in a lambda.
+    fn enclosing_lambda(&self, context: ContextRef) -> Option<usize> {
+        let mut context = Some(context);
+        while let Some(ctx) = context {
+            if ctx.lambda_id.is_some() {
+                return ctx.lambda_id;
+            }
+            if !ctx.iseq_ref.is_block() {
+                return None;
+            }
+            context = ctx.outer;
+        }
+        None
+    }
+
+    /// Evaluate `proc` passed as a block by `&proc`.
+    /// As for a block, a single Array argument is expanded for a lambda which requires more than one argument.
+    fn eval_proc_block(&mut self, proc: Value, args: &Args) -> VMResult {
+        let expand = match proc.as_proc().and_then(|pref| pref.context()) {
+            Some(context) if args.len() == 1 => {
+                let params = &context.iseq_ref.params;
+                params.req_params + params.post_params > 1
+            }
+            _ => false,
+        };
+        if expand {
+            if let Some(ary) = args[0].as_array() {
+                let mut new_args = Args::new(0);
+                for elem in &ary.elements {
+                    new_args.push(*elem);
+                }
+                new_args.block = args.block;
+                return self.eval_proc(proc, &new_args);
+            }
+        }
+        self.eval_proc(proc, args)
+    }
+
+    /// Evaluate the body `proc` of the method `id` defined by `define_method` with `self_val`.
+    /// The body is evaluated as a lambda, and `super` in it refers to the method `id`.
+    fn eval_proc_method(
+        &mut self,
+        id: IdentId,
+        proc: Value,
+        self_val: Value,
+        args: &Args,
+    ) -> VMResult {
+        let context = match proc.as_proc().and_then(|pref| pref.context()) {
+            Some(context) => context,
+            None => return self.eval_proc(proc, args),
+        };
+        let mut context =
+            Context::from_lambda_args(self, self_val, context.iseq_ref, args, context.outer)?;
+        context.kind = ISeqKind::Method(id);
+        self.run_lambda_context(&mut context)
+    }
+
+    /// Call the method `id` of the first element of `args` with the rest of `args`.
+    fn eval_symbol_proc(&mut self, id: IdentId, args: &Args) -> VMResult {
+        if args.len() == 0 {
+            return Err(self.error_argument("no receiver given"));
+        }
+        let mut new_args = Args::new(args.len() - 1);
+        for i in 1..args.len() {
+            new_args[i - 1] = args[i];
+        }
+        new_args.block = args.block;
+        new_args.kw_arg = args.kw_arg;
+        self.send(args[0], id, &new_args)
+    }
+
+    /// Invoke `callable` with `args`. `callable` is a Proc, a Method or an object which responds to `call`.
+    fn eval_callable(&mut self, callable: Value, args: &Args) -> VMResult {
+        if callable.as_proc().is_some() {
+            self.eval_proc(callable, args)
+        } else {
+            let call = self.globals.get_ident_id("call");
+            self.send(callable, call, args)
+        }
+    }
+
+    /// Get the context of the method which encloses the current context.
+    fn get_method_context(&self) -> ContextRef {
         let mut context = self.context();
         loop {
             if let ISeqKind::Method(_) = context.kind {
-                break;
+                return context;
+            }
+            context = match context.outer {
+                Some(outer) => outer,
+                None => return context,
+            };
+        }
+    }
+
+    /// Get the method which `super` in the current method refers to.
+    /// The method is searched in the ancestors of the class of self,
+    /// following the class or module which owns the current method.
+    fn get_super_method(&mut self) -> Option<MethodRef> {
+        let context = self.get_method_context();
+        let id = match context.kind {
+            ISeqKind::Method(id) => id,
+            _ => return None,
+        };
+        let class = context
+            .self_value
+            .get_class_object_for_method(&self.globals);
+        let ancestors = self.get_ancestors(class);
+        let owner = ancestors.iter().position(|module| {
+            match module.as_module().unwrap().method_table.get(&id) {
+                Some(method) => self.get_method_iseq(*method) == Some(context.iseq_ref),
+                None => false,
+            }
+        })?;
+        for module in &ancestors[owner + 1..] {
+            match module.as_module().unwrap().method_table.get(&id) {
+                Some(method) if self.globals.is_void_method(*method) => return None,
+                Some(method) => return Some(*method),
+                None => {}
+            }
+        }
+        None
+    }
+
+    /// Get ISeqRef which is executed by the method `method`.
+    fn get_method_iseq(&self, method: MethodRef) -> Option<ISeqRef> {
+        match self.globals.get_method_info(method) {
+            MethodInfo::RubyFunc { iseq } => Some(*iseq),
+            MethodInfo::ProcMethod { proc, .. } => proc.as_proc()?.context().map(|c| c.iseq_ref),
+            _ => None,
+        }
+    }
+
+    /// Collect the classes and modules of `class` in the order of method search.
+    ///
+    /// The ancestors of each class are linearized as its prepended modules, the class itself
+    /// and its included modules, followed by the ancestors of the superclass.
+    /// A module which already appears in the ancestors of the superclass is skipped.
+    pub fn get_ancestors(&self, class: Value) -> Vec<Value> {
+        let super_ancestors = match class.superclass() {
+            // The superclass of a module is only used for constant lookup.
+            _ if class.is_module().is_some() => vec![],
+            Some(superclass) => self.get_ancestors(superclass),
+            // The chain of singleton classes continues to the class of the original object.
+            None if class.as_module().unwrap().is_singleton => {
+                self.get_ancestors(class.as_object().class())
+            }
+            None => vec![],
+        };
+        let mut ancestors = vec![];
+        push_module(&mut ancestors, class, &super_ancestors);
+        ancestors.extend(super_ancestors);
+        return ancestors;
+
+        fn push_module(ancestors: &mut Vec<Value>, module: Value, super_ancestors: &[Value]) {
+            if ancestors.contains(&module) || super_ancestors.contains(&module) {
+                return;
+            }
+            let mref = module.as_module().unwrap();
+            for prepended in &mref.prepend {
+                push_module(ancestors, *prepended, super_ancestors);
+            }
+            ancestors.push(module);
+            for included in &mref.include {
+                push_module(ancestors, *included, super_ancestors);
             }
-            context = context
-                .outer
-                .ok_or_else(|| self.error_unimplemented("No block given."))?;
         }
-        let method = context
+    }
+
+    /// Push the description of `defined?` if `defined` is true, otherwise nil.
+    fn push_defined(&mut self, defined: bool, desc: &str) {
+        let val = if defined {
+            Value::string(&self.globals, desc.to_string())
+        } else {
+            Value::nil()
+        };
+        self.stack_push(val);
+    }
+
+    /// Evaluate method with self_val of current context, caller context as outer context, and given `args`.
+    fn eval_yield(&mut self, iseq: &ISeq) -> VMResult {
+        let args_num = self.read32(iseq, 1) as usize;
+        let args = self.pop_args_to_ary(args_num);
+        let method = self
+            .get_method_context()
             .block
             .ok_or_else(|| self.error_unimplemented("No block given."))?;
         let res = self.eval_method(
@@ -1948,7 +3083,13 @@ impl VM {
                 {
                     self.perf.get_perf(Perf::EXTERN);
                 }
-                let val = func(self, self_val, args)?;
+                let func = *func;
+                let len = self.temp_stack.len();
+                self.temp_push(self_val);
+                self.temp_push_args(args);
+                let res = func(self, self_val, args);
+                self.temp_stack.truncate(len);
+                let val = res?;
                 #[cfg(feature = "perf")]
                 {
                     self.perf.get_perf_no_count(inst);
@@ -1969,6 +3110,23 @@ impl VM {
                 }
                 None => unreachable!("AttrReader must be used only for class instance."),
             },
+            MethodInfo::Void => return Err(self.error_nomethod("Undefined method is called.")),
+            MethodInfo::SymbolProc { id } => {
+                let id = *id;
+                self.eval_symbol_proc(id, args)?
+            }
+            MethodInfo::ProcBlock { proc } => {
+                let proc = *proc;
+                self.eval_proc_block(proc, args)?
+            }
+            MethodInfo::NativeBlock { depth } => {
+                let depth = *depth;
+                self.eval_native_block(depth, args)?
+            }
+            MethodInfo::ProcMethod { id, proc } => {
+                let (id, proc) = (*id, *proc);
+                self.eval_proc_method(id, proc, self_val, args)?
+            }
             MethodInfo::RubyFunc { iseq } => {
                 let iseq = *iseq;
                 let context = Context::from_args(self, self_val, iseq, args, outer)?;
@@ -1991,14 +3149,17 @@ impl VM {
 // API's for handling instance/singleton methods.
 
 impl VM {
-    pub fn define_method(&mut self, id: IdentId, method: MethodRef) {
-        if self.exec_context.len() == 1 {
+    /// Define the method `id` and call `method_added` of the class in which it is defined.
+    pub fn define_method(&mut self, id: IdentId, method: MethodRef) -> Result<(), RubyError> {
+        let class = if self.exec_context.len() == 1 {
             // A method defined in "top level" is registered as an object method.
-            self.add_object_method(id, method);
+            self.globals.builtins.object
         } else {
             // A method defined in a class definition is registered as an instance method of the class.
-            self.add_instance_method(self.class(), id, method);
-        }
+            self.class()
+        };
+        self.add_instance_method(class, id, method);
+        self.call_method_added(class, id)
     }
 
     pub fn define_singleton_method(
@@ -2044,6 +3205,119 @@ impl VM {
         self.add_instance_method(self.globals.builtins.object, id, info);
     }
 
+    /// Define `new` in `class_obj` as a copy of the method `old`.
+    pub fn alias_method(
+        &mut self,
+        class_obj: Value,
+        new: IdentId,
+        old: IdentId,
+    ) -> Result<(), RubyError> {
+        let method = match self.get_instance_method(class_obj, old) {
+            Ok(method) => method,
+            Err(_) => return Err(self.error_undefined_method_for_class(old, class_obj)),
+        };
+        self.add_instance_method(class_obj, new, method);
+        self.call_method_added(class_obj, new)
+    }
+
+    /// Include `module` in `class_obj`, so that `module` follows `class_obj` in its ancestors.
+    /// Nothing is done if `module` is already one of the ancestors.
+    pub fn include_module(&mut self, class_obj: Value, module: Value) -> Result<(), RubyError> {
+        if self.get_ancestors(module).contains(&class_obj) {
+            return Err(self.error_argument("cyclic include detected"));
+        }
+        if !self.get_ancestors(class_obj).contains(&module) {
+            class_obj.as_module().unwrap().include.insert(0, module);
+            self.globals.class_version += 1;
+        }
+        Ok(())
+    }
+
+    /// Prepend `module` to `class_obj`, so that `module` precedes `class_obj` in its ancestors.
+    /// Nothing is done if `module` is already prepended.
+    pub fn prepend_module(&mut self, class_obj: Value, module: Value) -> Result<(), RubyError> {
+        if self.get_ancestors(module).contains(&class_obj) {
+            return Err(self.error_argument("cyclic prepend detected"));
+        }
+        let mut class = class_obj.as_module().unwrap();
+        if !class.prepend.contains(&module) {
+            class.prepend.insert(0, module);
+            self.globals.class_version += 1;
+        }
+        Ok(())
+    }
+
+    /// Call `method_added` of `class_obj` for the method `id` newly defined in it.
+    /// Singleton classes are skipped, as `singleton_method_added` is not supported.
+    pub fn call_method_added(&mut self, class_obj: Value, id: IdentId) -> Result<(), RubyError> {
+        if class_obj.as_module().unwrap().is_singleton {
+            return Ok(());
+        }
+        self.call_hook(class_obj, "method_added", Value::symbol(id))
+    }
+
+    /// Call the hook method `name` (e.g. `included`, `method_added`) of `receiver` with `arg`.
+    pub fn call_hook(&mut self, receiver: Value, name: &str, arg: Value) -> Result<(), RubyError> {
+        let id = self.globals.get_ident_id(name);
+        self.send1(receiver, id, arg)?;
+        Ok(())
+    }
+
+    /// Prevent `class_obj` from responding to the method `id`,
+    /// including the methods defined in its superclasses.
+    pub fn undef_method(&mut self, class_obj: Value, id: IdentId) -> Result<(), RubyError> {
+        if self.get_instance_method(class_obj, id).is_err() {
+            return Err(self.error_undefined_method_for_class(id, class_obj));
+        }
+        let void = self.globals.add_method(MethodInfo::Void);
+        self.add_instance_method(class_obj, id, void);
+        Ok(())
+    }
+
+    /// Change the visibility of the method `id` in `class_obj`.
+    /// A new method entry is registered to `class_obj`, so that the aliases and
+    /// the method in superclasses are not affected.
+    pub fn set_method_visibility(
+        &mut self,
+        class_obj: Value,
+        id: IdentId,
+        visibility: Visibility,
+    ) -> Result<(), RubyError> {
+        let method = match self.get_instance_method(class_obj, id) {
+            Ok(method) => method,
+            Err(_) => return Err(self.error_undefined_method_for_class(id, class_obj)),
+        };
+        if self.globals.get_method_visibility(method) == visibility {
+            return Ok(());
+        }
+        let info = self.globals.get_method_info(method).clone();
+        let new_method = self.globals.add_method(info);
+        self.globals.set_method_visibility(new_method, visibility);
+        self.add_instance_method(class_obj, id, new_method);
+        Ok(())
+    }
+
+    /// Remove the method `id` from the method table of `class_obj`.
+    /// The methods defined in its superclasses become visible again.
+    pub fn remove_method(&mut self, class_obj: Value, id: IdentId) -> Result<(), RubyError> {
+        let mut class = class_obj.as_module().unwrap();
+        match class.method_table.get(&id) {
+            Some(method) if !self.globals.is_void_method(*method) => {
+                class.method_table.remove(&id);
+                self.globals.class_version += 1;
+                Ok(())
+            }
+            _ => {
+                let class_name = self.val_inspect(class_obj);
+                let method_name = self.globals.get_ident_name(id);
+                Err(self.error_name(format!(
+                    "method `{}' not defined in {}",
+                    method_name, class_name
+                )))
+            }
+        }
+    }
+
     /// Get method(MethodRef) for receiver.
     pub fn get_method(
         &mut self,
@@ -2058,7 +3332,7 @@ impl VM {
     /// Get instance method(MethodRef) for the class object.
     pub fn get_instance_method(
         &mut self,
-        mut class: Value,
+        class: Value,
         method: IdentId,
     ) -> Result<MethodRef, RubyError> {
         match self.globals.get_method_cache_entry(class, method) {
@@ -2069,33 +3343,21 @@ impl VM {
             }
             None => {}
         };
-        let original_class = class;
-        let mut singleton_flag = original_class.as_class().is_singleton;
-        loop {
-            match class.get_instance_method(method) {
+        for module in self.get_ancestors(class) {
+            match module.as_module().unwrap().method_table.get(&method) {
+                Some(methodref) if self.globals.is_void_method(*methodref) => break,
                 Some(methodref) => {
+                    let methodref = *methodref;
                     self.globals
-                        .add_method_cache_entry(original_class, method, methodref);
+                        .add_method_cache_entry(class, method, methodref);
                     return Ok(methodref);
                 }
-                None => match class.superclass() {
-                    Some(superclass) => class = superclass,
-                    None => {
-                        if singleton_flag {
-                            singleton_flag = false;
-                            class = original_class.as_object().class();
-                        } else {
-                            let inspect = self.val_inspect(original_class);
-                            let method_name = self.globals.get_ident_name(method);
-                            return Err(self.error_nomethod(format!(
-                                "no method `{}' found for {}",
-                                method_name, inspect
-                            )));
-                        }
-                    }
-                },
-            };
+                None => {}
+            }
         }
+        let inspect = self.val_inspect(class);
+        let method_name = self.globals.get_ident_name(method);
+        Err(self.error_nomethod(format!("no method `{}' found for {}", method_name, inspect)))
     }
 
     pub fn get_singleton_class(&mut self, obj: Value) -> VMResult {
@@ -2106,31 +3368,147 @@ impl VM {
 }
 
 impl VM {
-    fn unwind_context(&mut self, err: &mut RubyError) {
-        self.context_pop().unwrap();
-        if let Some(context) = self.exec_context.last_mut() {
-            self.pc = context.pc;
-            err.info.push((self.source_info(), self.get_loc()));
+    /// Search the exception table of `iseq` for the handler which catches `err` at current pc.
+    fn get_exception_handler(&self, iseq: ISeqRef, err: &RubyError) -> Option<ExceptionEntry> {
+        let rescuable = match err.kind {
+            RubyErrorKind::RuntimeErr(_) | RubyErrorKind::Exception(_, _) => true,
+            _ => false,
         };
+        iseq.exception_table
+            .iter()
+            .find(|entry| {
+                entry.include(self.pc) && (rescuable || entry.ty == ExceptionType::Ensure)
+            })
+            .cloned()
+    }
+
+    /// Get the exception object which corresponds to `err`.
+    /// Errors occured in the VM are converted to new exception objects.
+    /// The backtrace of the exception is set from `err` if not yet set.
+    pub fn exception_from_error(&mut self, err: &RubyError) -> Value {
+        let exception = match &err.kind {
+            RubyErrorKind::Exception(exception, _) => *exception,
+            RubyErrorKind::RuntimeErr(kind) => {
+                let class = builtin::errorobj::get_error_class(&mut self.globals, kind);
+                self.create_exception(class, kind.message())
+            }
+            _ => unreachable!("Can not convert {:?} to an exception object.", err.kind),
+        };
+        let mut eref = exception.as_exception().unwrap();
+        if eref.backtrace.is_nil() {
+            let backtrace = err
+                .info
+                .iter()
+                .map(|(info, loc)| {
+                    let pos = format!("{}:{}", info.path.to_string_lossy(), info.get_line(loc));
+                    Value::string(&self.globals, pos)
+                })
+                .collect();
+            eref.backtrace = Value::array_from(&self.globals, backtrace);
+        }
+        exception
     }
 
-    pub fn fiber_send_to_parent(&mut self, val: VMResult) {
-        match &mut self.channel {
-            Some((tx, rx)) => {
-                tx.send(val.clone()).unwrap();
-                rx.recv().unwrap();
+    /// Create a new exception object of `class` with `message`.
+    pub fn create_exception(&mut self, class: Value, message: &str) -> Value {
+        let message = Value::string(&self.globals, message.to_string());
+        Value::exception(class, message)
+    }
+
+    /// Create RubyError which raises `exception`.
+    pub fn error_from_exception(&mut self, exception: Value) -> RubyError {
+        let message = builtin::errorobj::get_message(self, exception);
+        let desc = format!("{} ({})", message, self.globals.get_class_name(exception));
+        RubyError::new_exception(exception, desc, self.source_info(), self.get_loc())
+    }
+
+    /// Examine whether `val` is an instance of `module` or its descendants.
+    pub fn kind_of(&self, val: Value, module: Value) -> bool {
+        // Walk the same chain as `get_ancestors` without building the list.
+        let mut class = Some(val.get_class_object_for_method(&self.globals));
+        while let Some(c) = class {
+            if has_module(c, module) {
+                return true;
             }
-            None => return,
-        };
-        #[cfg(feature = "trace")]
-        {
-            match val {
-                Ok(val) => println!("<=== yield Ok({})", self.val_inspect(val),),
-                Err(err) => println!("<=== yield Err({:?})", err.kind),
+            class = match c.superclass() {
+                _ if c.is_module().is_some() => None,
+                Some(superclass) => Some(superclass),
+                None if c.as_module().unwrap().is_singleton => Some(c.as_object().class()),
+                None => None,
+            };
+        }
+        return false;
+
+        /// Cyclic include and prepend are rejected, so the recursion terminates.
+        fn has_module(class: Value, module: Value) -> bool {
+            if class.id() == module.id() {
+                return true;
+            }
+            let mref = class.as_module().unwrap();
+            mref.prepend.iter().any(|m| has_module(*m, module))
+                || mref.include.iter().any(|m| has_module(*m, module))
+        }
+    }
+
+    /// Push `val` on the temporary stack to preserve it from GC
+    /// while the current builtin method is running.
+    pub fn temp_push(&mut self, val: Value) {
+        self.temp_stack.push(val);
+    }
+
+    /// Push `args` on the temporary stack to preserve them from GC
+    /// while the current builtin method is running.
+    /// The Proc of a block given by `&proc` is also preserved.
+    pub fn temp_push_args(&mut self, args: &Args) {
+        self.temp_stack.extend_from_slice(args);
+        if let Some(kw_arg) = args.kw_arg {
+            self.temp_stack.push(kw_arg);
+        }
+        if let Some(block) = args.block {
+            if let MethodInfo::ProcBlock { proc } = self.globals.get_method_info(block) {
+                self.temp_stack.push(*proc);
             }
         }
     }
 
+    /// Perform GC if the number of allocated objects exceeded the threshold.
+    pub fn gc(&mut self) {
+        if !self.globals.allocator.is_gc_needed() {
+            return;
+        }
+        self.gc_start();
+    }
+
+    /// Perform GC unless GC is disabled.
+    pub fn gc_start(&mut self) {
+        if self.globals.allocator.is_disabled() {
+            return;
+        }
+        let mut alloc = self.globals.allocator;
+        let root = VMRoot(self);
+        alloc.gc(&root);
+        self.globals.sweep_proc_blocks();
+    }
+
+    /// Leave the rescue clauses entered after `errinfo_stack` had `len` elements,
+    /// restoring `$!` to the value saved by the outermost one.
+    fn truncate_errinfo(&mut self, len: usize) {
+        if self.errinfo_stack.len() > len {
+            let errinfo = self.errinfo_stack[len].errinfo;
+            self.errinfo_stack.truncate(len);
+            let id = self.globals.get_ident_id("$!");
+            self.set_global_var(id, errinfo);
+        }
+    }
+
+    fn unwind_context(&mut self, err: &mut RubyError) {
+        self.context_pop().unwrap();
+        if let Some(context) = self.exec_context.last_mut() {
+            self.pc = context.pc;
+            err.info.push((self.source_info(), self.get_loc()));
+        };
+    }
+
     /// Get local variable table.
     fn get_outer_context(&mut self, outer: u32) -> ContextRef {
         let mut context = self.context();
@@ -2145,7 +3523,7 @@ impl VM {
         if index < 0 {
             let i = len as i64 + index;
             if i < 0 {
-                return Err(self.error_unimplemented("Index out of range."));
+                return Err(self.error_index("Index out of range."));
             };
             Ok(i as usize)
         } else {
@@ -2153,14 +3531,18 @@ impl VM {
         }
     }
 
-    fn pop_key_value_pair(&mut self, arg_num: usize) -> HashMap<HashKey, Value> {
-        let mut hash = HashMap::new();
-        for _ in 0..arg_num {
-            let value = self.stack_pop();
-            let key = self.stack_pop();
-            hash.insert(HashKey(key), value);
-        }
-        hash
+    fn pop_key_value_pair(&mut self, arg_num: usize) -> Result<OrderedMap<HashKey>, RubyError> {
+        let mut hash = OrderedMap::new();
+        let len = self.exec_stack.len();
+        let start = len - arg_num * 2;
+        let res = HashInfo::catch_key_error(|| {
+            for pair in self.exec_stack[start..].chunks(2) {
+                hash.insert(HashKey(pair[0]), pair[1]);
+            }
+        });
+        self.exec_stack.truncate(start);
+        res?;
+        Ok(hash)
     }
 
     fn pop_args_to_ary(&mut self, arg_num: usize) -> Args {
@@ -2203,11 +3585,42 @@ impl VM {
     /// Create new Proc object from `method`,
     /// moving outer `Context`s on stack to heap.
     pub fn create_proc(&mut self, method: MethodRef) -> VMResult {
+        match self.globals.get_method_info(method) {
+            MethodInfo::SymbolProc { id } => {
+                let pref = ProcRef::new(ProcInfo {
+                    is_lambda: true,
+                    kind: ProcKind::Symbol(*id),
+                });
+                return Ok(Value::procobj_from(&self.globals, pref));
+            }
+            MethodInfo::ProcBlock { proc } => return Ok(*proc),
+            MethodInfo::NativeBlock { .. } => {
+                let id = self.globals.get_ident_id("native_block");
+                let method = Value::method(&self.globals, id, Value::nil(), method);
+                let pref = ProcRef::new(ProcInfo {
+                    is_lambda: false,
+                    kind: ProcKind::Method(method),
+                });
+                return Ok(Value::procobj_from(&self.globals, pref));
+            }
+            _ => {}
+        }
         self.move_outer_to_heap();
         let context = self.create_block_context(method)?;
         Ok(Value::procobj(&self.globals, context))
     }
 
+    /// Create a lambda from the block `method`.
+    /// A block converted from a Proc object keeps the lambda-ness of the Proc.
+    pub fn create_lambda(&mut self, method: MethodRef) -> VMResult {
+        if let MethodInfo::RubyFunc { .. } = self.globals.get_method_info(method) {
+            let context = self.create_block_context(method)?;
+            Ok(Value::lambda(&self.globals, context))
+        } else {
+            self.create_proc(method)
+        }
+    }
+
     /// Move outer execution contexts on the stack to the heap.
     fn move_outer_to_heap(&mut self) {
         let mut prev_ctx: Option<ContextRef> = None;
@@ -2236,6 +3649,12 @@ impl VM {
         Ok(ContextRef::from(outer.self_value, None, iseq, Some(outer)))
     }
 
+    /// Create a copy of the current context on the heap, which outlives the current method call.
+    pub fn dup_context(&mut self) -> ContextRef {
+        self.move_outer_to_heap();
+        self.context().dup()
+    }
+
     pub fn get_iseq(&self, method: MethodRef) -> Result<ISeqRef, RubyError> {
         self.globals.get_method_info(method).as_iseq(&self)
     }
@@ -32,12 +32,47 @@ impl MethodRef {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    Public,
+    Protected,
+    Private,
+}
+
 #[derive(Clone)]
 pub enum MethodInfo {
-    RubyFunc { iseq: ISeqRef },
-    AttrReader { id: IdentId },
-    AttrWriter { id: IdentId },
-    BuiltinFunc { name: String, func: BuiltinFunc },
+    RubyFunc {
+        iseq: ISeqRef,
+    },
+    AttrReader {
+        id: IdentId,
+    },
+    AttrWriter {
+        id: IdentId,
+    },
+    BuiltinFunc {
+        name: String,
+        func: BuiltinFunc,
+    },
+    /// A marker of an undefined method. Method search stops here.
+    Void,
+    /// A block converted from a Symbol by `&:sym`, which calls the method `id` of the first argument.
+    SymbolProc {
+        id: IdentId,
+    },
+    /// A block converted from a Proc object which has no block context of its own.
+    ProcBlock {
+        proc: Value,
+    },
+    /// A block which calls the closure given to `VM::eval_with_native_block` at `depth` of the nesting.
+    NativeBlock {
+        depth: usize,
+    },
+    /// A method `id` defined by `define_method`, which evaluates a Proc object with self of the receiver.
+    ProcMethod {
+        id: IdentId,
+        proc: Value,
+    },
 }
 
 impl MethodInfo {
@@ -69,6 +104,11 @@ impl std::fmt::Debug for MethodInfo {
             MethodInfo::AttrReader { id } => write!(f, "AttrReader {:?}", id),
             MethodInfo::AttrWriter { id } => write!(f, "AttrWriter {:?}", id),
             MethodInfo::BuiltinFunc { name, .. } => write!(f, "BuiltinFunc {:?}", name),
+            MethodInfo::Void => write!(f, "Void"),
+            MethodInfo::SymbolProc { id } => write!(f, "SymbolProc {:?}", id),
+            MethodInfo::ProcBlock { proc } => write!(f, "ProcBlock {:?}", proc),
+            MethodInfo::NativeBlock { depth } => write!(f, "NativeBlock {:?}", depth),
+            MethodInfo::ProcMethod { id, proc } => write!(f, "ProcMethod {:?} {:?}", id, proc),
         }
     }
 }
@@ -88,6 +128,9 @@ pub struct ISeqInfo {
     /// Set to Some() in class definition context, or None in the top level.
     pub class_defined: Option<ClassListRef>,
     pub iseq_sourcemap: Vec<(ISeqPos, Loc)>,
+    /// Exception handlers for `rescue` and `ensure` clauses.
+    /// Inner handlers precede outer ones.
+    pub exception_table: Vec<ExceptionEntry>,
     pub source_info: SourceInfoRef,
     pub kind: ISeqKind,
 }
@@ -101,6 +144,65 @@ pub struct ISeqParams {
     pub block_param: bool,
     pub param_ident: Vec<IdentId>,
     pub keyword_params: HashMap<IdentId, LvarId>,
+    /// Keyword params without default values, in the order of declaration.
+    pub required_keywords: Vec<IdentId>,
+    pub kwrest_param: Option<LvarId>,
+}
+
+impl ISeqParams {
+    pub fn default() -> Self {
+        ISeqParams {
+            req_params: 0,
+            opt_params: 0,
+            rest_param: false,
+            post_params: 0,
+            block_param: false,
+            param_ident: vec![],
+            keyword_params: HashMap::new(),
+            required_keywords: vec![],
+            kwrest_param: None,
+        }
+    }
+
+    /// Return true if the method or block accepts keyword arguments.
+    pub fn has_keyword_params(&self) -> bool {
+        !self.keyword_params.is_empty() || self.kwrest_param.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExceptionEntry {
+    pub ty: ExceptionType,
+    /// The start position of the protected region (inclusive).
+    pub start: ISeqPos,
+    /// The end position of the protected region (exclusive).
+    pub end: ISeqPos,
+    /// The position of the handler.
+    pub dest: ISeqPos,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExceptionType {
+    /// Catch exceptions and push the exception object on the stack.
+    Rescue,
+    /// Catch any kind of error, including `return` from inner blocks,
+    /// and keep the error to re-raise it after the ensure clause.
+    Ensure,
+}
+
+impl ExceptionEntry {
+    pub fn new(ty: ExceptionType, start: ISeqPos, end: ISeqPos, dest: ISeqPos) -> Self {
+        ExceptionEntry {
+            ty,
+            start,
+            end,
+            dest,
+        }
+    }
+
+    pub fn include(&self, pc: usize) -> bool {
+        self.start.to_usize() <= pc && pc < self.end.to_usize()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -129,36 +231,24 @@ pub enum ISeqKind {
 impl ISeqInfo {
     pub fn new(
         method: MethodRef,
-        req_params: usize,
-        opt_params: usize,
-        rest_param: bool,
-        post_params: usize,
-        block_param: bool,
-        param_ident: Vec<IdentId>,
-        keyword_params: HashMap<IdentId, LvarId>,
+        params: ISeqParams,
         iseq: ISeq,
         lvar: LvarCollector,
         iseq_sourcemap: Vec<(ISeqPos, Loc)>,
+        exception_table: Vec<ExceptionEntry>,
         source_info: SourceInfoRef,
         kind: ISeqKind,
     ) -> Self {
         let lvars = lvar.len();
         ISeqInfo {
             method,
-            params: ISeqParams {
-                req_params,
-                opt_params,
-                rest_param,
-                post_params,
-                block_param,
-                param_ident,
-                keyword_params,
-            },
+            params,
             iseq,
             lvar,
             lvars,
             class_defined: None,
             iseq_sourcemap,
+            exception_table,
             source_info,
             kind,
         }
@@ -167,16 +257,11 @@ impl ISeqInfo {
     pub fn default(method: MethodRef) -> Self {
         ISeqInfo::new(
             method,
-            0,
-            0,
-            false,
-            0,
-            false,
-            vec![],
-            std::collections::HashMap::new(),
+            ISeqParams::default(),
             vec![],
             LvarCollector::new(),
             vec![],
+            vec![],
             SourceInfoRef::empty(),
             ISeqKind::Method(IdentId::from(0)),
         )
@@ -193,7 +278,32 @@ impl ISeqInfo {
 #[derive(Debug, Clone)]
 pub struct GlobalMethodTable {
     table: Vec<MethodInfo>,
+    /// Visibility of each method entry.
+    visibility: Vec<Visibility>,
     method_id: u32,
+    /// Entries released by `remove_method`, which are reused by `add_method`.
+    free_list: Vec<MethodRef>,
+}
+
+impl GC for GlobalMethodTable {
+    fn mark(&self, alloc: &mut Allocator) {
+        for info in &self.table {
+            match info {
+                MethodInfo::RubyFunc { iseq } => {
+                    let mut class_list = iseq.class_defined;
+                    while let Some(list) = class_list {
+                        list.class.mark(alloc);
+                        class_list = list.outer;
+                    }
+                }
+                // The proc of ProcBlock is not marked here. It is kept alive by the contexts
+                // and the builtin calls which use the block, and the block is removed
+                // by `Globals::sweep_proc_blocks` when the proc is freed.
+                MethodInfo::ProcMethod { proc, .. } => proc.mark(alloc),
+                _ => {}
+            }
+        }
+    }
 }
 
 impl GlobalMethodTable {
@@ -202,21 +312,36 @@ impl GlobalMethodTable {
             table: vec![MethodInfo::AttrReader {
                 id: IdentId::from(1),
             }],
+            visibility: vec![Visibility::Public],
             method_id: 1,
+            free_list: vec![],
         }
     }
 
     pub fn add_method(&mut self, info: MethodInfo) -> MethodRef {
+        if let Some(method) = self.free_list.pop() {
+            self.table[method.0 as usize] = info;
+            self.visibility[method.0 as usize] = Visibility::Public;
+            return method;
+        }
         let new_method = MethodRef(self.method_id);
         self.method_id += 1;
         self.table.push(info);
+        self.visibility.push(Visibility::Public);
         new_method
     }
 
+    /// Release the entry of `method`, which must not be referred anymore.
+    pub fn remove_method(&mut self, method: MethodRef) {
+        self.table[method.0 as usize] = MethodInfo::Void;
+        self.free_list.push(method);
+    }
+
     pub fn new_method(&mut self) -> MethodRef {
         let new_method = MethodRef(self.method_id);
         self.method_id += 1;
         self.table.push(MethodInfo::default());
+        self.visibility.push(Visibility::Public);
         new_method
     }
 
@@ -231,6 +356,14 @@ impl GlobalMethodTable {
     pub fn get_mut_method(&mut self, method: MethodRef) -> &mut MethodInfo {
         &mut self.table[method.0 as usize]
     }
+
+    pub fn get_visibility(&self, method: MethodRef) -> Visibility {
+        self.visibility[method.0 as usize]
+    }
+
+    pub fn set_visibility(&mut self, method: MethodRef, visibility: Visibility) {
+        self.visibility[method.0 as usize] = visibility;
+    }
 }
 
 //----------------------------------------------------------------------------------
@@ -21,19 +21,21 @@ impl Inst {
     pub const TEQ: u8 = 17;
     pub const GT: u8 = 18;
     pub const GE: u8 = 19;
-    pub const NOT: u8 = 20;
-    pub const SHR: u8 = 21;
-    pub const SHL: u8 = 22;
-    pub const BIT_OR: u8 = 23;
-    pub const BIT_AND: u8 = 24;
-    pub const BIT_XOR: u8 = 25;
-    pub const BIT_NOT: u8 = 26;
-    pub const POW: u8 = 27;
-    pub const CMP: u8 = 28;
-
-    pub const ADDI: u8 = 30;
-    pub const SUBI: u8 = 31;
-    pub const IVAR_ADDI: u8 = 32;
+    pub const LT: u8 = 20;
+    pub const LE: u8 = 21;
+    pub const NOT: u8 = 22;
+    pub const SHR: u8 = 23;
+    pub const SHL: u8 = 24;
+    pub const BIT_OR: u8 = 25;
+    pub const BIT_AND: u8 = 26;
+    pub const BIT_XOR: u8 = 27;
+    pub const BIT_NOT: u8 = 28;
+    pub const POW: u8 = 29;
+    pub const CMP: u8 = 30;
+
+    pub const ADDI: u8 = 33;
+    pub const SUBI: u8 = 34;
+    pub const IVAR_ADDI: u8 = 35;
 
     pub const SET_LOCAL: u8 = 40;
     pub const GET_LOCAL: u8 = 41;
@@ -50,9 +52,21 @@ impl Inst {
     pub const SET_INDEX: u8 = 51;
 
     pub const CHECK_LOCAL: u8 = 52;
+    pub const DEFINED_IVAR: u8 = 53;
+    pub const DEFINED_GVAR: u8 = 54;
+    pub const DEFINED_CONST: u8 = 55;
+    pub const DEFINED_SCOPE: u8 = 56;
+    pub const DEFINED_METHOD: u8 = 57;
+    pub const DEFINED_YIELD: u8 = 58;
+    pub const DEFINED_SUPER: u8 = 59;
 
     pub const SEND: u8 = 60;
     pub const SEND_SELF: u8 = 61;
+    pub const SUPER: u8 = 62;
+
+    pub const GET_CVAR: u8 = 65;
+    pub const SET_CVAR: u8 = 66;
+    pub const DEFINED_CVAR: u8 = 67;
 
     pub const CREATE_RANGE: u8 = 70;
     pub const CREATE_ARRAY: u8 = 71;
@@ -70,6 +84,10 @@ impl Inst {
     pub const DEF_CLASS: u8 = 90;
     pub const DEF_METHOD: u8 = 91;
     pub const DEF_SMETHOD: u8 = 92;
+    pub const ALIAS_METHOD: u8 = 93;
+    pub const ALIAS_GVAR: u8 = 94;
+    pub const UNDEF_METHOD: u8 = 95;
+    pub const DEF_SCLASS: u8 = 96;
 
     pub const JMP: u8 = 100;
     pub const JMP_IF_FALSE: u8 = 101;
@@ -78,6 +96,12 @@ impl Inst {
     pub const OPT_CASE: u8 = 104;
     pub const MRETURN: u8 = 105;
     pub const YIELD: u8 = 106;
+    pub const RESCUE: u8 = 107;
+    pub const RAISE: u8 = 108;
+    pub const ENSURE_END: u8 = 109;
+    pub const TRY: u8 = 110;
+    pub const DISCARD_ERR: u8 = 111;
+    pub const RESTORE_ERRINFO: u8 = 112;
 }
 
 #[allow(dead_code)]
@@ -103,6 +127,8 @@ impl Inst {
             Inst::TEQ => "TEQ",
             Inst::GT => "GT",
             Inst::GE => "GE",
+            Inst::LT => "LT",
+            Inst::LE => "LE",
             Inst::CMP => "CMP",
             Inst::NOT => "NOT",
             Inst::SHR => "SHR",
@@ -133,8 +159,20 @@ impl Inst {
 
             Inst::SEND => "SEND",
             Inst::SEND_SELF => "SEND_SELF",
+            Inst::SUPER => "SUPER",
+
+            Inst::GET_CVAR => "GET_CVAR",
+            Inst::SET_CVAR => "SET_CVAR",
+            Inst::DEFINED_CVAR => "DEFINED_CVAR",
 
             Inst::CHECK_LOCAL => "CHECK_LOCAL",
+            Inst::DEFINED_IVAR => "DEFINED_IVAR",
+            Inst::DEFINED_GVAR => "DEFINED_GVAR",
+            Inst::DEFINED_CONST => "DEFINED_CONST",
+            Inst::DEFINED_SCOPE => "DEFINED_SCOPE",
+            Inst::DEFINED_METHOD => "DEFINED_METHOD",
+            Inst::DEFINED_YIELD => "DEFINED_YIELD",
+            Inst::DEFINED_SUPER => "DEFINED_SUPER",
 
             Inst::CREATE_RANGE => "CREATE_RANGE",
             Inst::CREATE_ARRAY => "CREATE_ARRAY",
@@ -152,6 +190,10 @@ impl Inst {
             Inst::DEF_CLASS => "DEF_CLASS",
             Inst::DEF_METHOD => "DEF_METHOD",
             Inst::DEF_SMETHOD => "DEF_CMETHOD",
+            Inst::ALIAS_METHOD => "ALIAS_METHOD",
+            Inst::ALIAS_GVAR => "ALIAS_GVAR",
+            Inst::UNDEF_METHOD => "UNDEF_METHOD",
+            Inst::DEF_SCLASS => "DEF_SCLASS",
 
             Inst::JMP => "JMP",
             Inst::JMP_IF_FALSE => "JMP_IF_FALSE",
@@ -160,6 +202,12 @@ impl Inst {
             Inst::OPT_CASE => "OPT_CASE",
             Inst::MRETURN => "MRETURN",
             Inst::YIELD => "YIELD",
+            Inst::RESCUE => "RESCUE",
+            Inst::RAISE => "RAISE",
+            Inst::ENSURE_END => "ENSURE_END",
+            Inst::TRY => "TRY",
+            Inst::DISCARD_ERR => "DISCARD_ERR",
+            Inst::RESTORE_ERRINFO => "RESTORE_ERRINFO",
 
             _ => "undefined",
         }
@@ -179,6 +227,8 @@ impl Inst {
             | Inst::NE
             | Inst::GT
             | Inst::GE
+            | Inst::LT
+            | Inst::LE
             | Inst::CMP
             | Inst::NOT
             | Inst::SHR
@@ -193,7 +243,14 @@ impl Inst {
             | Inst::SPLAT
             | Inst::POP
             | Inst::RETURN
-            | Inst::MRETURN => 1,
+            | Inst::MRETURN
+            | Inst::RAISE
+            | Inst::ENSURE_END
+            | Inst::DEFINED_YIELD
+            | Inst::DEFINED_SUPER
+            | Inst::TRY
+            | Inst::DISCARD_ERR
+            | Inst::RESTORE_ERRINFO => 1,
                                         // operand
             Inst::PUSH_STRING           // IdentId: u32
             | Inst::PUSH_SYMBOL         // IdentId: u32
@@ -203,6 +260,8 @@ impl Inst {
             | Inst::GET_SCOPE           // IdentId: u32
             | Inst::GET_IVAR            // IdentId: u32
             | Inst::SET_IVAR            // IdentId: u32
+            | Inst::GET_CVAR            // IdentId: u32
+            | Inst::SET_CVAR            // IdentId: u32
             | Inst::GET_GVAR            // IdentId: u32
             | Inst::SET_GVAR            // IdentId: u32
             | Inst::GET_INDEX
@@ -221,6 +280,15 @@ impl Inst {
             | Inst::SHL                 // inline cache: u32
             | Inst::CREATE_HASH         // number of items: u32
             | Inst::YIELD               // number of items: u32
+            | Inst::RESCUE              // number of items: u32
+            | Inst::UNDEF_METHOD        // IdentId: u32
+            | Inst::DEF_SCLASS          // method: u32
+            | Inst::DEFINED_IVAR        // IdentId: u32
+            | Inst::DEFINED_GVAR        // IdentId: u32
+            | Inst::DEFINED_CVAR        // IdentId: u32
+            | Inst::DEFINED_CONST       // IdentId: u32
+            | Inst::DEFINED_SCOPE       // IdentId: u32
+            | Inst::DEFINED_METHOD      // IdentId: u32
             => 5,
 
             Inst::PUSH_FIXNUM
@@ -229,7 +297,10 @@ impl Inst {
             | Inst::GET_LOCAL
             | Inst::DEF_METHOD
             | Inst::DEF_SMETHOD
+            | Inst::ALIAS_METHOD
+            | Inst::ALIAS_GVAR
             | Inst::OPT_CASE
+            | Inst::SUPER
             | Inst::IVAR_ADDI => 9,
             Inst::DEF_CLASS => 10,
             Inst::SEND | Inst::SEND_SELF => 17,
@@ -254,6 +325,8 @@ impl Inst {
             | Inst::NE
             | Inst::GT
             | Inst::GE
+            | Inst::LT
+            | Inst::LE
             | Inst::CMP
             | Inst::NOT
             | Inst::SHR
@@ -269,7 +342,14 @@ impl Inst {
             | Inst::TO_S
             | Inst::SPLAT
             | Inst::POP
-            | Inst::YIELD => format!("{}", Inst::inst_name(iseq[pc])),
+            | Inst::YIELD
+            | Inst::RAISE
+            | Inst::ENSURE_END
+            | Inst::DEFINED_YIELD
+            | Inst::DEFINED_SUPER
+            | Inst::TRY
+            | Inst::DISCARD_ERR
+            | Inst::RESTORE_ERRINFO => format!("{}", Inst::inst_name(iseq[pc])),
             Inst::PUSH_STRING => format!("PUSH_STRING {}", Inst::read32(iseq, pc + 1) as i32),
             Inst::PUSH_SYMBOL => format!("PUSH_SYMBOL {}", Inst::read32(iseq, pc + 1) as i32),
             Inst::ADDI => format!("ADDI {}", Inst::read32(iseq, pc + 1) as i32),
@@ -329,6 +409,8 @@ impl Inst {
             Inst::GET_SCOPE => format!("GET_SCOPE '{}'", Inst::ident_name(globals, iseq, pc + 1)),
             Inst::GET_IVAR => format!("GET_IVAR '{}'", Inst::ident_name(globals, iseq, pc + 1)),
             Inst::SET_IVAR => format!("SET_IVAR '{}'", Inst::ident_name(globals, iseq, pc + 1)),
+            Inst::GET_CVAR => format!("GET_CVAR '{}'", Inst::ident_name(globals, iseq, pc + 1)),
+            Inst::SET_CVAR => format!("SET_CVAR '{}'", Inst::ident_name(globals, iseq, pc + 1)),
             Inst::GET_INDEX => format!("GET_INDEX {} items", Inst::read32(iseq, pc + 1)),
             Inst::SET_INDEX => format!("SET_INDEX {} items", Inst::read32(iseq, pc + 1)),
             Inst::SEND => format!(
@@ -341,12 +423,14 @@ impl Inst {
                 Inst::ident_name(globals, iseq, pc + 1),
                 Inst::read32(iseq, pc + 5)
             ),
+            Inst::SUPER => format!("SUPER {} items", Inst::read16(iseq, pc + 1)),
 
             Inst::CREATE_ARRAY => format!("CREATE_ARRAY {} items", Inst::read32(iseq, pc + 1)),
             Inst::CREATE_PROC => format!("CREATE_PROC method:{}", Inst::read32(iseq, pc + 1)),
             Inst::CREATE_HASH => format!("CREATE_HASH {} items", Inst::read32(iseq, pc + 1)),
             Inst::DUP => format!("DUP {}", Inst::read32(iseq, pc + 1)),
             Inst::TAKE => format!("TAKE {}", Inst::read32(iseq, pc + 1)),
+            Inst::RESCUE => format!("RESCUE {} items", Inst::read32(iseq, pc + 1)),
             Inst::DEF_CLASS => format!(
                 "DEF_CLASS {} '{}' method:{}",
                 if Inst::read8(iseq, pc + 1) == 1 {
@@ -357,10 +441,34 @@ impl Inst {
                 Inst::ident_name(globals, iseq, pc + 2),
                 Inst::read32(iseq, pc)
             ),
+            Inst::DEF_SCLASS => format!("DEF_SCLASS method:{}", Inst::read32(iseq, pc + 1)),
             Inst::DEF_METHOD => format!("DEF_METHOD '{}'", Inst::ident_name(globals, iseq, pc + 1)),
             Inst::DEF_SMETHOD => {
                 format!("DEF_SMETHOD '{}'", Inst::ident_name(globals, iseq, pc + 1))
             }
+            Inst::ALIAS_METHOD => format!(
+                "ALIAS_METHOD '{}' '{}'",
+                Inst::ident_name(globals, iseq, pc + 1),
+                Inst::ident_name(globals, iseq, pc + 5)
+            ),
+            Inst::ALIAS_GVAR => format!(
+                "ALIAS_GVAR '{}' '{}'",
+                Inst::ident_name(globals, iseq, pc + 1),
+                Inst::ident_name(globals, iseq, pc + 5)
+            ),
+            Inst::DEFINED_IVAR
+            | Inst::DEFINED_GVAR
+            | Inst::DEFINED_CVAR
+            | Inst::DEFINED_CONST
+            | Inst::DEFINED_SCOPE
+            | Inst::DEFINED_METHOD => format!(
+                "{} '{}'",
+                Inst::inst_name(iseq[pc]),
+                Inst::ident_name(globals, iseq, pc + 1)
+            ),
+            Inst::UNDEF_METHOD => {
+                format!("UNDEF_METHOD '{}'", Inst::ident_name(globals, iseq, pc + 1))
+            }
             _ => format!("undefined"),
         }
     }
@@ -375,6 +483,11 @@ impl Inst {
         unsafe { *ptr }
     }
 
+    fn read16(iseq: &ISeq, pc: usize) -> u16 {
+        let ptr = iseq[pc..pc + 1].as_ptr() as *const u16;
+        unsafe { *ptr }
+    }
+
     fn read8(iseq: &ISeq, pc: usize) -> u8 {
         iseq[pc]
     }
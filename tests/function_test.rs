@@ -137,3 +137,53 @@ fn splat_argument() {
     "#;
     assert_script(program);
 }
+
+#[test]
+fn keyword_argument() {
+    let program = r#"
+        def foo(a, b = 2, *rest, c:, d: 4, **opts)
+            [a, b, rest, c, d, opts]
+        end
+
+        assert [1, 2, [], 3, 4, {}], foo(1, c: 3)
+        assert [1, 5, [6, 7], 3, 8, {e: 9}], foo(1, 5, 6, 7, d: 8, c: 3, e: 9)
+        h = {c: 10, f: 11}
+        assert [1, 2, [], 10, 12, {f: 11}], foo(1, **h, d: 12)
+        assert [1, 2, [], 13, 4, {f: 11}], foo(1, **h, c: 13)
+        assert({c: 10, f: 11}, h)
+
+        def bar(x:, y:)
+            x - y
+        end
+        assert 3, bar(y: 2, x: 5)
+        assert 3, bar(**{x: 5, y: 2})
+
+        def baz(**opts)
+            opts
+        end
+        assert({}, baz)
+        assert({a: 1}, baz(a: 1))
+
+        def qux(h)
+            h
+        end
+        assert({a: 1}, qux(a: 1))
+
+        begin
+            bar(x: 1)
+        rescue ArgumentError => e
+            assert "missing keyword: :y", e.message
+        end
+        begin
+            bar
+        rescue ArgumentError => e
+            assert "missing keywords: :x, :y", e.message
+        end
+        begin
+            bar(x: 1, y: 2, z: 3)
+        rescue ArgumentError => e
+            assert "unknown keyword: :z", e.message
+        end
+    "#;
+    assert_script(program);
+}
@@ -75,6 +75,23 @@ fn array_lit1() {
 fn percent_notation() {
     let program = r#"
     assert(%w(We are the champions), ["We", "are", "the", "champions"])
+    x = 5
+    assert(["a", "b\#{x}", "c d", "e\\f"], %w[ a  b#{x}
+      c\ d e\\f ])
+    assert(["a", "b5", "c d", "5"], %W{a b#{x} c\ d #{x}})
+    assert([:foo, :"b\#{x}"], %i<foo b#{x}>)
+    assert([:foo, :b5], %I|foo b#{x}|)
+    assert([], %w())
+    assert("it's (nested) \#{x}", %q(it's (nested) #{x}))
+    assert("value: 5\n", %Q!value: #{x}\n!)
+    assert("paren (5)", %(paren (#{x})))
+    assert("a{b}", %q{a{b}})
+    assert("a)b", %q(a\)b))
+    assert(:sym, %s(sym))
+    assert(true, %r{ab+c}i === "xABBC")
+    assert(true, %r[a#{x}b] === "a5b")
+    assert("hello\n", %x(echo hello))
+    assert(1, 11 %5)
     "#;
     assert_script(program);
 }
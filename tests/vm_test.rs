@@ -968,3 +968,496 @@ fn singleton() {
         ";
     assert_script(program);
 }
+
+#[test]
+fn rescue1() {
+    let program = "
+    def foo
+      begin
+        1 + nil
+      rescue Integer, TypeError => e
+        :rescued
+      else
+        :else
+      end
+    end
+    def bar
+      begin
+        3
+      rescue
+        4
+      else
+        5
+      end
+    end
+    assert(:rescued, foo)
+    assert(5, bar)
+    assert(42, (undefined_method rescue 42))
+    assert([1, 2, 3], [1, begin; undefined_method; rescue; 2; end, 3])
+    assert([2, 4], [1, 2].map do |x| x.undefined_method rescue x * 2 end)
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn rescue_unmatched() {
+    let program = "
+    def foo
+      begin
+        [].undefined_method(3)
+      rescue TypeError
+        :rescued
+      end
+    end
+    assert(NoMethodError, (begin; foo; rescue => e; e.class; end))
+    foo
+        ";
+    let mut vm = VM::new();
+    let err = vm
+        .run(std::path::PathBuf::from(""), program, None)
+        .unwrap_err();
+    match &err.kind {
+        RubyErrorKind::RuntimeErr(RuntimeErrKind::NoMethod(_)) => {}
+        kind => panic!("Unexpected error: {:?}", kind),
+    }
+    assert_eq!(
+        program.find("[].undefined_method(3)").unwrap() as u32,
+        err.loc().0
+    );
+}
+
+#[test]
+fn ensure1() {
+    let program = "
+    $r = []
+    def foo
+      [1, 2].each do |x|
+        begin
+          return x
+        ensure
+          $r << :ensure
+        end
+      end
+    end
+    assert(1, foo)
+    assert([:ensure], $r)
+    i = 0
+    while i < 3
+      begin
+        i += 1
+        next if i == 1
+        break if i == 2
+      ensure
+        $r << i
+      end
+    end
+    assert([:ensure, 1, 2], $r)
+    def bar
+      begin
+        begin
+          undefined_method
+        ensure
+          $r << :inner
+        end
+      rescue
+        $r << :rescue
+      end
+      10
+    ensure
+      $r << :method
+    end
+    assert(10, bar)
+    assert([:ensure, 1, 2, :inner, :rescue, :method], $r)
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn rescue_modifier() {
+    let program = r#"
+    a = raise rescue 1
+    assert(1, a)
+    b = 5
+    b += nil.foo rescue 3
+    assert(8, b)
+    c = nil
+    c ||= raise("e") rescue 6
+    assert(6, c)
+    def foo
+      x = undefined_method rescue 2
+      x
+    end
+    assert(2, foo)
+        "#;
+    assert_script(program);
+}
+
+#[test]
+fn ensure_escape() {
+    let program = r#"
+    def foo
+      begin
+        raise "inner"
+      ensure
+        return :ensured
+      end
+    end
+    assert(:ensured, foo)
+    def bar
+      begin
+        begin
+          raise "a"
+        ensure
+          raise "b"
+        end
+      rescue => e
+        e.message
+      end
+    end
+    assert("b", bar)
+    r = []
+    3.times do |i|
+      while true
+        begin
+          raise "x"
+        ensure
+          break
+        end
+      end
+      r << i
+    end
+    assert([0, 1, 2], r)
+    e = begin
+      begin
+        raise "one"
+      ensure
+        r << :ensure
+      end
+    rescue => e
+      e
+    end
+    assert("one", e.message)
+    assert([0, 1, 2, :ensure], r)
+        "#;
+    assert_script(program);
+}
+
+#[test]
+fn defined() {
+    let program = r#"
+    module Outer
+      Inner = 1
+    end
+    class Foo
+      def initialize
+        @a = 1
+      end
+      def foo
+        [defined?(@a), defined?(@b), defined?(yield), defined?(super)]
+      end
+      def to_s
+        defined?(super)
+      end
+    end
+    a = 1
+    $g = 2
+    assert("local-variable", defined?(a))
+    assert(nil, defined?(b))
+    assert("global-variable", defined?($g))
+    assert(nil, defined?($undefined))
+    assert("constant", defined?(Foo))
+    assert("constant", defined?(::Foo))
+    assert("constant", defined?(Outer::Inner))
+    assert(nil, defined?(Outer::Nothing))
+    assert(nil, defined?(Nothing::Inner))
+    assert(nil, defined?(Nothing))
+    assert("method", defined?(puts))
+    assert("method", defined?(a.to_s))
+    assert(nil, defined?(a.nothing))
+    assert(nil, defined?(b.to_s))
+    assert("method", defined?(1 + 1))
+    assert("method", defined?(a != 2))
+    assert(nil, defined?(b + 1))
+    assert("expression", defined?(a && b))
+    assert("assignment", defined?(c = 1))
+    assert("self", defined?(self))
+    assert("nil", defined?(nil))
+    assert("true", defined? true)
+    assert(["instance-variable", nil, nil, nil], Foo.new.foo)
+    assert(["instance-variable", nil, "yield", nil], Foo.new.foo {})
+    assert("super", Foo.new.to_s)
+    $count = 0
+    def count
+      $count += 1
+    end
+    defined?(count)
+    assert(0, $count)
+    def boom
+      raise "boom"
+    end
+    assert(nil, defined?(boom.foo))
+    assert(nil, defined?(boom.boom.foo))
+    assert(nil, defined?(Foo.new.foo.nothing))
+    assert("method", defined?(Foo.new.foo.size))
+    p defined?(a)
+    p defined? String
+    x = defined? String
+    assert("constant", x)
+    assert("method", [1].map { defined?(puts) }[0])
+        "#;
+    assert_script(program);
+}
+
+#[test]
+fn heredoc() {
+    let program = r##"
+    a = <<EOS
+  foo
+bar
+EOS
+    assert("  foo\nbar\n", a)
+    b = <<-EOS
+      baz
+      EOS
+    assert("      baz\n", b)
+    x = 42
+    c = <<~EOS
+      x = #{x}
+        indented\t#{x + 1}
+
+      end
+    EOS
+    assert("x = 42\n  indented\t43\n\nend\n", c)
+    d = <<~'EOS'
+      raw #{x}\t
+    EOS
+    assert("raw \#{x}\\t\n", d)
+    e = <<~"EOS".upcase
+      quoted #{x}
+    EOS
+    assert("QUOTED 42\n", e)
+    f = [<<~ONE, <<~TWO, 3]
+      one
+    ONE
+      two #{x}
+    TWO
+    assert(["one\n", "two 42\n", 3], f)
+    def g(s)
+      s.size
+    end
+    assert(4, g <<-EOS)
+abc
+    EOS
+    assert(8, 1 << 3)
+    "##;
+    assert_script(program);
+}
+
+#[test]
+fn class_var() {
+    let program = r#"
+    class Foo
+      @@count = 0
+      def initialize
+        @@count += 1
+      end
+      def count
+        @@count
+      end
+      def self.count
+        @@count
+      end
+      def self.reset
+        @@count = 0
+      end
+      def self.defined
+        [defined?(@@count), defined?(@@nothing)]
+      end
+      def foo_bar
+        @@bar
+      end
+    end
+    class Bar < Foo
+      def bar_count
+        @@count
+      end
+      def set_bar(x)
+        @@bar = x
+      end
+      def bar
+        @@bar
+      end
+    end
+    Foo.new
+    Foo.new
+    b = Bar.new
+    assert(3, Foo.count)
+    assert(3, b.count)
+    assert(3, b.bar_count)
+    Foo.reset
+    assert(0, b.bar_count)
+    b.set_bar(7)
+    assert(7, b.bar)
+    assert(false, Foo.class_variable_defined?(:@@bar))
+    module M
+      @@mod = 1
+    end
+    class Baz
+      include M
+      def mod
+        @@mod
+      end
+    end
+    assert(1, Baz.new.mod)
+    assert(["class variable", nil], Foo.defined)
+    e = begin
+      Foo.new.foo_bar
+    rescue NameError => e
+      e
+    end
+    assert(NameError, e.class)
+    assert("uninitialized class variable @@bar in Foo", e.message)
+    "#;
+    assert_script(program);
+}
+
+#[test]
+fn singleton_class_def() {
+    let program = r#"
+    class Foo
+      @@count = 5
+      class << self
+        attr_accessor :config
+        def create
+          helper + count
+        end
+        def count
+          @@count
+        end
+        private
+        def helper
+          10
+        end
+      end
+    end
+    assert(15, Foo.create)
+    Foo.config = :debug
+    assert(:debug, Foo.config)
+    e = begin
+      Foo.helper
+    rescue NoMethodError => e
+      e
+    end
+    assert(NoMethodError, e.class)
+    obj = Object.new
+    class << obj
+      def hi
+        "hi"
+      end
+    end
+    assert("hi", obj.hi)
+    assert(false, Object.new.respond_to?(:hi))
+    def make_singleton(o)
+      class << o
+        def bye
+          "bye"
+        end
+      end
+    end
+    make_singleton(obj)
+    assert("bye", obj.bye)
+    "#;
+    assert_script(program);
+}
+
+#[test]
+fn super_call() {
+    let program = r#"
+    class Base
+      def args(a, b = 2, *c, d, e: 5, **f, &g)
+        [a, b, c, d, e, f, g ? g.call : nil]
+      end
+      def none(*a)
+        a
+      end
+      def block
+        yield
+      end
+    end
+    class Derived < Base
+      def args(a, b = 7, *c, d, e: 8, **f, &g)
+        a = a * 10
+        super
+      end
+      def none(x, y)
+        super()
+      end
+      def block
+        super
+      end
+      def explicit(x)
+        pr = Proc.new { x + 1 }
+        [1, 2].map { |i| args(i, 3, &pr) }
+      end
+    end
+    d = Derived.new
+    assert([10, 7, [], 3, 8, {}, nil], d.args(1, 3))
+    assert([10, 2, [3, 4], 5, 6, {z: 7}, 9], d.args(1, 2, 3, 4, 5, e: 6, z: 7) { 9 })
+    assert([], d.none(1, 2))
+    assert(42, d.block { 42 })
+    class Derived
+      def args(a, b, c)
+        [1, 2].map { |i| super(a + i, b, c, &nil) }
+      end
+    end
+    assert([[11, 2, [], 3, 5, {}, nil], [12, 2, [], 3, 5, {}, nil]], d.args(10, 2, 3) { 0 })
+    module Greet
+      def hello
+        "module " + super
+      end
+    end
+    class Person
+      def hello
+        "person"
+      end
+    end
+    class Student < Person
+      include Greet
+      def hello
+        "student " + super
+      end
+    end
+    assert("student module person", Student.new.hello)
+    class Student
+      define_method(:name) { |x| "name " + super(x) }
+      define_method(:zsuper) { |x| super }
+    end
+    class Person
+      def name(x)
+        x.to_s
+      end
+      def zsuper(x)
+        x
+      end
+    end
+    assert("name 3", Student.new.name(3))
+    e = begin
+      Student.new.zsuper(1)
+    rescue RuntimeError => e
+      e
+    end
+    assert(RuntimeError, e.class)
+    class Person
+      def lonely
+        [defined?(super), super]
+      end
+    end
+    e = begin
+      Person.new.lonely
+    rescue NoMethodError => e
+      e
+    end
+    assert(NoMethodError, e.class)
+    "#;
+    assert_script(program);
+}